@@ -0,0 +1,106 @@
+use num_bigint::BigInt;
+
+use crate::{cancel, format, Token, Value};
+
+// Widest bigint an operation may produce, so that `2 ** 10 ** 9` fails
+// quickly instead of exhausting memory
+const MAX_BITS: u64 = 1 << 16;
+
+pub fn parse(digits: &str, radix: u32) -> Option<BigInt> {
+    BigInt::parse_bytes(digits.as_bytes(), radix)
+}
+
+// Machine integers whenever the result fits, so bigints only appear when needed
+pub fn demote(num: BigInt) -> Value {
+    match i128::try_from(&num) {
+        Ok(num) => Value::Int(num),
+        Err(_) => Value::Big(num),
+    }
+}
+
+pub fn to_f64(num: &BigInt) -> f64 {
+    num.to_string().parse().unwrap_or(f64::NAN)
+}
+
+// Whether `a op b` on 128-bit integers would overflow and so should be
+// computed on bigints instead
+pub fn needs_promotion(op: &Token, a: i128, b: i128) -> bool {
+    match op {
+        Token::Plus => a.checked_add(b).is_none(),
+        Token::Minus => a.checked_sub(b).is_none(),
+        Token::Star => a.checked_mul(b).is_none(),
+        Token::StarStar => b >= 0 && u32::try_from(b).ok().and_then(|exp| a.checked_pow(exp)).is_none(),
+        Token::Shl => a != 0 && b >= 0 && (b >= 128 || (a << b) >> b != a),
+        _ => false,
+    }
+}
+
+// Exact integer arithmetic and bitwise operators; negative values behave as
+// infinitely sign-extended two's complement
+// Square and multiply, checking for Ctrl-C between squarings
+fn pow(mut base: BigInt, mut exp: u32) -> Result<BigInt, &'static str> {
+    let mut result = BigInt::from(1);
+    while exp > 0 {
+        cancel::check()?;
+        if exp & 1 == 1 {
+            result *= &base;
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = &base * &base;
+        }
+    }
+    Ok(result)
+}
+
+pub fn op(op: &Token, a: BigInt, b: BigInt) -> Result<Value, &'static str> {
+    let zero = BigInt::from(0);
+    let result = match op {
+        Token::Plus => a + b,
+        Token::Minus => a - b,
+        Token::Star => a * b,
+        Token::Slash | Token::Percent if b == zero => return Err("Division by zero"),
+        Token::Slash => a / b,
+        Token::Percent => a % b,
+        Token::StarStar if b < zero => {
+            return Err("Integer powers need a non-negative exponent; use a float base such as 2.0")
+        }
+        Token::StarStar => {
+            let exp = u32::try_from(&b).ok().filter(|&exp| a.bits().saturating_mul(exp as u64) <= MAX_BITS);
+            pow(a, exp.ok_or("Result exceeds 65536 bits")?)?
+        }
+        Token::Amp => a & b,
+        Token::Pipe => a | b,
+        Token::Caret => a ^ b,
+        Token::Shl | Token::Shr => {
+            let amount = usize::try_from(&b).map_err(|_| "Shift amount must not be negative")?;
+            if *op == Token::Shr {
+                a >> amount
+            } else if a.bits().saturating_add(amount as u64) > MAX_BITS {
+                return Err("Result exceeds 65536 bits");
+            } else {
+                a << amount
+            }
+        }
+        _ => unreachable!(), // Only called for binary operators
+    };
+    Ok(demote(result))
+}
+
+// Integer output bases for values too wide for 128 bits; negative values
+// keep their sign since they have no fixed-width pattern
+pub fn format_base(base: &str, num: &BigInt, style: &format::Style) -> Option<String> {
+    let sign = if num.sign() == num_bigint::Sign::Minus { "-" } else { "" };
+    let magnitude = num.magnitude();
+    Some(match base {
+        "2" => format!("{}b{}", sign, format::group_nibbles(format::zero_pad(magnitude.to_str_radix(2), style), style)),
+        "8" => format!("{}Ox{}", sign, format::zero_pad(magnitude.to_str_radix(8), style)),
+        "10" => format::group_decimal(num.to_string(), style),
+        "16" => format!("{}{}", sign, format::styled_hex(&magnitude.to_str_radix(16), style)),
+        "c" | "rust" | "python" => format::byte_array(base, &magnitude.to_bytes_be(), style)?,
+        name => {
+            let radix = format::radix_name(name)?.parse().ok()?;
+            format!("{}{}#{}", sign, radix, magnitude.to_str_radix(radix).to_uppercase())
+        }
+    })
+}
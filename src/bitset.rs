@@ -0,0 +1,129 @@
+use std::fmt;
+
+// Largest bit index a bitset may hold, to keep typos such as set(1e9) from
+// allocating huge masks
+const MAX_BITS: usize = 1 << 16;
+
+// Set of bit positions of arbitrary width, e.g. a 256-CPU affinity mask.
+// Stored as little-endian 64-bit words with no trailing zero words.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Bitset {
+    words: Vec<u64>,
+}
+
+impl Bitset {
+    fn from_words(mut words: Vec<u64>) -> Bitset {
+        while words.last() == Some(&0) {
+            words.pop();
+        }
+        Bitset { words }
+    }
+
+    pub fn new() -> Bitset {
+        Bitset { words: Vec::new() }
+    }
+
+    pub fn from_int(num: i128) -> Result<Bitset, &'static str> {
+        if num < 0 {
+            return Err("Negative values cannot be used as bitsets");
+        }
+        Ok(Bitset::from_words(vec![num as u64, (num >> 64) as u64]))
+    }
+
+    pub fn insert(&mut self, bit: usize) -> Result<(), &'static str> {
+        if bit >= MAX_BITS {
+            return Err("Bit index exceeds the maximum bitset width");
+        }
+        let word = bit / 64;
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= 1 << (bit % 64);
+        Ok(())
+    }
+
+    fn word(&self, index: usize) -> u64 {
+        self.words.get(index).copied().unwrap_or(0)
+    }
+
+    // Number of bits up to and including the highest set bit
+    fn width(&self) -> usize {
+        match self.words.last() {
+            Some(last) => self.words.len() * 64 - last.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    fn combine(&self, other: &Bitset, f: impl Fn(u64, u64) -> u64) -> Bitset {
+        let len = self.words.len().max(other.words.len());
+        Bitset::from_words((0..len).map(|i| f(self.word(i), other.word(i))).collect())
+    }
+
+    pub fn and(&self, other: &Bitset) -> Bitset {
+        self.combine(other, |a, b| a & b)
+    }
+
+    pub fn or(&self, other: &Bitset) -> Bitset {
+        self.combine(other, |a, b| a | b)
+    }
+
+    pub fn xor(&self, other: &Bitset) -> Bitset {
+        self.combine(other, |a, b| a ^ b)
+    }
+
+    pub fn shl(&self, amount: usize) -> Result<Bitset, &'static str> {
+        if self.words.is_empty() {
+            return Ok(self.clone());
+        }
+        if self.width().saturating_add(amount) > MAX_BITS {
+            return Err("Shift exceeds the maximum bitset width");
+        }
+        let (word_shift, bit_shift) = (amount / 64, amount % 64);
+        let mut words = vec![0; word_shift];
+        let mut carry = 0;
+        for &word in &self.words {
+            words.push(word << bit_shift | carry);
+            carry = if bit_shift == 0 { 0 } else { word >> (64 - bit_shift) };
+        }
+        words.push(carry);
+        Ok(Bitset::from_words(words))
+    }
+
+    pub fn shr(&self, amount: usize) -> Bitset {
+        let (word_shift, bit_shift) = (amount / 64, amount % 64);
+        let words = self.words.get(word_shift..).unwrap_or(&[]);
+        let shifted = (0..words.len())
+            .map(|i| {
+                let high = match words.get(i + 1) {
+                    Some(next) if bit_shift > 0 => next << (64 - bit_shift),
+                    _ => 0,
+                };
+                words[i] >> bit_shift | high
+            })
+            .collect();
+        Bitset::from_words(shifted)
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.words.iter().map(|word| word.count_ones()).sum()
+    }
+}
+
+// Hex in 32-bit groups, most significant first: 0x00000001_000000ff
+impl fmt::Display for Bitset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut groups: Vec<u32> = self
+            .words
+            .iter()
+            .flat_map(|&word| [word as u32, (word >> 32) as u32])
+            .collect();
+        while groups.len() > 1 && groups.last() == Some(&0) {
+            groups.pop();
+        }
+        if groups.is_empty() {
+            groups.push(0);
+        }
+        let groups: Vec<String> = groups.iter().rev().map(|group| format!("{:08x}", group)).collect();
+        write!(f, "0x{}", groups.join("_"))
+    }
+}
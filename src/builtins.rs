@@ -0,0 +1,552 @@
+use std::cmp::Ordering;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::bitset::Bitset;
+use crate::fixed::IntType;
+use crate::ieee::{self, Ieee754, Precision};
+use crate::{cancel, char_literal, checksum, codec, format, hash, parse_char, unicode, Value};
+
+// Names accepted by `call`, offered as completions by the language server
+pub const NAMES: &[&str] = &[
+    "abs", "arg", "re", "im", "conj", "len", "popcount", "set", "divmod", "deinterleave", "minmax",
+    "now", "now_ns", "today", "uptime", "rol", "ror", "ulp", "ulpf",
+    "nextup", "nextdown", "nextupf", "nextdownf", "chr", "ord",
+    "surrogates", "unsurrogate", "text",
+    "bswap16", "bswap32", "bswap64", "bswap128", "htons", "htonl", "ntohs", "ntohl", "brev", "clz", "ctz",
+    "parity", "even_parity", "odd_parity", "bcd", "unbcd",
+    "crc8", "crc8_maxim", "crc16", "crc16_modbus", "crc16_ccitt", "crc16_xmodem", "crc16_kermit", "crc32", "crc32c",
+    "adler32", "fletcher16", "fletcher32", "md5", "sha1", "sha256", "unhex", "file",
+    "q", "uq", "toq", "touq", "q15", "q31", "toq15", "toq31", "zigzag", "unzigzag",
+    #[cfg(feature = "roman")]
+    "roman",
+    #[cfg(feature = "mac")]
+    "oui",
+];
+
+// Functions available in every expression. User definitions with the same
+// name take precedence.
+pub fn call(name: &str, args: &[Value]) -> Option<Result<Value, String>> {
+    let result = match name {
+        "abs" => unary(args, abs),
+        "arg" => unary(args, |value| {
+            let (re, im) = value.as_complex()?;
+            Ok(Value::Float(im.atan2(re)))
+        }),
+        "re" => unary(args, |value| Ok(Value::Float(value.as_complex()?.0))),
+        "im" => unary(args, |value| Ok(Value::Float(value.as_complex()?.1))),
+        "conj" => unary(args, |value| {
+            let (re, im) = value.as_complex()?;
+            Ok(Value::Complex(re, -im))
+        }),
+        "len" => unary(args, |value| match value {
+            Value::List(items) => Ok(Value::Int(items.len() as i128)),
+            Value::Name(name) => Ok(Value::Int(name.chars().count() as i128)),
+            _ => Err("Expected a list"),
+        }),
+        "popcount" => unary(args, |value| Ok(Value::Int(popcount(value)? as i128))),
+        // 1 when an odd number of bits are set; also the bit to append for
+        // even parity, while odd_parity gives the bit for odd parity
+        "parity" | "even_parity" => unary(args, |value| Ok(Value::Int(popcount(value)? as i128 & 1))),
+        "odd_parity" => unary(args, |value| Ok(Value::Int(!popcount(value)? as i128 & 1))),
+        "set" => set(args).map(Value::Bitset).map_err(String::from),
+        "divmod" => binary(args, divmod),
+        "deinterleave" => unary(args, |value| {
+            let bits = value.as_int(&mut Vec::new())? as u128;
+            let (evens, odds) = (compact_bits(bits), compact_bits(bits >> 1));
+            Ok(Value::Tuple(vec![Value::Int(evens as i128), Value::Int(odds as i128)]))
+        }),
+        "minmax" => minmax(args).map_err(String::from),
+        // Epoch times in UTC, for arithmetic such as now() - 0x65a0c3f0
+        "now" => nullary(args, || Ok(Value::Int(since_epoch()?.as_secs() as i128))),
+        "now_ns" => nullary(args, || Ok(Value::Int(since_epoch()?.as_nanos() as i128))),
+        "today" => nullary(args, || {
+            let secs = since_epoch()?.as_secs() as i128;
+            Ok(Value::Int(secs - secs.rem_euclid(86_400)))
+        }),
+        "uptime" => nullary(args, uptime),
+        "bswap16" => unary(args, |value| bswap(value, 16)),
+        "bswap32" => unary(args, |value| bswap(value, 32)),
+        "bswap64" => unary(args, |value| bswap(value, 64)),
+        "bswap128" => unary(args, |value| bswap(value, 128)),
+        // Host to network byte order and back, as in C; both directions are
+        // the same swap
+        "htons" | "ntohs" => unary(args, |value| network_order(value, 16)),
+        "htonl" | "ntohl" => unary(args, |value| network_order(value, 32)),
+        "brev" => reverse_bits(args),
+        // Leading zeros within a width, e.g. clz(1, 32) is 31
+        "clz" => with_width(args).map(|(_, bits, width)| Value::Int((bits.leading_zeros() - (128 - width)) as i128)),
+        // Trailing zeros; zero has as many as its width, so needs one unless
+        // it is fixed-width
+        "ctz" => match args {
+            [value] if !matches!(value, Value::Fixed(..)) => match value.as_int(&mut Vec::new()) {
+                Ok(0) => Err("ctz(0) depends on the width; use ctz(0, width)".to_string()),
+                Ok(num) => Ok(Value::Int(num.trailing_zeros() as i128)),
+                Err(e) => Err(e.to_string()),
+            },
+            _ => with_width(args).map(|(_, bits, width)| Value::Int(bits.trailing_zeros().min(width) as i128)),
+        },
+        // Packed BCD, one decimal digit per nibble: bcd(1234) is 0x1234
+        "bcd" => unary(args, |value| {
+            let num = u128::try_from(value.as_int(&mut Vec::new())?).map_err(|_| "BCD needs a non-negative value")?;
+            if num >= 10u128.pow(32) {
+                return Err("Value has more than the 32 digits BCD fits in 128 bits");
+            }
+            let packed = num.to_string().bytes().fold(0u128, |packed, digit| packed << 4 | (digit - b'0') as u128);
+            Ok(unsigned(packed))
+        }),
+        "unbcd" => unary(args, |value| {
+            let packed = value.as_int(&mut Vec::new())? as u128;
+            let digits = format!("{:x}", packed);
+            if digits.bytes().any(|digit| !digit.is_ascii_digit()) {
+                return Err("Not packed BCD: a nibble is above 9");
+            }
+            Ok(Value::Int(digits.parse().map_err(|_| "Not packed BCD")?))
+        }),
+        // Protobuf's ZigZag mapping of signed to unsigned values, which
+        // interleaves them as 0, -1, 1, -2, ... so small magnitudes stay small
+        "zigzag" => unary(args, |value| {
+            let num = value.as_int(&mut Vec::new())?;
+            Ok(unsigned((num << 1 ^ num >> 127) as u128))
+        }),
+        "unzigzag" => unary(args, |value| {
+            let num = value.as_int(&mut Vec::new())?;
+            if num < 0 && !matches!(value, Value::Fixed(..)) {
+                return Err("ZigZag values are never negative");
+            }
+            Ok(Value::Int((num as u128 >> 1) as i128 ^ -(num & 1)))
+        }),
+        // A Roman numeral as a name, e.g. roman(2024) is MMXXIV, and the
+        // value of one given as a string, so roman("MMXXIV") is 2024
+        #[cfg(feature = "roman")]
+        "roman" => unary(args, |value| match value {
+            Value::Bytes(text) => {
+                let text = std::str::from_utf8(text).map_err(|_| "Expected a Roman numeral")?;
+                Ok(Value::Int(format::roman_value(text).ok_or("Expected a Roman numeral in standard form")?))
+            }
+            value => {
+                let numeral = format::roman_numeral(value.as_int(&mut Vec::new())?);
+                Ok(Value::Name(numeral.ok_or("Roman numerals only cover 1 to 3999")?))
+            }
+        }),
+        // The vendor prefix of a MAC address as a name, e.g. oui(00:1b:63:84:45:e6) is 00:1b:63
+        #[cfg(feature = "mac")]
+        "oui" => unary(args, |value| {
+            let oui = format::oui(value.as_int(&mut Vec::new())?);
+            Ok(Value::Name(oui.ok_or("MAC addresses only cover 0 to 0xffffffffffff")?))
+        }),
+        "rol" => rotate(args, true),
+        "ror" => rotate(args, false),
+        // Representable doubles (ulp) or singles (ulpf) between two values
+        "ulp" => binary(args, |a, b| ulp(Ieee754::double(a.as_float()?), Ieee754::double(b.as_float()?))),
+        "ulpf" => binary(args, |a, b| {
+            ulp(Ieee754::single(a.as_float()? as f32), Ieee754::single(b.as_float()? as f32))
+        }),
+        // Adjacent double or single with its bit pattern, e.g. nextup(1.0) is
+        // (1.0000000000000002, Fx3ff0000000000001)
+        "nextup" => unary(args, |value| Ok(adjacent(Ieee754::double(value.as_float()?.next_up())))),
+        "nextdown" => unary(args, |value| Ok(adjacent(Ieee754::double(value.as_float()?.next_down())))),
+        "nextupf" => unary(args, |value| Ok(adjacent(Ieee754::single((value.as_float()? as f32).next_up())))),
+        "nextdownf" => unary(args, |value| Ok(adjacent(Ieee754::single((value.as_float()? as f32).next_down())))),
+        "chr" => unary(args, |value| {
+            let code = u32::try_from(value.as_int(&mut Vec::new())?).ok().and_then(char::from_u32);
+            Ok(Value::Name(char_literal(code.ok_or("Not a Unicode code point")?)))
+        }),
+        // Character literals are already their code; ord also takes the
+        // result of chr or a one-character name from a table
+        "ord" => unary(args, |value| match value {
+            Value::Name(name) => {
+                let c = parse_char(name).or_else(|| name.chars().next().filter(|_| name.chars().count() == 1));
+                Ok(Value::Int(c.ok_or("Expected a single character")? as i128))
+            }
+            value => Ok(Value::Int(value.as_int(&mut Vec::new())?)),
+        }),
+        // A byte string or an integer's big-endian bytes as an escaped
+        // string literal, e.g. text(0x616263) is "abc"
+        "text" => unary(args, |value| match value {
+            Value::Bytes(bytes) => Ok(Value::Name(codec::escape(bytes))),
+            value => Ok(Value::Name(codec::escape(&codec::int_bytes(value.as_int(&mut Vec::new())?)))),
+        }),
+        // UTF-16 surrogate pairs, e.g. surrogates(U+1F600) is (0xd83d, 0xde00)
+        "surrogates" => unary(args, |value| {
+            let pair = u32::try_from(value.as_int(&mut Vec::new())?).ok().and_then(unicode::surrogate_pair);
+            let (high, low) = pair.ok_or("Expected a code point from U+10000 to U+10FFFF")?;
+            Ok(Value::Tuple(vec![Value::Int(high as i128), Value::Int(low as i128)]))
+        }),
+        "unsurrogate" => binary(args, |high, low| {
+            let unit = |value: &Value| Ok::<_, &'static str>(u16::try_from(value.as_int(&mut Vec::new())?).ok());
+            let code = unit(high)?.zip(unit(low)?).and_then(|(high, low)| unicode::from_surrogates(high, low));
+            Ok(Value::Int(code.ok_or("Expected a high surrogate (D800-DBFF) and a low surrogate (DC00-DFFF)")? as i128))
+        }),
+        // Q-format fixed point: q(x, m, n) reads x's bits as signed Qm.n,
+        // with m integer bits besides the sign and n fraction bits, and
+        // toq(x, m, n) gives the bits for x; uq and touq are unsigned
+        "q" | "uq" | "toq" | "touq" => q_args(name, args),
+        // CRCs of a string or of bytes given one per argument or as a list,
+        // e.g. crc32("123456789") or crc16_modbus(0x01, 0x03)
+        "crc8" | "crc8_maxim" | "crc16" | "crc16_modbus" | "crc16_ccitt" | "crc16_xmodem" | "crc16_kermit" | "crc32"
+        | "crc32c" => {
+            let crc = checksum::CRCS.iter().find(|crc| crc.name == name)?;
+            let sum = byte_args(args).and_then(|data| crc.checksum(&data));
+            sum.map(|sum| Value::Int(sum as i128)).map_err(String::from)
+        }
+        // zlib's checksum and Fletcher's, taking bytes the same way
+        "adler32" | "fletcher16" | "fletcher32" => byte_args(args).map_err(String::from).and_then(|data| {
+            let sum = match name {
+                "adler32" => checksum::adler32(&data),
+                "fletcher16" => checksum::fletcher16(&data).map(u32::from),
+                _ => checksum::fletcher32(&data),
+            };
+            sum.map(|sum| Value::Int(sum as i128)).map_err(String::from)
+        }),
+        // Byte strings to checksum from hex digits or a file, e.g.
+        // adler32(unhex("DEADBEEF")) or fletcher32(file("dump.bin"))
+        "unhex" => unary(args, |value| match value {
+            Value::Bytes(text) => std::str::from_utf8(text)
+                .ok()
+                .and_then(|text| checksum::parse_hex_bytes(text).ok())
+                .map(Value::Bytes)
+                .ok_or("Expected a string of hex byte pairs"),
+            _ => Err("Expected a string of hex byte pairs"),
+        }),
+        "file" => match args {
+            [Value::Bytes(path)] => {
+                let path = String::from_utf8_lossy(path).into_owned();
+                read_file(&path).map(Value::Bytes)
+            }
+            _ => Err(String::from("Expected a path string")),
+        },
+        // Digests as byte strings, taking bytes the same way as the CRCs
+        "md5" | "sha1" | "sha256" => {
+            let data = byte_args(args).map_err(String::from);
+            data.map(|data| Value::Bytes(hash::digest(name, &data).unwrap_or_default()))
+        }
+        // Q0.n shorthands such as q15(0x4000), which is 0.5, and toq15(0.5)
+        name => match (q_shorthand(name), args) {
+            (Some((kind, frac_bits)), [value]) => q_format(kind, value, 0, frac_bits),
+            (Some(_), _) => Err(format!("Expected 1 argument but found {}", args.len())),
+            (None, _) => return None,
+        },
+    };
+    Some(result)
+}
+
+// A u128 result as an integer, keeping values above i128::MAX unsigned
+fn unsigned(num: u128) -> Value {
+    match i128::try_from(num) {
+        Ok(num) => Value::Int(num),
+        Err(_) => Value::Fixed(num as i128, IntType::U128),
+    }
+}
+
+fn q_args(kind: &str, args: &[Value]) -> Result<Value, String> {
+    let mut warnings = Vec::new();
+    match args {
+        [value, int_bits, frac_bits] => {
+            q_format(kind, value, int_bits.as_int(&mut warnings)?, frac_bits.as_int(&mut warnings)?)
+        }
+        _ => Err(format!("Expected x and the integer and fraction bits but found {} arguments", args.len())),
+    }
+}
+
+// The bytes of string arguments and of integer arguments or list items from
+// 0 to 255, in order
+fn byte_args(args: &[Value]) -> Result<Vec<u8>, &'static str> {
+    let mut data = Vec::new();
+    for arg in args {
+        match arg {
+            Value::Bytes(bytes) => data.extend_from_slice(bytes),
+            Value::List(items) => data.extend(byte_args(items)?),
+            value => {
+                let byte = value.as_int(&mut Vec::new())?;
+                data.push(u8::try_from(byte).map_err(|_| "Bytes must be from 0 to 255")?);
+            }
+        }
+    }
+    Ok(data)
+}
+
+// Reads a file for file(path) a block at a time, so Ctrl-C can stop a
+// large one partway
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+    let error = |e: std::io::Error| format!("Cannot read {}: {}", path, e);
+    let mut file = std::fs::File::open(path).map_err(error)?;
+    let (mut data, mut block) = (Vec::new(), vec![0; 1 << 20]);
+    loop {
+        cancel::check()?;
+        match file.read(&mut block).map_err(error)? {
+            0 => return Ok(data),
+            read => data.extend_from_slice(&block[..read]),
+        }
+    }
+}
+
+// Splits a shorthand such as toq15 or uq16 into its long form and the
+// number of fraction bits
+fn q_shorthand(name: &str) -> Option<(&'static str, i128)> {
+    let kinds = [("touq", "touq"), ("toq", "toq"), ("uq", "uq"), ("q", "q")];
+    let (kind, bits) = kinds.iter().find_map(|&(prefix, kind)| Some((kind, name.strip_prefix(prefix)?)))?;
+    if bits.starts_with('0') || !bits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((kind, bits.parse().ok()?))
+}
+
+// Converts between a Qm.n bit pattern and its value; `kind` is q, uq, toq or
+// touq as for the builtins. Patterns take up m + n bits, plus a sign bit
+// unless unsigned.
+fn q_format(kind: &str, value: &Value, int_bits: i128, frac_bits: i128) -> Result<Value, String> {
+    let signed = !kind.ends_with("uq");
+    let width = int_bits + frac_bits + signed as i128;
+    if int_bits < 0 || frac_bits < 0 || !(1..=128).contains(&width) {
+        return Err(format!("Q{}.{} does not fit in 1 to 128 bits", int_bits, frac_bits));
+    }
+    let (width, scale) = (width as u32, 2f64.powi(frac_bits as i32));
+    let mask = u128::MAX >> (128 - width);
+    if kind.starts_with("to") {
+        let scaled = (value.as_float()? * scale).round();
+        let range = match signed {
+            true => -2f64.powi(width as i32 - 1)..2f64.powi(width as i32 - 1),
+            false => 0.0..2f64.powi(width as i32),
+        };
+        if !range.contains(&scaled) {
+            return Err(format!("{} is out of range for Q{}.{}", value, int_bits, frac_bits));
+        }
+        let raw = if scaled < 0.0 { scaled as i128 as u128 } else { scaled as u128 };
+        return Ok(unsigned(raw & mask));
+    }
+    let num = value.as_int(&mut Vec::new())?;
+    let pattern = num as u128 & mask;
+    // Either the bit pattern or a negative value a signed pattern can hold
+    if pattern != num as u128 && !(signed && num >> (width - 1) == -1) {
+        return Err(format!("{} does not fit in {} bits", num, width));
+    }
+    let raw = match signed {
+        true => ((pattern << (128 - width)) as i128 >> (128 - width)) as f64,
+        false => pattern as f64,
+    };
+    Ok(Value::Float(raw / scale))
+}
+
+fn popcount(value: &Value) -> Result<u32, &'static str> {
+    match value {
+        Value::Int(num) => Ok(format::bit_pattern(*num).count_ones()),
+        Value::Bitset(bits) => Ok(bits.count_ones()),
+        Value::Fixed(num, ty) => Ok((*num as u128 & ty.mask()).count_ones()),
+        _ => Err("Expected an integer or bitset"),
+    }
+}
+
+fn nullary(args: &[Value], f: impl Fn() -> Result<Value, &'static str>) -> Result<Value, String> {
+    match args {
+        [] => f().map_err(String::from),
+        _ => Err(format!("Expected no arguments but found {}", args.len())),
+    }
+}
+
+fn unary(args: &[Value], f: impl Fn(&Value) -> Result<Value, &'static str>) -> Result<Value, String> {
+    match args {
+        [value] => f(value).map_err(String::from),
+        _ => Err(format!("Expected 1 argument but found {}", args.len())),
+    }
+}
+
+fn binary(args: &[Value], f: impl Fn(&Value, &Value) -> Result<Value, &'static str>) -> Result<Value, String> {
+    match args {
+        [a, b] => f(a, b).map_err(String::from),
+        _ => Err(format!("Expected 2 arguments but found {}", args.len())),
+    }
+}
+
+fn since_epoch() -> Result<std::time::Duration, &'static str> {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_err(|_| "System clock is before 1970")
+}
+
+// Seconds since boot, from the first field of /proc/uptime
+fn uptime() -> Result<Value, &'static str> {
+    let text = std::fs::read_to_string("/proc/uptime").map_err(|_| "uptime() is not available on this system")?;
+    let secs = text.split_whitespace().next().and_then(|secs| secs.parse().ok());
+    secs.map(Value::Float).ok_or("Unexpected /proc/uptime contents")
+}
+
+// rol(x, n, width) and ror(x, n, width) rotate the low `width` bits of x by
+// n places; fixed-width values such as (u16) x may leave out the width
+fn rotate(args: &[Value], left: bool) -> Result<Value, String> {
+    let mut warnings = Vec::new();
+    let (value, amount, width) = match args {
+        [value @ Value::Fixed(_, ty), amount] => (value, amount, ty.bits() as i128),
+        [value, amount, width] => (value, amount, width.as_int(&mut warnings)?),
+        _ => return Err("Expected x, n and a bit width, or x and n for a fixed-width x".to_string()),
+    };
+    if !(1..=128).contains(&width) {
+        return Err(format!("Bit width must be between 1 and 128 but found {}", width));
+    }
+    let mask = u128::MAX >> (128 - width);
+    let num = value.as_int(&mut warnings)?;
+    let bits = num as u128 & mask;
+    if !matches!(value, Value::Fixed(..)) && bits != num as u128 {
+        return Err(format!("{} does not fit in {} bits", num, width));
+    }
+    let places = amount.as_int(&mut warnings)?.rem_euclid(width) as u32;
+    let places = if left { places } else { (width as u32 - places) % width as u32 };
+    let rotated = match places {
+        0 => bits,
+        places => (bits << places | bits >> (width as u32 - places)) & mask,
+    };
+    Ok(match value {
+        Value::Fixed(_, ty) => Value::Fixed(ty.wrap(rotated as i128), *ty),
+        _ => Value::Int(rotated as i128),
+    })
+}
+
+fn ulp(a: Ieee754, b: Ieee754) -> Result<Value, &'static str> {
+    let distance = ieee::ulp_distance(&a, &b).ok_or("ULP distance is undefined for NaN")?;
+    Ok(Value::Int(distance as i128))
+}
+
+fn adjacent(float: Ieee754) -> Value {
+    let pattern = match float.precision {
+        Precision::Single => format!("fx{:08x}", float.bits()),
+        Precision::Double => format!("Fx{:016x}", float.bits()),
+    };
+    Value::Tuple(vec![Value::Float(float.value), Value::Name(pattern)])
+}
+
+// Reverses the byte order of a `bits`-wide value, e.g. bswap32(0xDEADBEEF)
+// is 0xEFBEADDE
+fn bswap(value: &Value, bits: u32) -> Result<Value, &'static str> {
+    let num = value.as_int(&mut Vec::new())?;
+    let mask = u128::MAX >> (128 - bits);
+    let pattern = num as u128 & mask;
+    if !matches!(value, Value::Fixed(..)) && pattern != num as u128 {
+        return Err("Value does not fit in the swapped width");
+    }
+    let swapped = pattern.swap_bytes() >> (128 - bits);
+    Ok(match value {
+        Value::Fixed(_, ty) => Value::Fixed(ty.wrap(swapped as i128), *ty),
+        _ => Value::Int(swapped as i128),
+    })
+}
+
+// Network byte order is big-endian, so this is a swap only on little-endian
+// hosts; the width is checked either way
+fn network_order(value: &Value, bits: u32) -> Result<Value, &'static str> {
+    let swapped = bswap(value, bits)?;
+    match cfg!(target_endian = "little") {
+        true => Ok(swapped),
+        false => bswap(&swapped, bits),
+    }
+}
+
+// The (x, width) arguments of brev, clz and ctz: x's bits and the width,
+// which fixed-width values such as (u8) x may leave out
+fn with_width(args: &[Value]) -> Result<(&Value, u128, u32), String> {
+    let mut warnings = Vec::new();
+    let (value, width) = match args {
+        [value @ Value::Fixed(_, ty)] => (value, ty.bits() as i128),
+        [value, width] => (value, width.as_int(&mut warnings)?),
+        _ => return Err("Expected x and a bit width, or just x for a fixed-width x".to_string()),
+    };
+    if !(1..=128).contains(&width) {
+        return Err(format!("Bit width must be between 1 and 128 but found {}", width));
+    }
+    let num = value.as_int(&mut warnings)?;
+    let bits = num as u128 & u128::MAX >> (128 - width);
+    if !matches!(value, Value::Fixed(..)) && bits != num as u128 {
+        return Err(format!("{} does not fit in {} bits", num, width));
+    }
+    Ok((value, bits, width as u32))
+}
+
+// brev(x, width) mirrors the low `width` bits of x, so bit 0 trades places
+// with bit width - 1; fixed-width values such as (u8) x may leave out the width
+fn reverse_bits(args: &[Value]) -> Result<Value, String> {
+    let (value, bits, width) = with_width(args)?;
+    let reversed = bits.reverse_bits() >> (128 - width);
+    Ok(match value {
+        Value::Fixed(_, ty) => Value::Fixed(ty.wrap(reversed as i128), *ty),
+        _ => Value::Int(reversed as i128),
+    })
+}
+
+// Floored quotient and remainder, so the remainder takes the divisor's sign
+fn divmod(a: &Value, b: &Value) -> Result<Value, &'static str> {
+    if let (Value::Int(a), Value::Int(b)) = (a, b) {
+        if *b == 0 {
+            return Err("Division by zero");
+        }
+        let (mut quotient, mut remainder) = (a.wrapping_div(*b), a.wrapping_rem(*b));
+        if remainder != 0 && (remainder < 0) != (*b < 0) {
+            quotient -= 1;
+            remainder += b;
+        }
+        return Ok(Value::Tuple(vec![Value::Int(quotient), Value::Int(remainder)]));
+    }
+    let (a, b) = (a.as_float()?, b.as_float()?);
+    if b == 0.0 {
+        return Err("Division by zero");
+    }
+    let quotient = (a / b).floor();
+    Ok(Value::Tuple(vec![Value::Float(quotient), Value::Float(a - quotient * b)]))
+}
+
+// Packs bits 0, 2, 4, ... of `bits` into the low 64 bits
+fn compact_bits(bits: u128) -> u128 {
+    (0..64).fold(0, |packed, i| packed | (bits >> (2 * i) & 1) << i)
+}
+
+// Smallest and largest of the arguments, or of a single list argument
+fn minmax(args: &[Value]) -> Result<Value, &'static str> {
+    let values = match args {
+        [Value::List(items)] => items.as_slice(),
+        _ => args,
+    };
+    let Some(first) = values.first() else {
+        return Err("Expected at least one value");
+    };
+    let (mut min, mut max) = (first, first);
+    for value in values {
+        if value.compare(min)? == Ordering::Less {
+            min = value;
+        }
+        if value.compare(max)? == Ordering::Greater {
+            max = value;
+        }
+    }
+    Ok(Value::Tuple(vec![min.clone(), max.clone()]))
+}
+
+fn abs(value: &Value) -> Result<Value, &'static str> {
+    Ok(match value {
+        Value::Int(num) => Value::Int(num.checked_abs().ok_or("Result exceeds 128 bits")?),
+        Value::Float(float) => Value::Float(float.abs()),
+        Value::Decimal(decimal) => Value::Decimal(decimal.abs()),
+        Value::Complex(re, im) => Value::Float(re.hypot(*im)),
+        Value::Interval(lo, hi) if *lo >= 0.0 => Value::Interval(*lo, *hi),
+        Value::Interval(lo, hi) if *hi <= 0.0 => Value::Interval(-hi, -lo),
+        Value::Interval(lo, hi) => Value::Interval(0.0, hi.max(-lo)),
+        Value::List(items) => Value::List(items.iter().map(abs).collect::<Result<_, _>>()?),
+        Value::Fixed(num, ty) => Value::Fixed(ty.wrap(num.wrapping_abs()), *ty),
+        Value::Tuple(_) => return Err("Expected a number but found a tuple"),
+        Value::Bitset(_) => return Err("Expected a number but found a bitset"),
+        Value::Name(_) => return Err("Expected a number but found a name"),
+        Value::Bytes(_) => return Err("Expected a number but found a byte string"),
+        Value::Duration(nanos) => Value::Duration(nanos.checked_abs().ok_or("Duration out of range")?),
+        #[cfg(feature = "bigint")]
+        Value::Big(num) => Value::Big(num.magnitude().clone().into()),
+    })
+}
+
+// Bitset with the given bit positions set; lists such as ranges are
+// flattened, so set(0..7, 32) sets bits 0 through 7 and bit 32
+fn set(args: &[Value]) -> Result<Bitset, &'static str> {
+    let mut bits = Bitset::new();
+    for arg in args {
+        match arg {
+            Value::Int(bit) => bits.insert(usize::try_from(*bit).map_err(|_| "Bit positions must not be negative")?)?,
+            Value::List(items) => bits = bits.or(&set(items)?),
+            _ => return Err("Bit positions must be integers"),
+        }
+    }
+    Ok(bits)
+}
@@ -0,0 +1,28 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set by Ctrl-C and polled by the evaluator, so an interrupt abandons the
+// current computation instead of exiting the REPL
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+pub fn install_handler() {
+    if let Err(e) = ctrlc::set_handler(request) {
+        tracing::debug!(error = %e, "could not install Ctrl-C handler");
+    }
+}
+
+// Asks the running computation to stop, as Ctrl-C does
+pub fn request() {
+    CANCELLED.store(true, Ordering::Relaxed);
+}
+
+pub fn reset() {
+    CANCELLED.store(false, Ordering::Relaxed);
+}
+
+pub fn check() -> Result<(), &'static str> {
+    if CANCELLED.load(Ordering::Relaxed) {
+        Err("Interrupted")
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{cancel, BaseConversionError};
+
+const MOD_ADLER: u32 = 65521;
+
+// Inputs such as a large file("dump.bin") are summed a block at a time, so
+// Ctrl-C can stop them between blocks. Blocks have an even length, keeping
+// Fletcher-32's words whole.
+const BLOCK: usize = 1 << 16;
+
+pub fn adler32(data: &[u8]) -> Result<u32, &'static str> {
+    let (mut a, mut b) = (1u32, 0u32);
+    for block in data.chunks(BLOCK) {
+        cancel::check()?;
+        for &byte in block {
+            a = (a + byte as u32) % MOD_ADLER;
+            b = (b + a) % MOD_ADLER;
+        }
+    }
+    Ok((b << 16) | a)
+}
+
+pub fn fletcher16(data: &[u8]) -> Result<u16, &'static str> {
+    let (mut sum1, mut sum2) = (0u16, 0u16);
+    for block in data.chunks(BLOCK) {
+        cancel::check()?;
+        for &byte in block {
+            sum1 = (sum1 + byte as u16) % 255;
+            sum2 = (sum2 + sum1) % 255;
+        }
+    }
+    Ok((sum2 << 8) | sum1)
+}
+
+pub fn fletcher32(data: &[u8]) -> Result<u32, &'static str> {
+    // Fletcher-32 sums 16-bit little-endian words; an odd trailing byte
+    // is padded with zero
+    let (mut sum1, mut sum2) = (0u32, 0u32);
+    for block in data.chunks(BLOCK) {
+        cancel::check()?;
+        for word in block.chunks(2) {
+            let value = word[0] as u32 | (*word.get(1).unwrap_or(&0) as u32) << 8;
+            sum1 = (sum1 + value) % 65535;
+            sum2 = (sum2 + sum1) % 65535;
+        }
+    }
+    Ok((sum2 << 16) | sum1)
+}
+
+// A CRC in the usual parameter model: the generator polynomial without its
+// top bit, the starting register, whether bytes go in and the result comes
+// out least significant bit first, and a value xored into the result
+pub struct Crc {
+    pub name: &'static str,
+    pub width: u32,
+    pub poly: u32,
+    pub init: u32,
+    pub reflect: bool,
+    pub xorout: u32,
+}
+
+// Each one's result for "123456789" is in the CRC catalogue as its check
+pub const CRCS: [Crc; 9] = [
+    Crc { name: "crc8", width: 8, poly: 0x07, init: 0, reflect: false, xorout: 0 },
+    Crc { name: "crc8_maxim", width: 8, poly: 0x31, init: 0, reflect: true, xorout: 0 },
+    Crc { name: "crc16", width: 16, poly: 0x8005, init: 0, reflect: true, xorout: 0 },
+    Crc { name: "crc16_modbus", width: 16, poly: 0x8005, init: 0xffff, reflect: true, xorout: 0 },
+    Crc { name: "crc16_ccitt", width: 16, poly: 0x1021, init: 0xffff, reflect: false, xorout: 0 },
+    Crc { name: "crc16_xmodem", width: 16, poly: 0x1021, init: 0, reflect: false, xorout: 0 },
+    Crc { name: "crc16_kermit", width: 16, poly: 0x1021, init: 0, reflect: true, xorout: 0 },
+    Crc { name: "crc32", width: 32, poly: 0x04c11db7, init: 0xffffffff, reflect: true, xorout: 0xffffffff },
+    Crc { name: "crc32c", width: 32, poly: 0x1edc6f41, init: 0xffffffff, reflect: true, xorout: 0xffffffff },
+];
+
+impl Crc {
+    fn reflected(&self, value: u32) -> u32 {
+        value.reverse_bits() >> (32 - self.width)
+    }
+
+    // Bit at a time; the inputs here are short enough not to need a table
+    pub fn checksum(&self, data: &[u8]) -> Result<u32, &'static str> {
+        let top = 1 << (self.width - 1);
+        let mask = u32::MAX >> (32 - self.width);
+        let mut register = self.init;
+        if self.reflect {
+            let (poly, mut register) = (self.reflected(self.poly), self.reflected(register));
+            for block in data.chunks(BLOCK) {
+                cancel::check()?;
+                for &byte in block {
+                    register ^= byte as u32;
+                    for _ in 0..8 {
+                        register = if register & 1 != 0 { register >> 1 ^ poly } else { register >> 1 };
+                    }
+                }
+            }
+            return Ok(register ^ self.xorout);
+        }
+        for block in data.chunks(BLOCK) {
+            cancel::check()?;
+            for &byte in block {
+                register ^= (byte as u32) << (self.width - 8);
+                for _ in 0..8 {
+                    register = if register & top != 0 { register << 1 ^ self.poly } else { register << 1 } & mask;
+                }
+            }
+        }
+        Ok(register ^ self.xorout)
+    }
+}
+
+// Accepts "DEADBEEF", "0xDEADBEEF" or "de ad be ef"
+pub fn parse_hex_bytes(input: &str) -> Result<Vec<u8>, BaseConversionError> {
+    let digits: String = input
+        .strip_prefix("0x")
+        .unwrap_or(input)
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    if !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+        return Err(BaseConversionError::InvalidInputFormat);
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(BaseConversionError::from))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc_check_values() {
+        let checks = [0xf4, 0xa1, 0xbb3d, 0x4b37, 0x29b1, 0x31c3, 0x2189, 0xcbf43926, 0xe3069283];
+        for (crc, check) in CRCS.iter().zip(checks) {
+            assert_eq!(crc.checksum(b"123456789"), Ok(check), "{}", crc.name);
+        }
+        // An empty input leaves the starting register, xored with xorout
+        for crc in &CRCS {
+            assert_eq!(crc.checksum(b""), Ok(crc.init ^ crc.xorout), "{}", crc.name);
+        }
+        assert_eq!(CRCS[7].checksum(&[0; 32]), Ok(0x190a55ad));
+    }
+
+    #[test]
+    fn adler_and_fletcher_sums() {
+        assert_eq!(adler32(b""), Ok(1));
+        assert_eq!(adler32(b"Wikipedia"), Ok(0x11e60398));
+        assert_eq!(adler32(b"abcdef"), Ok(0x081e0256));
+        assert_eq!(fletcher16(b""), Ok(0));
+        assert_eq!(fletcher16(b"abcde"), Ok(0xc8f0));
+        assert_eq!(fletcher16(b"abcdefgh"), Ok(0x0627));
+        // Sums are taken mod 255 and 65535, so all-ones data sums to zero
+        assert_eq!(fletcher16(&[0xff; 4]), Ok(0));
+        assert_eq!(fletcher32(b""), Ok(0));
+        assert_eq!(fletcher32(b"abcde"), Ok(0xf04fc729));
+        assert_eq!(fletcher32(b"abcdef"), Ok(0x56502d2a));
+        assert_eq!(fletcher32(b"abcdefgh"), Ok(0xebe19591));
+        assert_eq!(fletcher32(&[0xff; 4]), Ok(0));
+    }
+
+    #[test]
+    fn hex_byte_strings() {
+        assert_eq!(parse_hex_bytes("DEADbeef").unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(parse_hex_bytes("0xdead").unwrap(), [0xde, 0xad]);
+        assert_eq!(parse_hex_bytes(" de ad\nbe\tef ").unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(parse_hex_bytes("").unwrap(), []);
+        assert!(parse_hex_bytes("abc").is_err());
+        assert!(parse_hex_bytes("zz").is_err());
+        assert!(parse_hex_bytes("0X12").is_err());
+        assert!(parse_hex_bytes("éé").is_err());
+    }
+}
@@ -0,0 +1,25 @@
+use std::process::Command;
+
+// Clipboard readers tried in order; the first one that runs successfully wins
+const PASTE_COMMANDS: &[&[&str]] = &[
+    &["pbpaste"],
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-out"],
+    &["xsel", "--clipboard", "--output"],
+    &["powershell.exe", "-NoProfile", "-Command", "Get-Clipboard"],
+];
+
+// Text on the system clipboard, read through whichever platform tool is
+// installed
+pub fn paste() -> Result<String, String> {
+    for command in PASTE_COMMANDS {
+        let Ok(output) = Command::new(command[0]).args(&command[1..]).output() else {
+            continue;
+        };
+        if output.status.success() {
+            return String::from_utf8(output.stdout).map_err(|_| "Clipboard does not hold text".to_string());
+        }
+        tracing::debug!(tool = command[0], status = %output.status, "clipboard tool failed");
+    }
+    Err("Cannot read the clipboard; install pbpaste, wl-paste, xclip or xsel".to_string())
+}
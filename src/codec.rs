@@ -0,0 +1,285 @@
+// Byte-string encodings for the b64, b32 and b58 subcommands and for
+// "quoted" string literals
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+const BASE32: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+// Bitcoin's alphabet, which leaves out 0, O, I and l
+const BASE58: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Splits bytes into `bits`-bit digits, most significant first, with the
+// last digit filled out with zero bits
+fn pack(bytes: &[u8], alphabet: &[u8], bits: u32) -> String {
+    let (mut encoded, mut buffer, mut count) = (String::new(), 0u32, 0);
+    for &byte in bytes {
+        buffer = buffer << 8 | byte as u32;
+        count += 8;
+        while count >= bits {
+            count -= bits;
+            encoded.push(alphabet[(buffer >> count) as usize] as char);
+            buffer &= (1 << count) - 1;
+        }
+    }
+    if count > 0 {
+        encoded.push(alphabet[(buffer << (bits - count)) as usize] as char);
+    }
+    encoded
+}
+
+// Joins `bits`-bit digit values back into bytes, dropping the fill bits
+fn unpack(values: &[u32], bits: u32) -> Vec<u8> {
+    let (mut bytes, mut buffer, mut count) = (Vec::new(), 0u32, 0);
+    for &value in values {
+        buffer = buffer << bits | value;
+        count += bits;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((buffer >> count) as u8);
+            buffer &= (1 << count) - 1;
+        }
+    }
+    bytes
+}
+
+// Digits with whitespace and trailing padding removed, so that wrapped or
+// unpadded input can be pasted in
+fn digits(input: &str) -> Vec<char> {
+    let digits: Vec<char> = input.chars().filter(|c| !c.is_whitespace()).collect();
+    let end = digits.iter().rposition(|&c| c != '=').map_or(0, |end| end + 1);
+    digits[..end].to_vec()
+}
+
+fn pad(mut encoded: String, multiple: usize) -> String {
+    while !encoded.len().is_multiple_of(multiple) {
+        encoded.push('=');
+    }
+    encoded
+}
+
+// RFC 4648 base64, padded with = unless using the URL-safe alphabet
+pub fn base64_encode(bytes: &[u8], url: bool) -> String {
+    match url {
+        true => pack(bytes, BASE64_URL, 6),
+        false => pad(pack(bytes, BASE64, 6), 4),
+    }
+}
+
+// Either alphabet, with or without padding
+pub fn base64_decode(input: &str) -> Result<Vec<u8>, &'static str> {
+    let digits = digits(input);
+    if digits.len() % 4 == 1 {
+        return Err("Base64 input is truncated");
+    }
+    let values = digits.iter().map(|&c| match c {
+        '+' | '-' => Ok(62),
+        '/' | '_' => Ok(63),
+        c => {
+            let value = BASE64[..62].iter().position(|&digit| digit as char == c);
+            value.map(|i| i as u32).ok_or("Invalid base64 character")
+        }
+    });
+    Ok(unpack(&values.collect::<Result<Vec<_>, _>>()?, 6))
+}
+
+// RFC 4648 base32, padded with = to a multiple of 8 digits
+pub fn base32_encode(bytes: &[u8]) -> String {
+    pad(pack(bytes, BASE32, 5), 8)
+}
+
+// Either case, with or without padding
+pub fn base32_decode(input: &str) -> Result<Vec<u8>, &'static str> {
+    let digits = digits(input);
+    if matches!(digits.len() % 8, 1 | 3 | 6) {
+        return Err("Base32 input is truncated");
+    }
+    let values = digits.iter().map(|c| {
+        let c = c.to_ascii_uppercase();
+        BASE32.iter().position(|&digit| digit as char == c).map(|i| i as u32).ok_or("Invalid base32 character")
+    });
+    Ok(unpack(&values.collect::<Result<Vec<_>, _>>()?, 5))
+}
+
+// Base58 treats the bytes as one big-endian number, with each leading zero
+// byte written as a leading 1
+pub fn base58_encode(bytes: &[u8]) -> String {
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+    // Little-endian base 58 digits of the number, built up a byte at a time
+    let mut digits: Vec<u32> = Vec::new();
+    for &byte in &bytes[zeros..] {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += *digit << 8;
+            *digit = carry % 58;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push(carry % 58);
+            carry /= 58;
+        }
+    }
+    let leading = std::iter::repeat_n('1', zeros);
+    leading.chain(digits.iter().rev().map(|&digit| BASE58[digit as usize] as char)).collect()
+}
+
+pub fn base58_decode(input: &str) -> Result<Vec<u8>, &'static str> {
+    let input = input.trim();
+    let zeros = input.chars().take_while(|&c| c == '1').count();
+    // Little-endian bytes of the number, built up a digit at a time
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in input[zeros..].chars() {
+        let digit = BASE58.iter().position(|&digit| digit as char == c);
+        let mut carry = digit.ok_or("Invalid base58 character")? as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    Ok(std::iter::repeat_n(0, zeros).chain(bytes.into_iter().rev()).collect())
+}
+
+// The bytes of a string literal such as "abc\n" or "\xde\xad", with other
+// characters stored as UTF-8
+pub fn parse_string(input: &str) -> Option<Vec<u8>> {
+    let body = input.strip_prefix('"')?.strip_suffix('"')?;
+    let (mut bytes, mut chars) = (Vec::new(), body.chars());
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '"' => return None,
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                '0' => '\0',
+                c @ ('\\' | '"' | '\'') => c,
+                'x' => {
+                    let digits: String = chars.by_ref().take(2).collect();
+                    bytes.push(u8::from_str_radix(&digits, 16).ok().filter(|_| digits.len() == 2)?);
+                    continue;
+                }
+                'u' => {
+                    let rest = chars.as_str().strip_prefix('{')?;
+                    let (digits, after) = rest.split_once('}')?;
+                    chars = after.chars();
+                    char::from_u32(u32::from_str_radix(digits, 16).ok()?)?
+                }
+                _ => return None,
+            },
+            c => c,
+        };
+        bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+    }
+    Some(bytes)
+}
+
+// A string literal parse_string reads back: printable ASCII as itself and
+// anything else escaped
+pub fn escape(bytes: &[u8]) -> String {
+    let mut text = String::from('"');
+    for &byte in bytes {
+        match byte {
+            b'"' => text.push_str("\\\""),
+            b'\\' => text.push_str("\\\\"),
+            b'\n' => text.push_str("\\n"),
+            b'\r' => text.push_str("\\r"),
+            b'\t' => text.push_str("\\t"),
+            b' '..=b'~' => text.push(byte as char),
+            byte => text.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    text.push('"');
+    text
+}
+
+// Big-endian bytes of a value, without leading zero bytes; negative values
+// use their 64- or 128-bit two's complement pattern
+pub fn int_bytes(value: i128) -> Vec<u8> {
+    pattern_bytes(crate::format::bit_pattern(value))
+}
+
+pub fn pattern_bytes(bits: u128) -> Vec<u8> {
+    let bytes = bits.to_be_bytes();
+    let start = bytes.iter().position(|&byte| byte != 0).unwrap_or(bytes.len() - 1);
+    bytes[start..].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_round_trips() {
+        assert_eq!(base64_encode(b"", false), "");
+        assert_eq!(base64_encode(b"f", false), "Zg==");
+        assert_eq!(base64_encode(b"foob", false), "Zm9vYg==");
+        assert_eq!(base64_encode(b"foobar", false), "Zm9vYmFy");
+        assert_eq!(base64_encode(&[0xfb, 0xff], true), "-_8");
+        assert_eq!(base64_decode("Zm9vYg=="), Ok(b"foob".to_vec()));
+        assert_eq!(base64_decode("Zm9v\nYg"), Ok(b"foob".to_vec()));
+        assert_eq!(base64_decode("-_8"), Ok(vec![0xfb, 0xff]));
+        assert!(base64_decode("Zm9vY").is_err());
+        assert!(base64_decode("Zm9v!").is_err());
+        assert_eq!(base64_decode(""), Ok(vec![]));
+        assert_eq!(base64_decode("===="), Ok(vec![]));
+        assert_eq!(base64_decode("+/8="), base64_decode("-_8"));
+        assert_eq!(base64_encode(&[0xfb, 0xff], false), "+/8=");
+        assert!(base64_decode("Zm=9v").is_err());
+    }
+
+    #[test]
+    fn base32_and_base58_round_trip() {
+        assert_eq!(base32_encode(b"f"), "MY======");
+        assert_eq!(base32_encode(b"foobar"), "MZXW6YTBOI======");
+        assert_eq!(base32_decode("mzxw6ytboi"), Ok(b"foobar".to_vec()));
+        assert!(base32_decode("MZX").is_err());
+        assert_eq!(base58_encode(b"hello world"), "StV1DL6CwTryKyV");
+        assert_eq!(base58_encode(&[0, 0, 1]), "112");
+        assert_eq!(base58_decode("StV1DL6CwTryKyV"), Ok(b"hello world".to_vec()));
+        assert_eq!(base58_decode("112"), Ok(vec![0, 0, 1]));
+        assert!(base58_decode("0OIl").is_err());
+        assert_eq!(base32_encode(b""), "");
+        assert_eq!(base32_decode("MZXW6YTBOI======"), Ok(b"foobar".to_vec()));
+        assert!(base32_decode("MZXW6YT1").is_err());
+        assert_eq!(base58_encode(b""), "");
+        assert_eq!(base58_encode(&[0, 0]), "11");
+        assert_eq!(base58_decode("11"), Ok(vec![0, 0]));
+        assert_eq!(base58_decode(""), Ok(vec![]));
+        assert_eq!(base58_decode(&base58_encode(&[0xff; 20])), Ok(vec![0xff; 20]));
+    }
+
+    #[test]
+    fn string_literals_and_escapes() {
+        assert_eq!(parse_string("\"\""), Some(vec![]));
+        assert_eq!(parse_string("\"a\\tb\""), Some(b"a\tb".to_vec()));
+        assert_eq!(parse_string("\"\\x00\\xFF\""), Some(vec![0, 0xff]));
+        assert_eq!(parse_string("\"\\u{1F600}\""), Some("😀".as_bytes().to_vec()));
+        assert_eq!(parse_string("\"\\\"\\\\\""), Some(b"\"\\".to_vec()));
+        for bad in ["abc", "\"abc", "\"a\"b\"", "\"\\q\"", "\"\\x4\"", "\"\\u{d800}\"", "\"\\u{41\"", "\"\\\""] {
+            assert_eq!(parse_string(bad), None, "{}", bad);
+        }
+        let all: Vec<u8> = (0..=255).collect();
+        assert_eq!(parse_string(&escape(&all)), Some(all));
+        assert_eq!(escape(b"say \"hi\"\r\n"), "\"say \\\"hi\\\"\\r\\n\"");
+        assert_eq!(escape(&[0x7f, 0x80]), "\"\\x7f\\x80\"");
+    }
+
+    #[test]
+    fn bytes_of_integers() {
+        assert_eq!(int_bytes(0), [0]);
+        assert_eq!(int_bytes(0xdead), [0xde, 0xad]);
+        assert_eq!(int_bytes(0x100), [1, 0]);
+        assert_eq!(int_bytes(-1), [0xff; 8]);
+        assert_eq!(int_bytes(i128::MIN), [0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(pattern_bytes(u128::MAX), [0xff; 16]);
+        assert_eq!(hex(&[]), "");
+        assert_eq!(hex(&[0, 0xab]), "00ab");
+    }
+}
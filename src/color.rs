@@ -0,0 +1,133 @@
+// Colors for the color subcommand: #rrggbb hex, rgb(), hsl() and the packed
+// 24-bit integer
+
+use crate::format::{self, Style};
+
+// Packed 0xRRGGBB from #1e90ff, #fff, rgb(30, 144, 255) or
+// hsl(210, 100%, 56%)
+pub fn parse(text: &str) -> Option<u32> {
+    let text = text.trim();
+    if let Some(digits) = text.strip_prefix('#') {
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        return match digits.len() {
+            // Each digit doubled, so #fa0 is #ffaa00
+            3 => u32::from_str_radix(&digits.chars().flat_map(|c| [c, c]).collect::<String>(), 16).ok(),
+            6 => u32::from_str_radix(digits, 16).ok(),
+            _ => None,
+        };
+    }
+    let (function, args) = text.strip_suffix(')')?.split_once('(')?;
+    let args: Vec<&str> = args.split(',').map(str::trim).collect();
+    let [a, b, c] = args.as_slice() else {
+        return None;
+    };
+    match function.trim() {
+        "rgb" => {
+            let channel = |arg: &str| arg.parse::<u8>().ok().map(u32::from);
+            Some(channel(a)? << 16 | channel(b)? << 8 | channel(c)?)
+        }
+        "hsl" => {
+            let percent = |arg: &str| {
+                arg.trim_end_matches('%').parse::<f64>().ok().filter(|percent| (0.0..=100.0).contains(percent))
+            };
+            let hue = a.trim_end_matches("deg").parse::<f64>().ok().filter(|hue| hue.is_finite())?;
+            Some(from_hsl(hue.rem_euclid(360.0), percent(b)? / 100.0, percent(c)? / 100.0))
+        }
+        _ => None,
+    }
+}
+
+fn channels(rgb: u32) -> [u32; 3] {
+    [rgb >> 16 & 0xff, rgb >> 8 & 0xff, rgb & 0xff]
+}
+
+// Hue in degrees with saturation and lightness from 0 to 1
+fn to_hsl(rgb: u32) -> (f64, f64, f64) {
+    let [r, g, b] = channels(rgb).map(|channel| channel as f64 / 255.0);
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = match max {
+        _ if max == r => (g - b) / delta % 6.0,
+        _ if max == g => (b - r) / delta + 2.0,
+        _ => (r - g) / delta + 4.0,
+    };
+    ((hue * 60.0).rem_euclid(360.0), saturation, lightness)
+}
+
+fn from_hsl(hue: f64, saturation: f64, lightness: f64) -> u32 {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - (hue / 60.0 % 2.0 - 1.0).abs());
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |value: f64| ((value + m) * 255.0).round().clamp(0.0, 255.0) as u32;
+    channel(r) << 16 | channel(g) << 8 | channel(b)
+}
+
+// The color in each notation, one per line
+pub fn describe(rgb: u32, style: &Style) -> Vec<String> {
+    let [r, g, b] = channels(rgb);
+    let (hue, saturation, lightness) = to_hsl(rgb);
+    vec![
+        format!("  hex  #{:06x}", rgb),
+        format!("  rgb  rgb({}, {}, {})", r, g, b),
+        format!("  hsl  hsl({}, {:.0}%, {:.0}%)", hue.round() as u32 % 360, saturation * 100.0, lightness * 100.0),
+        format!("  int  {} ({})", rgb, format::hex(rgb as i128, style)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse("#1e90ff"), Some(0x1e90ff));
+        assert_eq!(parse("#fa0"), Some(0xffaa00));
+        assert_eq!(parse("rgb(30, 144, 255)"), Some(0x1e90ff));
+        assert_eq!(parse("hsl(210, 100%, 56%)"), Some(0x1f8fff));
+        assert_eq!(parse("hsl(0, 0%, 100%)"), Some(0xffffff));
+        assert_eq!(parse("rgb(256, 0, 0)"), None);
+        assert_eq!(parse("#12345"), None);
+        assert_eq!(parse("  #FFF "), Some(0xffffff));
+        assert_eq!(parse("#ggg"), None);
+        assert_eq!(parse("#+fff"), None);
+        assert_eq!(parse("rgb(255,255,255)"), Some(0xffffff));
+        assert_eq!(parse("rgb(0, 0)"), None);
+        assert_eq!(parse("rgb(-1, 0, 0)"), None);
+        assert_eq!(parse("cmyk(0, 0, 0)"), None);
+        // Hues wrap around the circle
+        assert_eq!(parse("hsl(210, 100%, 50%)"), Some(0x0080ff));
+        assert_eq!(parse("hsl(570, 100%, 50%)"), Some(0x0080ff));
+        assert_eq!(parse("hsl(-150deg, 100%, 50%)"), Some(0x0080ff));
+        assert_eq!(parse("hsl(0, 101%, 50%)"), None);
+        assert_eq!(parse("hsl(inf, 100%, 50%)"), None);
+        assert_eq!(
+            describe(0x1e90ff, &Style::default()),
+            ["  hex  #1e90ff", "  rgb  rgb(30, 144, 255)", "  hsl  hsl(210, 100%, 56%)", "  int  2003199 (0x1e90ff)"]
+        );
+    }
+
+    #[test]
+    fn grays_have_no_hue() {
+        let describe = |rgb| describe(rgb, &Style::default());
+        assert_eq!(describe(0)[2], "  hsl  hsl(0, 0%, 0%)");
+        assert_eq!(describe(0xffffff)[2], "  hsl  hsl(0, 0%, 100%)");
+        assert_eq!(describe(0x808080)[2], "  hsl  hsl(0, 0%, 50%)");
+        assert_eq!(describe(0xff0000)[2], "  hsl  hsl(0, 100%, 50%)");
+        assert_eq!(describe(0xff00ff)[2], "  hsl  hsl(300, 100%, 50%)");
+    }
+}
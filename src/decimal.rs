@@ -0,0 +1,156 @@
+use std::fmt;
+
+// Digits kept after the point when a quotient does not terminate
+const DIVISION_SCALE: u32 = 28;
+
+// Exact base-10 number: mantissa / 10^scale. Used by `:set numerics decimal`
+// so that values like 0.1 are stored as written rather than in binary.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+fn pow10(exp: u32) -> Option<i128> {
+    10i128.checked_pow(exp)
+}
+
+impl Decimal {
+    pub fn from_int(num: i128) -> Decimal {
+        Decimal { mantissa: num, scale: 0 }
+    }
+
+    // Accepts "12", "0.125" and "1.5e-3"
+    pub fn parse(literal: &str) -> Option<Decimal> {
+        let (mantissa, exponent) = match literal.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().ok()?),
+            None => (literal, 0),
+        };
+        let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = format!("{}{}", int_part, frac_part);
+        let mut value = Decimal { mantissa: digits.parse().ok()?, scale: frac_part.len() as u32 };
+        if exponent >= 0 {
+            value.mantissa = value.mantissa.checked_mul(pow10(exponent as u32)?)?;
+        } else {
+            value.scale = value.scale.checked_add(exponent.unsigned_abs())?;
+        }
+        Some(value.normalize())
+    }
+
+    // Drops trailing zeros after the point
+    fn normalize(mut self) -> Decimal {
+        while self.scale > 0 && self.mantissa % 10 == 0 {
+            self.mantissa /= 10;
+            self.scale -= 1;
+        }
+        self
+    }
+
+    fn rescale(self, scale: u32) -> Option<i128> {
+        self.mantissa.checked_mul(pow10(scale - self.scale)?)
+    }
+
+    // Both mantissas at a common scale
+    fn align(self, other: Decimal) -> Option<(i128, i128, u32)> {
+        let scale = self.scale.max(other.scale);
+        Some((self.rescale(scale)?, other.rescale(scale)?, scale))
+    }
+
+    pub fn checked_add(self, other: Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.align(other)?;
+        Some(Decimal { mantissa: a.checked_add(b)?, scale }.normalize())
+    }
+
+    pub fn checked_sub(self, other: Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.align(other)?;
+        Some(Decimal { mantissa: a.checked_sub(b)?, scale }.normalize())
+    }
+
+    pub fn checked_mul(self, other: Decimal) -> Option<Decimal> {
+        let mantissa = self.mantissa.checked_mul(other.mantissa)?;
+        Some(Decimal { mantissa, scale: self.scale + other.scale }.normalize())
+    }
+
+    // Rounds half away from zero once DIVISION_SCALE digits are reached,
+    // using fewer digits if the intermediate product would overflow
+    pub fn checked_div(self, other: Decimal) -> Option<Decimal> {
+        if other.mantissa == 0 {
+            return None;
+        }
+        let denominator = other.mantissa.checked_mul(pow10(self.scale)?)?;
+        for scale in (0..=DIVISION_SCALE).rev() {
+            let Some(numerator) = pow10(other.scale + scale).and_then(|p| self.mantissa.checked_mul(p)) else {
+                continue;
+            };
+            let (quotient, remainder) = (numerator / denominator, numerator % denominator);
+            let round = if remainder.unsigned_abs() * 2 >= denominator.unsigned_abs() {
+                numerator.signum() * denominator.signum()
+            } else {
+                0
+            };
+            return Some(Decimal { mantissa: quotient + round, scale }.normalize());
+        }
+        None
+    }
+
+    // Repeated squaring, so huge exponents of 0 and 1 stay cheap
+    pub fn checked_pow(self, mut exp: u64) -> Option<Decimal> {
+        let (mut base, mut result) = (self, Decimal::from_int(1));
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.checked_mul(base)?;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = base.checked_mul(base)?;
+            }
+        }
+        Some(result)
+    }
+
+    // Remainder with the sign of self, as with `%` on integers
+    pub fn checked_rem(self, other: Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.align(other)?;
+        Some(Decimal { mantissa: a.checked_rem(b)?, scale }.normalize())
+    }
+
+    // Remainder in [0, modulus) for a positive modulus
+    pub fn rem_euclid(self, modulus: Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.align(modulus)?;
+        Some(Decimal { mantissa: a.checked_rem_euclid(b)?, scale }.normalize())
+    }
+
+    pub fn abs(self) -> Decimal {
+        Decimal { mantissa: self.mantissa.abs(), scale: self.scale }
+    }
+
+    pub fn signum(&self) -> std::cmp::Ordering {
+        self.mantissa.cmp(&0)
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.scale == 0
+    }
+
+    pub fn trunc(&self) -> i128 {
+        self.mantissa / pow10(self.scale).unwrap_or(i128::MAX)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (int_part, frac_part) = digits.split_at(digits.len() - scale);
+        write!(f, "{}{}.{}", sign, int_part, frac_part)
+    }
+}
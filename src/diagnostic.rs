@@ -0,0 +1,62 @@
+use std::ops::Range;
+
+use crate::highlight::{self, Stream};
+use crate::settings::Settings;
+
+// Byte range of a token within the input line
+pub type Span = Range<usize>;
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        Diagnostic { message: message.into(), span }
+    }
+
+    pub fn render(&self, input: &str, settings: &Settings) -> String {
+        render_all(input, std::slice::from_ref(self), settings)
+    }
+}
+
+// Echoes the input once followed by each diagnostic's span underlined, e.g.
+//
+//   3 + 12q * 4zz
+//       ^^^ Could not parse number 12q
+//             ^^^ Could not parse number 4zz
+pub fn render_all(input: &str, diagnostics: &[Diagnostic], settings: &Settings) -> String {
+    render_all_for(Stream::Stdout, input, diagnostics, settings)
+}
+
+// As render_all, highlighting the echoed input for `stream`
+pub fn render_all_for(stream: Stream, input: &str, diagnostics: &[Diagnostic], settings: &Settings) -> String {
+    let mut rendered = highlight::highlight_for(stream, input, settings);
+    for diagnostic in diagnostics {
+        let start = diagnostic.span.start.min(input.len());
+        let end = diagnostic.span.end.clamp(start, input.len());
+        let column = input[..start].chars().count();
+        let width = input[start..end].chars().count().max(1);
+        rendered.push_str(&format!("\n{}{} {}", " ".repeat(column), "^".repeat(width), diagnostic.message));
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carets_underline_each_span() {
+        let settings = Settings::default();
+        let diagnostics = [Diagnostic::new("first", 4..7), Diagnostic::new("second", 10..13)];
+        assert_eq!(render_all("3 + 12q * 4zz", &diagnostics, &settings), "3 + 12q * 4zz\n    ^^^ first\n          ^^^ second");
+        // Columns count characters, not bytes
+        assert_eq!(Diagnostic::new("here", 5..6).render("π + x", &settings), "π + x\n    ^ here");
+        // Empty spans and spans past the end still point somewhere
+        assert_eq!(Diagnostic::new("end", 3..3).render("1 +", &settings), "1 +\n   ^ end");
+        assert_eq!(Diagnostic::new("past", 9..12).render("1 +", &settings), "1 +\n   ^ past");
+    }
+}
@@ -0,0 +1,81 @@
+// Side-by-side binary of two values for the diff subcommand, with a row of
+// markers under the bits that differ
+
+use crate::format;
+use crate::highlight::{self, Stream};
+
+// Bold red for differing bits when color is on
+const CHANGED: &str = "1;31";
+
+// Rows for each value and the markers, padded to a whole number of bytes
+// and split into nibbles, then a count of the differing bits
+pub fn render(labels: [&str; 2], values: [i128; 2], style: &format::Style) -> Vec<String> {
+    let patterns = values.map(format::bit_pattern);
+    let used = patterns.iter().map(|pattern| 128 - pattern.leading_zeros()).max().unwrap_or(0);
+    let width = used.max(1).div_ceil(8) * 8;
+    let differing = patterns[0] ^ patterns[1];
+    let label_width = labels.iter().map(|label| label.chars().count()).max().unwrap_or(0);
+    let row = |cell: &dyn Fn(u32) -> String| {
+        let mut row = String::new();
+        for bit in (0..width).rev() {
+            if bit % 4 == 3 && bit != width - 1 {
+                row.push(' ');
+            }
+            row.push_str(&cell(bit));
+        }
+        row
+    };
+    let digits = |pattern: u128| {
+        row(&|bit| {
+            let digit = if pattern >> bit & 1 == 1 { "1" } else { "0" };
+            match differing >> bit & 1 == 1 && highlight::enabled(Stream::Stdout) {
+                true => format!("\x1b[{}m{}\x1b[0m", CHANGED, digit),
+                false => digit.to_string(),
+            }
+        })
+    };
+    let markers = row(&|bit| if differing >> bit & 1 == 1 { "^" } else { " " }.to_string());
+    let count = differing.count_ones();
+    vec![
+        format!("  {:<label_width$}  {}", labels[0], digits(patterns[0])),
+        format!("  {:<label_width$}  {}", labels[1], digits(patterns[1])),
+        format!("  {:<label_width$}  {}", "", markers).trim_end().to_string(),
+        format!("  {} of {} bits differ (xor {})", count, width, format::hex(values[0] ^ values[1], style)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_diff() {
+        let render = |labels, values| render(labels, values, &format::Style::default());
+        assert_eq!(
+            render(["0xDEAD", "0xBEEF"], [0xdead, 0xbeef]),
+            [
+                "  0xDEAD  1101 1110 1010 1101",
+                "  0xBEEF  1011 1110 1110 1111",
+                "           ^^        ^     ^",
+                "  4 of 16 bits differ (xor 0x6042)",
+            ]
+        );
+        assert_eq!(render(["1", "1"], [1, 1])[3], "  0 of 8 bits differ (xor 0x0)");
+    }
+
+    #[test]
+    fn negative_and_unequal_widths() {
+        let render = |labels, values| render(labels, values, &format::Style::default());
+        let rows = render(["-1", "0"], [-1, 0]);
+        assert_eq!(rows[3], "  64 of 64 bits differ (xor 0xffffffffffffffff)");
+        assert!(rows[0].ends_with(&["1111"; 16].join(" ")));
+        // The narrower value is padded to the wider one's bytes, and the
+        // labels to the longer label
+        let rows = render(["0x1", "0x100"], [1, 0x100]);
+        assert_eq!(rows[0], "  0x1    0000 0000 0000 0001");
+        assert_eq!(rows[1], "  0x100  0000 0001 0000 0000");
+        assert_eq!(rows[2], "                 ^         ^");
+        assert_eq!(render(["0", "0"], [0, 0]), ["  0  0000 0000", "  0  0000 0000", "", "  0 of 8 bits differ (xor 0x0)"]);
+        assert_eq!(render(["a", "b"], [1 << 100, 0])[3], "  1 of 104 bits differ (xor 0x10000000000000000000000000)");
+    }
+}
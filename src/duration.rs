@@ -0,0 +1,149 @@
+// Duration literals such as 1h30m or 500ms, held as a count of nanoseconds
+
+use crate::format;
+
+// Longest first, so that ms is not read as m followed by s
+const UNITS: [(&str, u128); 7] = [
+    ("ns", 1),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("ms", 1_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+];
+
+const SECOND: u128 = 1_000_000_000;
+
+// Nanoseconds in a run of amounts and units such as 1h30m, 45s or 1.5us;
+// fractions finer than a nanosecond are dropped
+pub fn parse(word: &str) -> Option<i128> {
+    let mut rest = word;
+    let mut total: u128 = 0;
+    while !rest.is_empty() {
+        let end = rest.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let (amount, after) = rest.split_at(end);
+        let (unit, per) = UNITS.iter().find(|(unit, _)| after.starts_with(unit))?;
+        let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+        if whole.is_empty() || fraction.contains('.') || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let mut nanos = whole.parse::<u128>().ok()?.checked_mul(*per)?;
+        let (mut scale, mut part) = (*per, 0);
+        for digit in fraction.bytes() {
+            if scale == 1 {
+                break;
+            }
+            scale /= 10;
+            part += (digit - b'0') as u128 * scale;
+        }
+        nanos = nanos.checked_add(part)?;
+        total = total.checked_add(nanos)?;
+        rest = &after[unit.len()..];
+    }
+    i128::try_from(total).ok().filter(|_| !word.is_empty())
+}
+
+// A count of `per` units, with as many fraction digits as it needs
+fn decimal(nanos: u128, per: u128) -> String {
+    let (whole, mut fraction) = (nanos / per, nanos % per);
+    if fraction == 0 {
+        return whole.to_string();
+    }
+    let mut digits = String::new();
+    let mut scale = per;
+    while fraction > 0 {
+        scale /= 10;
+        digits.push(char::from(b'0' + (fraction / scale) as u8));
+        fraction %= scale;
+    }
+    format!("{}.{}", whole, digits)
+}
+
+// Hours, minutes and seconds such as 1h30m44.5s, or a single smaller unit
+// for spans under a second; parse reads it back
+pub fn human(nanos: i128) -> String {
+    let sign = if nanos < 0 { "-" } else { "" };
+    let nanos = nanos.unsigned_abs();
+    let text = match nanos {
+        0 => "0s".to_string(),
+        1..1_000 => format!("{}ns", nanos),
+        1_000..1_000_000 => format!("{}us", decimal(nanos, 1_000)),
+        1_000_000..SECOND => format!("{}ms", decimal(nanos, 1_000_000)),
+        _ => {
+            let (hours, minutes, rest) = (nanos / (3600 * SECOND), nanos / (60 * SECOND) % 60, nanos % (60 * SECOND));
+            let mut text = String::new();
+            if hours > 0 {
+                text += &format!("{}h", hours);
+            }
+            if minutes > 0 {
+                text += &format!("{}m", minutes);
+            }
+            if rest > 0 {
+                text += &format!("{}s", decimal(rest, SECOND));
+            }
+            text
+        }
+    };
+    format!("{}{}", sign, text)
+}
+
+// The human form followed by the total in seconds and in milliseconds, each
+// also in hex when whole, e.g. 1m30s = 90 s (0x5a) = 90000 ms (0x15f90)
+pub fn summary(nanos: i128, style: &format::Style) -> String {
+    let total = |per: i128, unit: &str| {
+        let text = format!("{}{} {}", if nanos < 0 { "-" } else { "" }, decimal(nanos.unsigned_abs(), per as u128), unit);
+        match nanos % per {
+            0 => format!("{} ({})", text, format::hex(nanos / per, style)),
+            _ => text,
+        }
+    };
+    format!("{} = {} = {}", human(nanos), total(1_000_000_000, "s"), total(1_000_000, "ms"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_runs_of_units() {
+        assert_eq!(parse("1h30m"), Some(5_400_000_000_000));
+        assert_eq!(parse("1.5us"), Some(1500));
+        assert_eq!(parse("500ms"), Some(500_000_000));
+        assert_eq!(parse("2µs"), Some(2000));
+        assert_eq!(parse("1ns1us1ms"), Some(1_001_001));
+        assert_eq!(parse("0s"), Some(0));
+        // Fractions finer than a nanosecond are dropped
+        assert_eq!(parse("1.0000000019s"), Some(1_000_000_001));
+        assert_eq!(parse("0.5ns"), Some(0));
+        for bad in ["", "1", "h", "1h30", ".5s", "1.5.2s", "1x", "-1s", "1 s", "1e3s"] {
+            assert_eq!(parse(bad), None, "{}", bad);
+        }
+        assert_eq!(parse("999999999999999999999999999999h"), None);
+        assert_eq!(parse("170141183460469231731687303715884105728ns"), None);
+    }
+
+    #[test]
+    fn human_form_reads_back() {
+        assert_eq!(human(0), "0s");
+        assert_eq!(human(999), "999ns");
+        assert_eq!(human(1500), "1.5us");
+        assert_eq!(human(-1), "-1ns");
+        assert_eq!(human(3_600_000_000_000), "1h");
+        assert_eq!(human(3_600_500_000_000), "1h0.5s");
+        assert_eq!(human(61_000_000_001), "1m1.000000001s");
+        for nanos in [1, 999_999, 1_000_000_001, 5_444_500_000_000, i64::MAX as i128] {
+            assert_eq!(parse(&human(nanos)), Some(nanos));
+        }
+    }
+
+    #[test]
+    fn summaries_in_seconds_and_milliseconds() {
+        let summary = |nanos| summary(nanos, &format::Style::default());
+        assert_eq!(summary(90_000_000_000), "1m30s = 90 s (0x5a) = 90000 ms (0x15f90)");
+        assert_eq!(summary(5_444_500_000_000), "1h30m44.5s = 5444.5 s = 5444500 ms (0x531394)");
+        assert_eq!(summary(0), "0s = 0 s (0x0) = 0 ms (0x0)");
+        assert_eq!(summary(1), "1ns = 0.000000001 s = 0.000001 ms");
+        assert_eq!(summary(-1_500_000_000), "-1.5s = -1.5 s = -1500 ms (0xfffffffffffffa24)");
+    }
+}
@@ -0,0 +1,164 @@
+use clap::ValueEnum;
+use serde_json::Value as Json;
+
+use raxrs::settings::Settings;
+use raxrs::{format_base, Value};
+
+use crate::convert_input;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Aggregate {
+    Sum,
+    Min,
+    Max,
+    Mean,
+    Count,
+}
+
+#[derive(Debug)]
+enum Step {
+    Key(String),
+    Index(usize),
+    // `[]` or `[*]`: every element of an array
+    Each,
+}
+
+// Paths such as .latency_ns, .spans[0].duration or .events[].size
+fn parse_path(path: &str) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    let mut rest = path.strip_prefix('.').unwrap_or(path);
+    while !rest.is_empty() {
+        if let Some(inner) = rest.strip_prefix('[') {
+            let (index, after) = inner.split_once(']').ok_or_else(|| format!("Unclosed [ in path {}", path))?;
+            steps.push(match index {
+                "" | "*" => Step::Each,
+                index => Step::Index(index.parse().map_err(|_| format!("Invalid array index {} in path {}", index, path))?),
+            });
+            rest = after.strip_prefix('.').unwrap_or(after);
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(format!("Empty field name in path {}", path));
+            }
+            steps.push(Step::Key(rest[..end].to_string()));
+            rest = &rest[end..];
+            rest = rest.strip_prefix('.').unwrap_or(rest);
+        }
+    }
+    Ok(steps)
+}
+
+fn select<'a>(json: &'a Json, steps: &[Step], found: &mut Vec<&'a Json>) {
+    let Some((step, rest)) = steps.split_first() else {
+        found.push(json);
+        return;
+    };
+    match (step, json) {
+        (Step::Key(key), Json::Object(fields)) => {
+            if let Some(field) = fields.get(key) {
+                select(field, rest, found);
+            }
+        }
+        (Step::Index(index), Json::Array(items)) => {
+            if let Some(item) = items.get(*index) {
+                select(item, rest, found);
+            }
+        }
+        (Step::Each, Json::Array(items)) => items.iter().for_each(|item| select(item, rest, found)),
+        _ => {}
+    }
+}
+
+// JSON numbers, and strings holding literals such as "0x1f"
+fn to_value(json: &Json, settings: &Settings) -> Option<Value> {
+    match json {
+        Json::Number(number) => match (number.as_i64(), number.as_u64()) {
+            (Some(num), _) => Some(Value::Int(num as i128)),
+            (_, Some(num)) => Some(Value::Int(num as i128)),
+            _ => number.as_f64().map(Value::Float),
+        },
+        Json::String(text) => convert_input(text, settings).ok().map(|(num, _)| Value::Int(num)),
+        _ => None,
+    }
+}
+
+fn aggregate(values: &[Value], aggregate: Aggregate) -> Result<Value, &'static str> {
+    let all_ints: Option<Vec<i128>> = values
+        .iter()
+        .map(|value| match value {
+            Value::Int(num) => Some(*num),
+            _ => None,
+        })
+        .collect();
+    let floats: Vec<f64> = values.iter().map(Value::as_float).collect::<Result<_, _>>()?;
+    Ok(match (aggregate, all_ints) {
+        (Aggregate::Count, _) => Value::Int(values.len() as i128),
+        (Aggregate::Sum, Some(nums)) => {
+            Value::Int(nums.iter().try_fold(0i128, |sum, num| sum.checked_add(*num)).ok_or("Result exceeds 128 bits")?)
+        }
+        (Aggregate::Min, Some(nums)) => Value::Int(nums.into_iter().min().unwrap_or_default()),
+        (Aggregate::Max, Some(nums)) => Value::Int(nums.into_iter().max().unwrap_or_default()),
+        (Aggregate::Sum, None) => Value::Float(floats.iter().sum()),
+        (Aggregate::Min, None) => Value::Float(floats.into_iter().fold(f64::INFINITY, f64::min)),
+        (Aggregate::Max, None) => Value::Float(floats.into_iter().fold(f64::NEG_INFINITY, f64::max)),
+        (Aggregate::Mean, _) => Value::Float(floats.iter().sum::<f64>() / floats.len() as f64),
+    })
+}
+
+fn print_value(value: &Value, base: Option<&str>, settings: &Settings) {
+    let Some(base) = base else {
+        println!("{}", value);
+        return;
+    };
+    let mut warnings = Vec::new();
+    match value.as_int(&mut warnings) {
+        Ok(num) => {
+            for warning in warnings {
+                eprintln!("warning: {}", warning);
+            }
+            println!("{}", format_base(base, num, &value.to_string(), settings));
+        }
+        Err(message) => println!("Error: {}", message),
+    }
+}
+
+// Prints every number found at `path` in `text`, a JSON document or one
+// document per line, or a single aggregate of them
+pub fn run(path: &str, text: &str, base: Option<&str>, how: Option<Aggregate>, settings: &Settings) {
+    let steps = match parse_path(path) {
+        Ok(steps) => steps,
+        Err(message) => {
+            println!("Error: {}", message);
+            return;
+        }
+    };
+    let mut values = Vec::new();
+    for (number, document) in serde_json::Deserializer::from_str(text).into_iter::<Json>().enumerate() {
+        let document = match document {
+            Ok(document) => document,
+            Err(e) => {
+                println!("Error: Invalid JSON: {}", e);
+                return;
+            }
+        };
+        let mut found = Vec::new();
+        select(&document, &steps, &mut found);
+        for json in found {
+            match to_value(json, settings) {
+                Some(value) if how.is_some() => values.push(value),
+                Some(value) => print_value(&value, base, settings),
+                None => eprintln!("warning: {} in document {} is not a number: {}", path, number + 1, json),
+            }
+        }
+    }
+    if let Some(how) = how {
+        if values.is_empty() {
+            println!("Error: No numbers found at {}", path);
+            return;
+        }
+        match aggregate(&values, how) {
+            Ok(value) => print_value(&value, base, settings),
+            Err(message) => println!("Error: {}", message),
+        }
+    }
+}
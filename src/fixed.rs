@@ -0,0 +1,81 @@
+use std::fmt;
+
+// Integer type of an arbitrary width such as u13 or i7, declared with a
+// `(u13) value` cast. Arithmetic on these values wraps to the width.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntType {
+    bits: u32,
+    signed: bool,
+}
+
+impl IntType {
+    pub const U64: IntType = IntType { bits: 64, signed: false };
+    pub const U128: IntType = IntType { bits: 128, signed: false };
+
+    // "u1" through "u128" and "i1" through "i128"
+    pub fn parse(name: &str) -> Option<IntType> {
+        let (signed, bits) = match name.split_at_checked(1)? {
+            ("u", bits) => (false, bits),
+            ("i", bits) => (true, bits),
+            _ => return None,
+        };
+        if bits.starts_with('0') || !bits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let bits = bits.parse().ok().filter(|bits| (1..=128).contains(bits))?;
+        Some(IntType { bits, signed })
+    }
+
+    // Type of a result mixing both operands: the wider one, unsigned on a tie
+    pub fn common(self, other: IntType) -> IntType {
+        match self.bits.cmp(&other.bits) {
+            std::cmp::Ordering::Less => other,
+            std::cmp::Ordering::Greater => self,
+            std::cmp::Ordering::Equal => IntType { bits: self.bits, signed: self.signed && other.signed },
+        }
+    }
+
+    pub fn bits(self) -> u32 {
+        self.bits
+    }
+
+    pub fn is_signed(self) -> bool {
+        self.signed
+    }
+
+    // Same width with the other interpretation, e.g. i16 to u16
+    pub fn with_signed(self, signed: bool) -> IntType {
+        IntType { bits: self.bits, signed }
+    }
+
+    pub fn mask(self) -> u128 {
+        u128::MAX >> (128 - self.bits)
+    }
+
+    // Keeps the low `bits` bits, sign-extending for signed types
+    pub fn wrap(self, num: i128) -> i128 {
+        let raw = num as u128 & self.mask();
+        let sign_bit = 1 << (self.bits - 1);
+        if self.signed && raw & sign_bit != 0 {
+            (raw | !self.mask()) as i128
+        } else {
+            raw as i128
+        }
+    }
+
+    // Decimal rendering of a wrapped value; u128 values above i128::MAX are
+    // stored with the sign bit set
+    pub fn display(self, num: i128) -> String {
+        if self.signed {
+            num.to_string()
+        } else {
+            (num as u128).to_string()
+        }
+    }
+}
+
+impl fmt::Display for IntType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", if self.signed { 'i' } else { 'u' }, self.bits)
+    }
+}
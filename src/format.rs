@@ -0,0 +1,610 @@
+use std::sync::{Arc, LazyLock};
+
+use clap::ValueEnum;
+use tracing::debug;
+
+use crate::fixed::IntType;
+use crate::BaseConversionError;
+
+mod alphabet;
+#[cfg(feature = "gray")]
+mod gray;
+#[cfg(feature = "ip")]
+mod ip;
+#[cfg(feature = "mac")]
+mod mac;
+#[cfg(feature = "roman")]
+mod roman;
+#[cfg(feature = "uuid")]
+mod uuid;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(feature = "ip")]
+pub use ip::subnet;
+#[cfg(feature = "mac")]
+pub use mac::oui;
+#[cfg(feature = "roman")]
+pub use roman::{numeral as roman_numeral, value as roman_value};
+#[cfg(feature = "uuid")]
+pub use uuid::fields as uuid_fields;
+
+// An input/output encoding that lives outside the core prefix scheme.
+// Formats are consulted before the built-in prefixes when parsing a literal
+// and can be selected for output with `=name`.
+pub trait Format: Send + Sync {
+    fn name(&self) -> &'static str;
+    // Whether `input` looks like a literal of this format
+    fn detect(&self, input: &str) -> bool;
+    fn parse(&self, input: &str) -> Result<i128, BaseConversionError>;
+    fn format(&self, value: i128) -> String;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum HexCase {
+    #[default]
+    Lower,
+    Upper,
+}
+
+// Marker written before hex output: C, assembler, Verilog or none
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum HexPrefix {
+    #[default]
+    #[value(name = "0x")]
+    C,
+    #[value(name = "$")]
+    Dollar,
+    #[value(name = "'h", alias = "h'")]
+    Verilog,
+    #[value(name = "none")]
+    Bare,
+}
+
+// How numbers are written out, set from the command line and changed with
+// `:set`. Each Env carries its own, so formatting never reads process state.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Style {
+    pub hex_case: HexCase,
+    pub hex_prefix: HexPrefix,
+    // Minimum digit count of hex, octal and binary output; 0 for no padding
+    pub pad: usize,
+    // Separator between groups of decimal digits, or None to leave output
+    // ungrouped; hex and binary digits are grouped with _ whenever it is set
+    pub group: Option<char>,
+    // Column to wrap long bit strings at; 0 leaves them on one line
+    pub wrap: usize,
+}
+
+// Left-pads digits with zeros to the width set by --pad or `:set pad`
+pub fn zero_pad(digits: String, style: &Style) -> String {
+    format!("{:0>width$}", digits, width = style.pad)
+}
+
+// Inserts `separator` between every `size` digits, counting from the right
+pub fn group(digits: &str, size: usize, separator: char) -> String {
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(size) {
+            grouped.push(separator);
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+// Hex or binary digits in fours, when --group or `:set group` is on
+pub fn group_nibbles(digits: String, style: &Style) -> String {
+    match style.group {
+        Some(_) => group(&digits, 4, '_'),
+        None => digits,
+    }
+}
+
+// A decimal number in thousands, keeping any sign in front
+pub fn group_decimal(number: String, style: &Style) -> String {
+    let Some(separator) = style.group else {
+        return number;
+    };
+    match number.strip_prefix('-') {
+        Some(digits) => format!("-{}", group(digits, 3, separator)),
+        None => group(&number, 3, separator),
+    }
+}
+
+// Lays out `chunks` after `prefix`, starting a new line whenever the next
+// chunk would pass the wrap width. Wrapped lines end in a ` \` marker and
+// continue under the first chunk.
+pub fn wrap_chunks(prefix: &str, chunks: &[&str], style: &Style) -> String {
+    let width = style.wrap;
+    let total = prefix.len() + chunks.iter().map(|chunk| chunk.len()).sum::<usize>();
+    if width == 0 || total <= width {
+        return format!("{}{}", prefix, chunks.concat());
+    }
+    let indent = " ".repeat(prefix.len());
+    let mut lines = vec![prefix.to_string()];
+    for chunk in chunks {
+        let line = lines.last_mut().unwrap();
+        // Leave room for the marker, but always place at least one chunk
+        if line.len() > prefix.len() && line.len() + chunk.len() + 2 > width {
+            lines.push(indent.clone());
+        }
+        lines.last_mut().unwrap().push_str(chunk);
+    }
+    lines.join(" \\\n")
+}
+
+// Binary digits split into whole bytes counted from the least significant
+// bit; grouped digits keep their separators, with one after each byte
+pub fn wrap_binary(prefix: &str, digits: &str, style: &Style) -> String {
+    let digits = digits.replace('_', "");
+    let first = match digits.len() % 8 {
+        0 => 8.min(digits.len()),
+        partial => partial,
+    };
+    let mut chunks = vec![&digits[..first]];
+    chunks.extend(digits.as_bytes()[first..].chunks(8).map(|chunk| std::str::from_utf8(chunk).unwrap_or_default()));
+    let last = chunks.len() - 1;
+    let chunks: Vec<String> = match style.group {
+        Some(_) => chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| group(chunk, 4, '_') + if i < last { "_" } else { "" })
+            .collect(),
+        None => chunks.iter().map(|chunk| chunk.to_string()).collect(),
+    };
+    wrap_chunks(prefix, &chunks.iter().map(String::as_str).collect::<Vec<_>>(), style)
+}
+
+// Bits shown for a value in binary, octal and hex: negative values use their
+// 64-bit two's complement when they fit in 64 bits, and 128 bits otherwise
+pub fn bit_pattern(value: i128) -> u128 {
+    match i64::try_from(value) {
+        Ok(small) => small as u64 as u128,
+        Err(_) => value as u128,
+    }
+}
+
+// Hex output in the style chosen with --hex-case and --hex-prefix
+pub fn hex(value: i128, style: &Style) -> String {
+    hex_pattern(bit_pattern(value), style)
+}
+
+pub fn hex_pattern(bits: u128, style: &Style) -> String {
+    hex_digits(bits, style.pad, style)
+}
+
+// Hex zero-padded to `width` digits
+fn hex_digits(value: u128, width: usize, style: &Style) -> String {
+    style_hex(&group_nibbles(format!("{:0>width$x}", value, width = width), style), style)
+}
+
+// Styles, pads and groups lowercase hex digits computed elsewhere
+#[cfg(feature = "bigint")]
+pub fn styled_hex(digits: &str, style: &Style) -> String {
+    style_hex(&group_nibbles(zero_pad(digits.to_string(), style), style), style)
+}
+
+// Lowercase hex digits in the case chosen with --hex-case
+fn hex_case(digits: &str, style: &Style) -> String {
+    match style.hex_case {
+        HexCase::Lower => digits.to_string(),
+        HexCase::Upper => digits.to_uppercase(),
+    }
+}
+
+fn style_hex(digits: &str, style: &Style) -> String {
+    let digits = hex_case(digits, style);
+    let prefix = match style.hex_prefix {
+        HexPrefix::C => "0x",
+        HexPrefix::Dollar => "$",
+        HexPrefix::Verilog => "'h",
+        HexPrefix::Bare => "",
+    };
+    format!("{}{}", prefix, digits)
+}
+
+// Output names e1 through e17 for scientific notation with that many
+// significant digits; plain =e gives the shortest form that reads back
+static SCIENTIFIC: LazyLock<Vec<String>> = LazyLock::new(|| (1..=17).map(|digits| format!("e{}", digits)).collect());
+
+pub fn scientific_name(name: &str) -> Option<&'static str> {
+    SCIENTIFIC.iter().find(|known| *known == name).map(String::as_str)
+}
+
+pub fn scientific_digits(name: &str) -> Option<usize> {
+    scientific_name(name)?[1..].parse().ok()
+}
+
+// `value` as 1.5e6, rounded to `digits` significant digits if given
+pub fn scientific(value: f64, digits: Option<usize>) -> String {
+    match digits {
+        Some(digits) => format!("{:.*e}", digits - 1, value),
+        None => format!("{:e}", value),
+    }
+}
+
+// SI prefixes from 10^-30 to 10^30, a power of 1000 apart
+const SI_PREFIXES: [&str; 21] =
+    ["q", "r", "y", "z", "a", "f", "p", "n", "µ", "m", "", "k", "M", "G", "T", "P", "E", "Z", "Y", "R", "Q"];
+
+// Engineering notation with an SI prefix, such as 1.234 M, 56.7 k or 890 n:
+// the exponent is a multiple of 3 and the digits are cut to 4 significant
+pub fn si(value: f64) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return value.to_string();
+    }
+    let mut power = ((value.abs().log10() / 3.0).floor() as i32).clamp(-10, 10);
+    let mut scaled = value / 1000f64.powi(power);
+    // Rounding 999.96 up to 1000 moves it to the next prefix
+    let places = |scaled: f64| (3 - scaled.abs().log10().floor() as i32).clamp(0, 3) as usize;
+    if format!("{:.*}", places(scaled), scaled.abs()).starts_with("1000") && power < 10 {
+        power += 1;
+        scaled = value / 1000f64.powi(power);
+    }
+    let digits = format!("{:.*}", places(scaled), scaled);
+    let digits = if digits.contains('.') { digits.trim_end_matches('0').trim_end_matches('.') } else { &digits };
+    match SI_PREFIXES[(power + 10) as usize] {
+        "" => digits.to_string(),
+        prefix => format!("{} {}", digits, prefix),
+    }
+}
+
+// A byte count in the largest unit it reaches: `base` is 1024 for KiB, MiB
+// and so on, or 1000 for kB, MB and so on
+fn in_units(bytes: u128, base: u128, units: [&str; 7]) -> String {
+    let power = (0..units.len() as u32).rev().find(|&power| bytes >= base.pow(power)).unwrap_or(0);
+    let scaled = format!("{:.3}", bytes as f64 / base.pow(power) as f64);
+    format!("{} {}", scaled.trim_end_matches('0').trim_end_matches('.'), units[power as usize])
+}
+
+// A byte count in binary and decimal units, e.g. 1.5 KiB (1.536 kB)
+pub fn byte_size(value: i128) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let bytes = value.unsigned_abs();
+    if bytes < 1000 {
+        return format!("{}{} B", sign, bytes);
+    }
+    let binary = in_units(bytes, 1024, ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"]);
+    let decimal = in_units(bytes, 1000, ["B", "kB", "MB", "GB", "TB", "PB", "EB"]);
+    format!("{}{} ({}{})", sign, binary, sign, decimal)
+}
+
+// Output names for a signed representation at 1 to 128 bits: t8 for two's
+// complement, o8 for one's complement and s8 for sign-magnitude
+static REPRESENTATIONS: LazyLock<Vec<String>> = LazyLock::new(|| {
+    let names = ["t", "o", "s"].map(|kind| (1..=128).map(move |bits| format!("{}{}", kind, bits)));
+    names.into_iter().flatten().collect()
+});
+
+pub fn representation_name(name: &str) -> Option<&'static str> {
+    REPRESENTATIONS.iter().find(|known| *known == name).map(String::as_str)
+}
+
+pub fn representation_bits(name: &str) -> Option<u32> {
+    representation_name(name)?[1..].parse().ok()
+}
+
+// Output names 3 through 36 for positional digits in that base, e.g. =36;
+// the usual 2, 8, 10 and 16 keep their own prefixes
+static RADIXES: LazyLock<Vec<String>> = LazyLock::new(|| (2..=36).map(|radix| radix.to_string()).collect());
+static RADIX_TITLES: LazyLock<Vec<String>> = LazyLock::new(|| (2..=36).map(|radix| format!("Base {}", radix)).collect());
+
+pub fn radix_name(name: &str) -> Option<&'static str> {
+    RADIXES.iter().find(|known| *known == name).map(String::as_str)
+}
+
+pub fn radix_title(name: &str) -> Option<&'static str> {
+    let radix: usize = radix_name(name)?.parse().ok()?;
+    Some(&RADIX_TITLES[radix - 2])
+}
+
+// Digits of `value` in base `radix`, least significant first, each between
+// `lowest` and `lowest + |radix| - 1`. A negative radix needs no sign, and
+// lowest -1 in base 3 gives balanced ternary; a negative value in a positive
+// radix with lowest `1 - radix` gives its magnitude's digits negated.
+fn place_digits(mut value: i128, radix: i128, lowest: i128) -> Vec<i128> {
+    let base = radix.abs();
+    let mut digits = Vec::new();
+    loop {
+        let (mut quotient, mut digit) = (value.div_euclid(base), value.rem_euclid(base));
+        if digit >= lowest + base {
+            digit -= base;
+            quotient += 1;
+        }
+        digits.push(digit);
+        value = if radix < 0 { -quotient } else { quotient };
+        if value == 0 {
+            break;
+        }
+    }
+    digits
+}
+
+// `value` as base#digits, the form `base#digits` input accepts; negative
+// values keep their sign rather than a two's complement pattern
+pub fn radix(value: i128, radix: u32) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let lowest = if value < 0 { 1 - radix as i128 } else { 0 };
+    let digits = place_digits(value, radix as i128, lowest).into_iter().rev();
+    let digits = digits.map(|digit| std::char::from_digit(digit.unsigned_abs() as u32, radix).unwrap());
+    format!("{}{}#{}", sign, radix, digits.collect::<String>().to_ascii_uppercase())
+}
+
+// Base -2, which writes negative values without a sign, e.g. -2#1101 for -3
+pub fn negabinary(value: i128) -> String {
+    let digits = place_digits(value, -2, 0).into_iter().rev();
+    format!("-2#{}", digits.map(|digit| if digit == 1 { '1' } else { '0' }).collect::<String>())
+}
+
+// Base 3 with digits -1, 0 and 1, the -1 written as T, e.g. bal3#1TT for 5
+pub fn balanced_ternary(value: i128) -> String {
+    let digits = place_digits(value, 3, -1).into_iter().rev();
+    let digits = digits.map(|digit| match digit {
+        -1 => 'T',
+        0 => '0',
+        _ => '1',
+    });
+    format!("bal3#{}", digits.collect::<String>())
+}
+
+// The bit pattern `value` occupies in `bits` bits under representation
+// `name`, as hex covering the whole width, e.g. 0xff for -1 as t8, 0xfe as
+// o8 and 0x81 as s8; None if it fits neither signed nor unsigned
+pub fn representation(name: &str, value: i128, bits: u32, style: &Style) -> Option<String> {
+    let ty = IntType::parse(&format!("u{}", bits))?;
+    let magnitude = value.unsigned_abs();
+    let pattern = match (name.as_bytes()[0], value < 0) {
+        // Unsigned values up to 2^bits - 1 look the same in all three
+        (_, false) if value as u128 & !ty.mask() == 0 => value as u128,
+        (b't', true) if value >> (bits - 1) == -1 => value as u128 & ty.mask(),
+        // One's complement and sign-magnitude spend a pattern on -0, so
+        // they only reach -(2^(bits-1) - 1)
+        (b'o', true) if magnitude >> (bits - 1) == 0 => !magnitude & ty.mask(),
+        (b's', true) if magnitude >> (bits - 1) == 0 => 1 << (bits - 1) | magnitude,
+        _ => return None,
+    };
+    Some(hex_digits(pattern, (bits as usize).div_ceil(4).max(style.pad), style))
+}
+
+// Bytes as source code for the `c`, `rust` and `python` outputs: a C
+// initializer, a Rust array or a Python bytes literal
+pub fn byte_array(syntax: &str, bytes: &[u8], style: &Style) -> Option<String> {
+    let bytes = bytes.iter().map(|byte| hex_case(&format!("{:02x}", byte), style));
+    Some(match syntax {
+        "c" => format!("{{{}}}", bytes.map(|byte| format!("0x{}", byte)).collect::<Vec<_>>().join(", ")),
+        "rust" => format!("[{}]", bytes.map(|byte| format!("0x{}", byte)).collect::<Vec<_>>().join(", ")),
+        "python" => format!("b\"{}\"", bytes.map(|byte| format!("\\x{}", byte)).collect::<String>()),
+        _ => return None,
+    })
+}
+
+// A value's bytes in memory order both ways, for --endian:
+//
+//   byte           0  1  2  3
+//   big-endian    de ad be ef
+//   little-endian ef be ad de
+//
+// `bytes` is the width to show; by default the value's own width rounded up
+// to 1, 2, 4, 8 or 16 bytes
+pub fn byte_order(value: i128, bytes: Option<usize>, style: &Style) -> Vec<String> {
+    let pattern = bit_pattern(value).to_be_bytes();
+    let used = 16 - pattern.iter().take_while(|&&byte| byte == 0).count();
+    let width = bytes.unwrap_or(used.max(1).next_power_of_two()).clamp(1, 16);
+    let big: Vec<String> = pattern[16 - width..].iter().map(|byte| hex_case(&format!("{:02x}", byte), style)).collect();
+    let little: Vec<String> = big.iter().rev().cloned().collect();
+    let indices: Vec<String> = (0..width).map(|i| format!("{:>2}", i)).collect();
+    vec![
+        format!("  byte          {}", indices.join(" ")),
+        format!("  big-endian    {}", big.join(" ")),
+        format!("  little-endian {}", little.join(" ")),
+    ]
+}
+
+// Owner, group and other permission bits with the setuid, setgid and sticky
+// bit that shows in each one's execute position: s or t when the execute bit
+// is also set, S or T when it is not
+const PERMISSION_CLASSES: [(u32, u32, char); 3] = [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')];
+
+// A mode such as 0o4755 as ls shows it, e.g. rwsr-xr-x
+pub fn permissions(mode: u32) -> String {
+    let mut text = String::new();
+    for (shift, special, marker) in PERMISSION_CLASSES {
+        let bits = mode >> shift;
+        text.push(if bits & 4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 1 != 0, mode & special != 0) {
+            (true, true) => marker,
+            (false, true) => marker.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+// The mode of symbolic permissions such as rwxr-xr-x, optionally after a
+// file type character as in drwxr-xr-x
+pub fn parse_permissions(text: &str) -> Option<u32> {
+    let text = match text.len() {
+        10 if text.starts_with(['-', 'd', 'l', 'c', 'b', 'p', 's']) => &text[1..],
+        _ => text,
+    };
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() != 9 {
+        return None;
+    }
+    let mut mode = 0;
+    for ((shift, special, marker), class) in PERMISSION_CLASSES.into_iter().zip(chars.chunks(3)) {
+        let execute = match class[2] {
+            'x' => 1,
+            '-' => 0,
+            c if c == marker => 1 | special >> shift,
+            c if c == marker.to_ascii_uppercase() => special >> shift,
+            _ => return None,
+        };
+        let read = match class[0] {
+            'r' => 4,
+            '-' => 0,
+            _ => return None,
+        };
+        let write = match class[1] {
+            'w' => 2,
+            '-' => 0,
+            _ => return None,
+        };
+        mode |= (read | write | execute) << shift;
+    }
+    Some(mode)
+}
+
+// The formats a session can parse and print: every one compiled in through
+// cargo features, plus alphabets and plugins defined at runtime. Each Env
+// carries its own, so defining a format in one leaves the others as they were.
+#[derive(Clone)]
+pub struct Registry {
+    formats: Vec<Arc<dyn Format>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry { formats: builtin_formats() }
+    }
+}
+
+#[allow(unused_mut)]
+fn builtin_formats() -> Vec<Arc<dyn Format>> {
+    let mut formats: Vec<Arc<dyn Format>> = vec![
+        #[cfg(feature = "gray")]
+        Arc::new(gray::Gray),
+        #[cfg(feature = "roman")]
+        Arc::new(roman::Roman),
+        #[cfg(feature = "ip")]
+        Arc::new(ip::Ipv4),
+        #[cfg(feature = "ip")]
+        Arc::new(ip::Ipv6 { full: false }),
+        #[cfg(feature = "ip")]
+        Arc::new(ip::Ipv6 { full: true }),
+        #[cfg(feature = "uuid")]
+        Arc::new(uuid::Uuid),
+    ];
+    #[cfg(feature = "mac")]
+    formats.extend(mac::STYLES.map(|style| Arc::new(style) as Arc<dyn Format>));
+    formats
+}
+
+impl Registry {
+    // Adds a format, replacing any registered under the same name
+    pub fn register(&mut self, format: Arc<dyn Format>) {
+        self.formats.retain(|registered| registered.name() != format.name());
+        self.formats.push(format);
+    }
+
+    // Adds a format for positional digits over `digits`, as set up by
+    // `:alphabet name digits`
+    pub fn define_alphabet(&mut self, name: &str, digits: &str) -> Result<(), String> {
+        self.check_new_name(name, "alphabet")?;
+        let digits: Vec<char> = digits.chars().collect();
+        if digits.len() < 2 {
+            return Err("An alphabet needs at least two digits".to_string());
+        }
+        if let Some(&c) = digits.iter().find(|&&c| c.is_whitespace() || c == '#') {
+            return Err(format!("{:?} cannot be a digit", c));
+        }
+        if let Some(&c) = digits.iter().enumerate().find_map(|(i, c)| digits[..i].contains(c).then_some(c)) {
+            return Err(format!("{:?} appears more than once in the alphabet", c));
+        }
+        let name = Box::leak(name.to_string().into_boxed_str());
+        self.register(Arc::new(alphabet::Alphabet { name, digits }));
+        Ok(())
+    }
+
+    // Adds the WebAssembly format in `path`, named after the file, as set
+    // up by `:plugin path`
+    pub fn load_plugin(&mut self, path: &std::path::Path) -> Result<&'static str, String> {
+        let name = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+        self.check_new_name(name, "plugin")?;
+        #[cfg(feature = "wasm")]
+        {
+            let name = Box::leak(name.to_string().into_boxed_str());
+            self.register(Arc::new(wasm::Plugin::load(name, path)?));
+            Ok(name)
+        }
+        #[cfg(not(feature = "wasm"))]
+        Err("Plugins need the wasm feature".to_string())
+    }
+
+    fn check_new_name(&self, name: &str, kind: &str) -> Result<(), String> {
+        if !crate::is_identifier(name) {
+            return Err(format!("{} is not a valid {} name", name, kind));
+        }
+        if crate::output_name(name, self).is_some() {
+            return Err(format!("{} is already an output format", name));
+        }
+        Ok(())
+    }
+
+    pub fn parse(&self, input: &str) -> Option<Result<i128, BaseConversionError>> {
+        let format = self.formats.iter().find(|format| format.detect(input))?;
+        debug!(input, format = format.name(), "detected registered format");
+        Some(format.parse(input))
+    }
+
+    pub fn format_as(&self, name: &str, value: i128) -> Option<String> {
+        self.formats.iter().find(|format| format.name() == name).map(|format| format.format(value))
+    }
+
+    pub fn lookup_name(&self, name: &str) -> Option<&'static str> {
+        self.formats.iter().map(|format| format.name()).find(|&registered| registered == name)
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.formats.iter().map(|format| format.name()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permission_modes() {
+        assert_eq!(permissions(0o755), "rwxr-xr-x");
+        assert_eq!(permissions(0o4755), "rwsr-xr-x");
+        assert_eq!(permissions(0o2644), "rw-r-Sr--");
+        assert_eq!(permissions(0o1777), "rwxrwxrwt");
+        assert_eq!(parse_permissions("rwxr-xr-x"), Some(0o755));
+        assert_eq!(parse_permissions("drwxrwxrwT"), Some(0o1776));
+        assert_eq!(parse_permissions("-rwSr-sr-x"), Some(0o6655));
+        assert_eq!(parse_permissions("rwxr-xr-q"), None);
+        assert_eq!(permissions(0), "---------");
+        assert_eq!(permissions(0o7777), "rwsrwsrwt");
+        assert_eq!(parse_permissions("---------"), Some(0));
+        assert_eq!(parse_permissions("rwxr-xr-"), None);
+        assert_eq!(parse_permissions("rwxr-xr-xx"), None);
+        assert_eq!(parse_permissions(""), None);
+    }
+
+    #[test]
+    #[cfg(feature = "ip")]
+    fn builtin_formats_need_no_setup() {
+        let registry = Registry::default();
+        assert!(matches!(registry.parse("10.0.0.1"), Some(Ok(0x0a00_0001))));
+        assert_eq!(registry.format_as("ip", 0x7f00_0001).as_deref(), Some("127.0.0.1"));
+    }
+
+    #[test]
+    fn registering_a_name_again_replaces_it() {
+        let alphabet = |digits: &str| alphabet::Alphabet { name: "registry_test", digits: digits.chars().collect() };
+        let mut registry = Registry::default();
+        registry.register(Arc::new(alphabet("ab")));
+        let before = registry.clone();
+        registry.register(Arc::new(alphabet("xyz")));
+        assert_eq!(registry.names().iter().filter(|&&name| name == "registry_test").count(), 1);
+        assert_eq!(registry.format_as("registry_test", 5).as_deref(), Some("registry_test#yz"));
+        assert!(registry.define_alphabet("registry_test", "01").is_err());
+        // A copy taken earlier keeps the formats it had
+        assert_eq!(before.format_as("registry_test", 5).as_deref(), Some("registry_test#bab"));
+        assert_eq!(Registry::default().lookup_name("registry_test"), None);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::BaseConversionError;
+
+use super::Format;
+
+// Positional digits over a user-defined alphabet such as base62, written as
+// the alphabet's name, `#` and the digits, e.g. `b62#4c92`. The first
+// character of the alphabet is the zero digit.
+pub struct Alphabet {
+    pub name: &'static str,
+    pub digits: Vec<char>,
+}
+
+impl Alphabet {
+    fn digit(&self, c: char) -> Option<u128> {
+        self.digits.iter().position(|&digit| digit == c).map(|i| i as u128)
+    }
+}
+
+impl Format for Alphabet {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        input.split_once('#').is_some_and(|(name, digits)| {
+            name == self.name && !digits.is_empty() && digits.chars().all(|c| self.digit(c).is_some())
+        })
+    }
+
+    fn parse(&self, input: &str) -> Result<i128, BaseConversionError> {
+        let radix = self.digits.len() as u128;
+        let mut value: u128 = 0;
+        for c in input[self.name.len() + 1..].chars() {
+            let digit = self.digit(c).ok_or(BaseConversionError::InvalidInputFormat)?;
+            value = value
+                .checked_mul(radix)
+                .and_then(|value| value.checked_add(digit))
+                .ok_or(BaseConversionError::ParseIntError)?;
+        }
+        Ok(value as i128)
+    }
+
+    fn format(&self, value: i128) -> String {
+        let radix = self.digits.len() as u128;
+        let mut value = super::bit_pattern(value);
+        let mut digits = Vec::new();
+        loop {
+            digits.push(self.digits[(value % radix) as usize]);
+            value /= radix;
+            if value == 0 {
+                break;
+            }
+        }
+        format!("{}#{}", self.name, digits.iter().rev().collect::<String>())
+    }
+}
@@ -0,0 +1,56 @@
+use crate::BaseConversionError;
+
+use super::Format;
+
+// Reflected binary (Gray) code, written as `0g` followed by the code bits.
+// The leading digit keeps codes apart from identifiers such as g1.
+pub struct Gray;
+
+impl Format for Gray {
+    fn name(&self) -> &'static str {
+        "gray"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        input
+            .strip_prefix("0g")
+            .is_some_and(|bits| !bits.is_empty() && bits.chars().all(|c| c == '0' || c == '1'))
+    }
+
+    fn parse(&self, input: &str) -> Result<i128, BaseConversionError> {
+        let mut code = u128::from_str_radix(&input[2..], 2)?;
+        let mut value = code;
+        while code > 0 {
+            code >>= 1;
+            value ^= code;
+        }
+        Ok(value as i128)
+    }
+
+    fn format(&self, value: i128) -> String {
+        let value = super::bit_pattern(value);
+        format!("0g{:b}", value ^ (value >> 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_need_the_0g_prefix() {
+        assert!(Gray.detect("0g1101") && Gray.detect("0g0"));
+        assert!(!Gray.detect("g1") && !Gray.detect("0g") && !Gray.detect("0g12"));
+    }
+
+    #[test]
+    fn codes_round_trip() {
+        assert!(matches!(Gray.parse("0g1101"), Ok(9)));
+        assert!(matches!(Gray.parse("0g0"), Ok(0)));
+        assert_eq!(Gray.format(9), "0g1101");
+        assert_eq!(Gray.format(0), "0g0");
+        for value in 0..64 {
+            assert!(matches!(Gray.parse(&Gray.format(value)), Ok(parsed) if parsed == value));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::net::Ipv6Addr;
+
+use crate::BaseConversionError;
+
+use super::Format;
+
+// IPv4 addresses in dotted-quad form such as 192.168.1.1, the four octets
+// of a 32-bit value from most to least significant
+pub struct Ipv4;
+
+fn octets(input: &str) -> Option<[u8; 4]> {
+    let mut octets = [0; 4];
+    let mut parts = input.split('.');
+    for octet in octets.iter_mut() {
+        let part = parts.next().filter(|part| (1..=3).contains(&part.len()))?;
+        if !part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        *octet = part.parse().ok()?;
+    }
+    parts.next().is_none().then_some(octets)
+}
+
+fn dotted(address: u32) -> String {
+    address.to_be_bytes().map(|octet| octet.to_string()).join(".")
+}
+
+// The masks, range and size of a block such as 10.0.0.0/22; any host bits
+// set in the address are ignored. /31 and /32 blocks have no network or
+// broadcast address to set aside, so every address in them is a host.
+pub fn subnet(cidr: &str, style: &super::Style) -> Result<Vec<String>, String> {
+    let (address, prefix) = cidr.split_once('/').ok_or(format!("{} has no /prefix length", cidr))?;
+    let address = octets(address).map(u32::from_be_bytes).ok_or(format!("{} is not an IPv4 address", address))?;
+    let prefix = prefix.parse::<u32>().ok().filter(|&prefix| prefix <= 32);
+    let prefix = prefix.ok_or(format!("{} needs a prefix length from 0 to 32", cidr))?;
+    let netmask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+    let (network, broadcast) = (address & netmask, address | !netmask);
+    let (first, last, hosts) = match prefix {
+        31 | 32 => (network, broadcast, 1u64 << (32 - prefix)),
+        _ => (network + 1, broadcast - 1, (1u64 << (32 - prefix)) - 2),
+    };
+    Ok(vec![
+        format!("  netmask     {} ({})", dotted(netmask), super::hex(netmask as i128, style)),
+        format!("  wildcard    {} ({})", dotted(!netmask), super::hex(!netmask as i128, style)),
+        format!("  network     {}", dotted(network)),
+        format!("  broadcast   {}", dotted(broadcast)),
+        format!("  first host  {}", dotted(first)),
+        format!("  last host   {}", dotted(last)),
+        format!("  hosts       {}", hosts),
+    ])
+}
+
+impl Format for Ipv4 {
+    fn name(&self) -> &'static str {
+        "ip"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        octets(input).is_some()
+    }
+
+    fn parse(&self, input: &str) -> Result<i128, BaseConversionError> {
+        let octets = octets(input).ok_or(BaseConversionError::InvalidInputFormat)?;
+        Ok(u32::from_be_bytes(octets) as i128)
+    }
+
+    fn format(&self, value: i128) -> String {
+        match u32::try_from(value) {
+            Ok(address) => dotted(address),
+            Err(_) => format!("Error: {} is not an IPv4 address (0 to 0xffffffff)", value),
+        }
+    }
+}
+
+// IPv6 addresses such as 2001:db8::1, the eight groups of a 128-bit value.
+// `=ip6` writes the compressed form and `=ip6full` every group in full.
+pub struct Ipv6 {
+    pub full: bool,
+}
+
+impl Format for Ipv6 {
+    fn name(&self) -> &'static str {
+        if self.full { "ip6full" } else { "ip6" }
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        input.contains(':') && input.parse::<Ipv6Addr>().is_ok()
+    }
+
+    fn parse(&self, input: &str) -> Result<i128, BaseConversionError> {
+        let address = input.parse::<Ipv6Addr>().map_err(|_| BaseConversionError::InvalidInputFormat)?;
+        Ok(u128::from(address) as i128)
+    }
+
+    // Negative values are taken as their 128-bit pattern, as for other
+    // integers past i128::MAX
+    fn format(&self, value: i128) -> String {
+        let address = Ipv6Addr::from(value as u128);
+        match self.full {
+            true => address.segments().map(|group| format!("{:04x}", group)).join(":"),
+            false => address.to_string(),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::BaseConversionError;
+
+use super::Format;
+
+// Highest 48-bit value, the all-ones broadcast address
+const MAX: i128 = 0xffff_ffff_ffff;
+
+// MAC addresses, the six bytes of a 48-bit value, written as colon pairs
+// (aa:bb:cc:dd:ee:ff), dash pairs as Windows shows them (AA-BB-CC-DD-EE-FF)
+// or dotted quads as Cisco shows them (aabb.ccdd.eeff)
+pub struct Mac {
+    pub name: &'static str,
+    pub separator: char,
+    // Hex digits between separators
+    pub group: usize,
+}
+
+pub const STYLES: [Mac; 3] = [
+    Mac { name: "mac", separator: ':', group: 2 },
+    Mac { name: "macdash", separator: '-', group: 2 },
+    Mac { name: "macdot", separator: '.', group: 4 },
+];
+
+fn value(input: &str) -> Option<i128> {
+    let style = STYLES.iter().find(|style| input.contains(style.separator))?;
+    let groups: Vec<&str> = input.split(style.separator).collect();
+    let valid = |group: &&str| group.len() == style.group && group.chars().all(|c| c.is_ascii_hexdigit());
+    if groups.len() != 12 / style.group || !groups.iter().all(valid) {
+        return None;
+    }
+    i128::from_str_radix(&groups.concat(), 16).ok()
+}
+
+// The vendor prefix of an address, its top three bytes, e.g. aa:bb:cc
+pub fn oui(value: i128) -> Option<String> {
+    let digits = format!("{:06x}", Some(value).filter(|value| (0..=MAX).contains(value))? >> 24);
+    Some(format!("{}:{}:{}", &digits[..2], &digits[2..4], &digits[4..]))
+}
+
+impl Format for Mac {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        input.contains(self.separator) && value(input).is_some()
+    }
+
+    fn parse(&self, input: &str) -> Result<i128, BaseConversionError> {
+        value(input).ok_or(BaseConversionError::InvalidInputFormat)
+    }
+
+    fn format(&self, value: i128) -> String {
+        if !(0..=MAX).contains(&value) {
+            return format!("Error: {} is not a MAC address (0 to 0xffffffffffff)", value);
+        }
+        let digits = format!("{:012x}", value);
+        let digits = if self.separator == '-' { digits.to_uppercase() } else { digits };
+        let groups: Vec<&str> = (0..12).step_by(self.group).map(|i| &digits[i..i + self.group]).collect();
+        groups.join(&self.separator.to_string())
+    }
+}
@@ -0,0 +1,67 @@
+use crate::BaseConversionError;
+
+use super::Format;
+
+const NUMERALS: [(u32, &str); 13] = [
+    (1000, "M"),
+    (900, "CM"),
+    (500, "D"),
+    (400, "CD"),
+    (100, "C"),
+    (90, "XC"),
+    (50, "L"),
+    (40, "XL"),
+    (10, "X"),
+    (9, "IX"),
+    (5, "V"),
+    (4, "IV"),
+    (1, "I"),
+];
+
+// Roman numerals from I to MMMCMXCIX, in the standard subtractive form.
+// Input is written with a `0r` prefix, as in 0rMMXXIV, so that names such
+// as MIX or CD stay free for variables.
+pub struct Roman;
+
+// `value` as a Roman numeral, or None outside 1 to 3999
+pub fn numeral(value: i128) -> Option<String> {
+    let mut rest = u32::try_from(value).ok().filter(|value| (1..=3999).contains(value))?;
+    let mut numeral = String::new();
+    for (amount, letters) in NUMERALS {
+        while rest >= amount {
+            numeral.push_str(letters);
+            rest -= amount;
+        }
+    }
+    Some(numeral)
+}
+
+// The value of a numeral in standard form, so that IIII or IM are None
+pub fn value(input: &str) -> Option<i128> {
+    let (mut rest, mut value) = (input, 0);
+    for (amount, letters) in NUMERALS {
+        while let Some(after) = rest.strip_prefix(letters) {
+            rest = after;
+            value += amount as i128;
+        }
+    }
+    Some(value).filter(|&value| rest.is_empty() && numeral(value).is_some_and(|standard| standard == input))
+}
+
+impl Format for Roman {
+    fn name(&self) -> &'static str {
+        "roman"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        input.strip_prefix("0r").is_some_and(|numeral| !numeral.is_empty())
+    }
+
+    fn parse(&self, input: &str) -> Result<i128, BaseConversionError> {
+        value(&input[2..]).ok_or(BaseConversionError::InvalidInputFormat)
+    }
+
+    fn format(&self, value: i128) -> String {
+        numeral(value).unwrap_or_else(|| format!("Error: {} has no Roman numeral (1 to 3999)", value))
+    }
+}
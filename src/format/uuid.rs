@@ -0,0 +1,91 @@
+use crate::timestamp::{self, Precision};
+use crate::BaseConversionError;
+
+use super::Format;
+
+// UUIDs such as 550e8400-e29b-41d4-a716-446655440000, the 32 hex digits of a
+// 128-bit value in groups of 8, 4, 4, 4 and 12
+pub struct Uuid;
+
+const GROUPS: [usize; 5] = [8, 4, 4, 4, 12];
+
+// 100-nanosecond intervals from the Gregorian calendar reform to the Unix
+// epoch, which version 1 and 6 timestamps count from
+const GREGORIAN_OFFSET: i128 = 0x01b2_1dd2_1381_4000;
+
+fn value(input: &str) -> Option<u128> {
+    let groups: Vec<&str> = input.split('-').collect();
+    let valid = groups.len() == GROUPS.len()
+        && groups.iter().zip(GROUPS).all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()));
+    valid.then(|| u128::from_str_radix(&groups.concat(), 16).ok()).flatten()
+}
+
+// The version, variant and any embedded time of a UUID, one per line
+pub fn fields(value: u128) -> Vec<String> {
+    let version = (value >> 76 & 0xf) as u8;
+    let variant = match value >> 61 & 0b111 {
+        0b000..=0b011 => "NCS (0xx)",
+        0b100 | 0b101 => "RFC 9562 (10x)",
+        0b110 => "Microsoft (110)",
+        _ => "reserved (111)",
+    };
+    let kind = match version {
+        1 => "time-based",
+        2 => "DCE security",
+        3 => "name-based, MD5",
+        4 => "random",
+        5 => "name-based, SHA-1",
+        6 => "reordered time-based",
+        7 => "Unix time-based",
+        8 => "custom",
+        _ => "unknown",
+    };
+    let mut lines = vec![
+        format!("  uuid     {}", Uuid.format(value as i128)),
+        format!("  hex      0x{:032x}", value),
+        format!("  version  {} ({})", version, kind),
+        format!("  variant  {}", variant),
+    ];
+    // Only the RFC variant lays out its time fields this way
+    let time = match (version, value >> 62 & 0b11 == 0b10) {
+        (1, true) => {
+            let ticks = (value >> 64 & 0xfff) << 48 | (value >> 80 & 0xffff) << 32 | value >> 96;
+            Some(((ticks as i128 - GREGORIAN_OFFSET) / 10, Precision::Micros))
+        }
+        (6, true) => {
+            let ticks = (value >> 80) << 12 | value >> 64 & 0xfff;
+            Some(((ticks as i128 - GREGORIAN_OFFSET) / 10, Precision::Micros))
+        }
+        (7, true) => Some(((value >> 80) as i128, Precision::Millis)),
+        _ => None,
+    };
+    if let Some((utc, _)) = time.and_then(|(epoch, precision)| timestamp::from_epoch(epoch, precision)) {
+        lines.push(format!("  time     {}", utc));
+    }
+    lines
+}
+
+impl Format for Uuid {
+    fn name(&self) -> &'static str {
+        "uuid"
+    }
+
+    fn detect(&self, input: &str) -> bool {
+        value(input).is_some()
+    }
+
+    fn parse(&self, input: &str) -> Result<i128, BaseConversionError> {
+        value(input).map(|value| value as i128).ok_or(BaseConversionError::InvalidInputFormat)
+    }
+
+    // Negative values are taken as their 128-bit pattern
+    fn format(&self, value: i128) -> String {
+        let digits = format!("{:032x}", value as u128);
+        let mut start = 0;
+        let groups: Vec<&str> = GROUPS.iter().map(|len| {
+            start += len;
+            &digits[start - len..start]
+        }).collect();
+        groups.join("-")
+    }
+}
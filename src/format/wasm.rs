@@ -0,0 +1,166 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use wasmi::{Engine, Linker, Memory, Module, Store, TypedFunc};
+
+use crate::BaseConversionError;
+
+use super::Format;
+
+// A format compiled to WebAssembly and loaded with `:plugin path.wasm`,
+// named after the file. The module exports its `memory` and
+//
+//   alloc(len: i32) -> i32             space for an input of len bytes
+//   detect(ptr: i32, len: i32) -> i32  nonzero for inputs in this format
+//   parse(ptr: i32, len: i32) -> i64   the value of a detected input
+//   format(value: i64) -> i64          the output text as ptr << 32 | len
+//
+// Values cross as i64, so wider values have no plugin output.
+pub struct Plugin {
+    name: &'static str,
+    instance: Mutex<Instance>,
+}
+
+struct Instance {
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    detect: TypedFunc<(i32, i32), i32>,
+    parse: TypedFunc<(i32, i32), i64>,
+    format: TypedFunc<i64, i64>,
+}
+
+impl Plugin {
+    pub fn load(name: &'static str, path: &Path) -> Result<Plugin, String> {
+        let wasm = std::fs::read(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        Plugin::new(name, &wasm).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn new(name: &'static str, wasm: &[u8]) -> Result<Plugin, String> {
+        let engine = Engine::default();
+        let module = Module::new(&engine, wasm).map_err(|e| e.to_string())?;
+        let mut store = Store::new(&engine, ());
+        let instance = Linker::new(&engine)
+            .instantiate(&mut store, &module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| e.to_string())?;
+        let memory = instance.get_memory(&store, "memory").ok_or("The module does not export its memory")?;
+        let missing = |export: &str| format!("The module does not export {} with the plugin signature", export);
+        let instance = Instance {
+            alloc: instance.get_typed_func(&store, "alloc").map_err(|_| missing("alloc"))?,
+            detect: instance.get_typed_func(&store, "detect").map_err(|_| missing("detect"))?,
+            parse: instance.get_typed_func(&store, "parse").map_err(|_| missing("parse"))?,
+            format: instance.get_typed_func(&store, "format").map_err(|_| missing("format"))?,
+            memory,
+            store,
+        };
+        Ok(Plugin { name, instance: Mutex::new(instance) })
+    }
+}
+
+impl Instance {
+    // Copies `input` into the module's memory for detect or parse
+    fn pass(&mut self, input: &str) -> Option<(i32, i32)> {
+        let len = i32::try_from(input.len()).ok()?;
+        let ptr = self.alloc.call(&mut self.store, len).ok()?;
+        self.memory.write(&mut self.store, ptr as u32 as usize, input.as_bytes()).ok()?;
+        Some((ptr, len))
+    }
+
+    fn text(&self, packed: i64) -> Option<String> {
+        let (ptr, len) = ((packed as u64 >> 32) as usize, packed as u32 as usize);
+        let bytes = self.memory.data(&self.store).get(ptr..ptr.checked_add(len)?)?;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl Format for Plugin {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    // A trap counts as not detected, so a faulty plugin cannot stop other
+    // literals from parsing
+    fn detect(&self, input: &str) -> bool {
+        let mut instance = self.instance.lock().unwrap();
+        let Some(args) = instance.pass(input) else { return false };
+        let detect = instance.detect;
+        detect.call(&mut instance.store, args).is_ok_and(|found| found != 0)
+    }
+
+    fn parse(&self, input: &str) -> Result<i128, BaseConversionError> {
+        let mut instance = self.instance.lock().unwrap();
+        let args = instance.pass(input).ok_or(BaseConversionError::InvalidInputFormat)?;
+        let parse = instance.parse;
+        parse
+            .call(&mut instance.store, args)
+            .map(i128::from)
+            .map_err(|_| BaseConversionError::InvalidInputFormat)
+    }
+
+    fn format(&self, value: i128) -> String {
+        let Ok(value) = i64::try_from(value) else {
+            return format!("Error: {} is outside the 64 bits plugins take", value);
+        };
+        let mut instance = self.instance.lock().unwrap();
+        let format = instance.format;
+        let packed = format.call(&mut instance.store, value);
+        match packed.ok().and_then(|packed| instance.text(packed)) {
+            Some(text) => text,
+            None => format!("Error: {} failed to format {}", self.name, value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tally marks: `|||` is 3
+    const TALLY: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) i32.const 0)
+          (func (export "detect") (param $ptr i32) (param $len i32) (result i32)
+            (local $i i32)
+            (if (i32.eqz (local.get $len)) (then (return (i32.const 0))))
+            (block $done
+              (loop $next
+                (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+                (if (i32.ne (i32.load8_u (i32.add (local.get $ptr) (local.get $i))) (i32.const 124))
+                  (then (return (i32.const 0))))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br $next)))
+            i32.const 1)
+          (func (export "parse") (param i32) (param $len i32) (result i64)
+            (i64.extend_i32_u (local.get $len)))
+          (func (export "format") (param $value i64) (result i64)
+            (memory.fill (i32.const 1024) (i32.const 124) (i32.wrap_i64 (local.get $value)))
+            (i64.or (i64.shl (i64.const 1024) (i64.const 32)) (local.get $value))))
+    "#;
+
+    fn tally() -> Plugin {
+        Plugin::new("tally", &wat::parse_str(TALLY).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn plugins_detect_parse_and_format() {
+        let plugin = tally();
+        assert!(plugin.detect("|||") && !plugin.detect("") && !plugin.detect("||x"));
+        assert!(matches!(plugin.parse("|||||"), Ok(5)));
+        assert_eq!(plugin.format(4), "||||");
+        assert_eq!(plugin.format(0), "");
+    }
+
+    #[test]
+    fn plugin_failures_are_errors() {
+        let plugin = tally();
+        // memory.fill past the single page traps
+        assert_eq!(plugin.format(1 << 20), "Error: tally failed to format 1048576");
+        assert_eq!(plugin.format(1 << 64), "Error: 18446744073709551616 is outside the 64 bits plugins take");
+        let incomplete = wat::parse_str(r#"(module (memory (export "memory") 1))"#).unwrap();
+        let error = Plugin::new("incomplete", &incomplete).err().unwrap();
+        assert_eq!(error, "The module does not export alloc with the plugin signature");
+        assert!(Plugin::new("garbage", b"not wasm").is_err());
+    }
+}
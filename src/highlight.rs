@@ -0,0 +1,133 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::settings::Settings;
+use crate::{tokenize, Token};
+
+// Whether text printed to stdout and stderr is colored, decided apart
+// since only one of them may be a terminal
+static STDOUT: AtomicBool = AtomicBool::new(false);
+static STDERR: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+const HEX: &str = "36";
+const BINARY: &str = "32";
+const OCTAL: &str = "33";
+const DECIMAL: &str = "34";
+const OPERATOR: &str = "35";
+const IDENT: &str = "1";
+const KEYWORD: &str = "1;35";
+
+const KEYWORDS: &[&str] = &["let", "if", "else", "for", "in", "while"];
+
+fn flag(stream: Stream) -> &'static AtomicBool {
+    match stream {
+        Stream::Stdout => &STDOUT,
+        Stream::Stderr => &STDERR,
+    }
+}
+
+pub fn set_enabled(stream: Stream, enabled: bool) {
+    flag(stream).store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled(stream: Stream) -> bool {
+    flag(stream).load(Ordering::Relaxed)
+}
+
+// Color of a number literal by the radix it is written in
+fn literal_color(text: &str, legacy: bool) -> &'static str {
+    // Verilog sized literals name their base after the quote, as in 8'hFF
+    if let Some((_, base)) = text.split_once('\'') {
+        return match base.trim_start_matches(['s', 'S']).chars().next() {
+            Some('h' | 'H') => HEX,
+            Some('b' | 'B') => BINARY,
+            Some('o' | 'O') => OCTAL,
+            _ => DECIMAL,
+        };
+    }
+    // Bx, Ox and the d suffix only meant hex and binary digits in the
+    // legacy grammar; b and Ox now prefix binary and octal digits
+    if ["0x", "0X", "Fx", "fx"].iter().any(|prefix| text.starts_with(prefix))
+        || (legacy && (text.starts_with("Bx") || text.starts_with("Ox")))
+    {
+        HEX
+    } else if text.ends_with('b')
+        || (legacy && text.ends_with('d'))
+        || (!legacy && text.starts_with('b'))
+        || text.starts_with('g')
+        || text.starts_with("0b")
+        || text.starts_with("0B")
+    {
+        BINARY
+    } else if text.ends_with('o') || text.starts_with("0o") || text.starts_with("0O") || text.starts_with("Ox") {
+        OCTAL
+    } else {
+        DECIMAL
+    }
+}
+
+// The input with ANSI colors around literals, operators and identifiers,
+// or unchanged when color is disabled for stdout
+pub fn highlight(input: &str, settings: &Settings) -> String {
+    highlight_for(Stream::Stdout, input, settings)
+}
+
+// As highlight, for text printed to `stream`. Text that does not tokenize,
+// such as a bad literal, is left plain.
+pub fn highlight_for(stream: Stream, input: &str, settings: &Settings) -> String {
+    if !enabled(stream) {
+        return input.to_string();
+    }
+    let (tokens, _) = tokenize(input, settings);
+    let mut highlighted = String::new();
+    let mut end = 0;
+    for spanned in tokens {
+        let text = &input[spanned.span.clone()];
+        let color = match &spanned.token {
+            Token::Number(_) | Token::Sized(..) => Some(literal_color(text, settings.legacy_literals)),
+            Token::Float(_) | Token::Imaginary(_) | Token::Bytes(_) | Token::Duration(_) => Some(DECIMAL),
+            Token::Ident(name) if KEYWORDS.contains(&name.as_str()) => Some(KEYWORD),
+            Token::Ident(_) => Some(IDENT),
+            Token::LParen
+            | Token::RParen
+            | Token::LBracket
+            | Token::RBracket
+            | Token::LBrace
+            | Token::RBrace
+            | Token::Comma
+            | Token::Semicolon => None,
+            _ => Some(OPERATOR),
+        };
+        highlighted.push_str(&input[end..spanned.span.start]);
+        match color {
+            Some(color) => highlighted.push_str(&format!("\x1b[{}m{}\x1b[0m", color, text)),
+            None => highlighted.push_str(text),
+        }
+        end = spanned.span.end;
+    }
+    highlighted.push_str(&input[end..]);
+    highlighted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Only stderr is enabled here, since other tests render diagnostics
+    // for stdout and expect them plain
+    #[test]
+    fn streams_are_colored_separately() {
+        let settings = Settings::default();
+        set_enabled(Stream::Stderr, true);
+        assert_eq!(highlight_for(Stream::Stderr, "x + 0x1f", &settings), "\x1b[1mx\x1b[0m \x1b[35m+\x1b[0m \x1b[36m0x1f\x1b[0m");
+        assert_eq!(highlight_for(Stream::Stderr, "(b101)", &settings), "(\x1b[32mb101\x1b[0m)");
+        assert_eq!(highlight_for(Stream::Stderr, "for i in 1", &settings), "\x1b[1;35mfor\x1b[0m \x1b[1mi\x1b[0m \x1b[1;35min\x1b[0m \x1b[34m1\x1b[0m");
+        assert_eq!(highlight_for(Stream::Stderr, "", &settings), "");
+        assert_eq!(highlight("x + 0x1f", &settings), "x + 0x1f");
+    }
+}
@@ -0,0 +1,250 @@
+use crate::convert_input;
+use crate::settings::Settings;
+
+// IEEE 754 binary formats that literals can name
+#[derive(Clone, Copy, PartialEq)]
+pub enum Precision {
+    Single,
+    Double,
+}
+
+impl Precision {
+    pub fn name(self) -> &'static str {
+        match self {
+            Precision::Single => "single",
+            Precision::Double => "double",
+        }
+    }
+
+    pub fn exponent_bits(self) -> u32 {
+        match self {
+            Precision::Single => 8,
+            Precision::Double => 11,
+        }
+    }
+
+    pub fn mantissa_bits(self) -> u32 {
+        match self {
+            Precision::Single => 23,
+            Precision::Double => 52,
+        }
+    }
+
+    fn bias(self) -> i64 {
+        (1 << (self.exponent_bits() - 1)) - 1
+    }
+}
+
+// A float split into its sign, biased exponent and stored mantissa
+pub struct Ieee754 {
+    pub precision: Precision,
+    // Exact value, widened to a double for singles
+    pub value: f64,
+    pub sign: u64,
+    pub exponent: u64,
+    pub mantissa: u64,
+}
+
+impl Ieee754 {
+    pub fn double(value: f64) -> Ieee754 {
+        Ieee754::from_bits(Precision::Double, value.to_bits(), value)
+    }
+
+    pub fn single(value: f32) -> Ieee754 {
+        Ieee754::from_bits(Precision::Single, value.to_bits() as u64, value as f64)
+    }
+
+    fn from_bits(precision: Precision, bits: u64, value: f64) -> Ieee754 {
+        let (exponent_bits, mantissa_bits) = (precision.exponent_bits(), precision.mantissa_bits());
+        Ieee754 {
+            precision,
+            value,
+            sign: bits >> (exponent_bits + mantissa_bits),
+            exponent: (bits >> mantissa_bits) & ((1 << exponent_bits) - 1),
+            mantissa: bits & ((1 << mantissa_bits) - 1),
+        }
+    }
+
+    pub fn bits(&self) -> u64 {
+        let mantissa_bits = self.precision.mantissa_bits();
+        self.sign << (self.precision.exponent_bits() + mantissa_bits) | self.exponent << mantissa_bits | self.mantissa
+    }
+
+    fn max_exponent(&self) -> u64 {
+        (1 << self.precision.exponent_bits()) - 1
+    }
+
+    // NaNs are quiet when the top mantissa bit is set and signaling otherwise
+    pub fn class(&self) -> &'static str {
+        let negative = self.sign == 1;
+        let quiet = self.mantissa >> (self.precision.mantissa_bits() - 1) == 1;
+        match (self.exponent, self.mantissa) {
+            (0, 0) if negative => "-0",
+            (0, 0) => "+0",
+            (0, _) => "subnormal",
+            (exponent, 0) if exponent == self.max_exponent() && negative => "-infinity",
+            (exponent, 0) if exponent == self.max_exponent() => "+infinity",
+            (exponent, _) if exponent == self.max_exponent() && quiet => "quiet NaN",
+            (exponent, _) if exponent == self.max_exponent() => "signaling NaN",
+            _ => "normal",
+        }
+    }
+
+    // The class with the value where it adds something, e.g. "a quiet NaN"
+    // or "subnormal (5e-324)"
+    pub fn describe(&self) -> String {
+        match self.class() {
+            class @ ("normal" | "subnormal") => format!("{} ({:?})", class, self.value),
+            class if class.ends_with("NaN") => format!("a {}", class),
+            class => class.to_string(),
+        }
+    }
+
+    // Power of two the significand is scaled by; subnormals share the
+    // smallest normal exponent
+    pub fn unbiased(&self) -> i64 {
+        self.exponent.max(1) as i64 - self.precision.bias()
+    }
+
+    // One line per field, e.g. for 1.5 as a double:
+    //
+    //   bits      0 01111111111 1000000000000000000000000000000000000000000000000000
+    //   sign      0 (positive)
+    //   exponent  0x3ff: biased 1023, unbiased 0
+    //   mantissa  0x8000000000000
+    //   value     1.1000000000000000000000000000000000000000000000000000b × 2^0 (implied leading 1)
+    pub fn explain(&self) -> Vec<String> {
+        let (exponent_bits, mantissa_bits) = (self.precision.exponent_bits(), self.precision.mantissa_bits());
+        let (exponent_width, mantissa_width) = (exponent_bits.div_ceil(4) as usize, mantissa_bits.div_ceil(4) as usize);
+        let fraction = format!("{:0width$b}", self.mantissa, width = mantissa_bits as usize);
+        let sign = if self.sign == 1 { "-" } else { "" };
+        let value = match self.class() {
+            "normal" => format!("{}1.{}b × 2^{} (implied leading 1)", sign, fraction, self.unbiased()),
+            "subnormal" => format!("{}0.{}b × 2^{} (subnormal: implied leading 0)", sign, fraction, self.unbiased()),
+            zero @ ("+0" | "-0") => format!("{} (exponent and mantissa all zero)", zero),
+            infinity @ ("+infinity" | "-infinity") => format!("{} (exponent all ones, mantissa zero)", infinity),
+            nan => format!("{} (exponent all ones, top mantissa bit {})", nan, self.mantissa >> (mantissa_bits - 1)),
+        };
+        vec![
+            format!(
+                "  bits      {} {:0ewidth$b} {}",
+                self.sign,
+                self.exponent,
+                fraction,
+                ewidth = exponent_bits as usize
+            ),
+            format!("  sign      {} ({})", self.sign, if self.sign == 1 { "negative" } else { "positive" }),
+            format!(
+                "  exponent  0x{:0width$x}: biased {}, unbiased {}",
+                self.exponent,
+                self.exponent,
+                self.unbiased(),
+                width = exponent_width
+            ),
+            format!("  mantissa  0x{:0width$x}", self.mantissa, width = mantissa_width),
+            format!("  value     {}", value),
+        ]
+    }
+}
+
+// Number of representable values between two floats of the same precision,
+// counting adjacent floats as 1 apart and +0 and -0 as equal. None when
+// either side is NaN.
+pub fn ulp_distance(a: &Ieee754, b: &Ieee754) -> Option<u128> {
+    if a.value.is_nan() || b.value.is_nan() {
+        return None;
+    }
+    // Sign-magnitude bits to a scale that increases with the value
+    let ordered = |float: &Ieee754| {
+        let magnitude = (float.bits() & !(1 << (float.precision.exponent_bits() + float.precision.mantissa_bits()))) as i128;
+        if float.sign == 1 { -magnitude } else { magnitude }
+    };
+    Some((ordered(a) - ordered(b)).unsigned_abs())
+}
+
+// C99 hexadecimal float literals such as 0x1.8p3 or 0x.4p-2. The binary
+// exponent is required, as in C, so that 0x1 stays an integer.
+pub fn parse_hex_float(input: &str) -> Option<f64> {
+    let body = input.strip_prefix("0x").or_else(|| input.strip_prefix("0X"))?;
+    let (digits, exponent) = body.split_once(['p', 'P'])?;
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    let mut scale = exponent.parse::<i32>().ok()? as i64;
+    // Up to 64 significant bits, with any nonzero bits past them folded into
+    // the lowest bit so that the conversion below still rounds correctly
+    let (mut mantissa, mut sticky) = (0u64, false);
+    for (i, c) in whole.chars().chain(fraction.chars()).enumerate() {
+        let digit = c.to_digit(16)? as u64;
+        let fractional = i >= whole.len();
+        if mantissa >> 60 == 0 {
+            mantissa = mantissa << 4 | digit;
+            scale -= if fractional { 4 } else { 0 };
+        } else {
+            sticky |= digit != 0;
+            scale += if fractional { 0 } else { 4 };
+        }
+    }
+    let mut value = (mantissa | sticky as u64) as f64;
+    // Powers of two in steps that stay within the exponent range
+    while scale != 0 {
+        let step = scale.clamp(-1000, 1000);
+        value *= 2f64.powi(step as i32);
+        scale -= step;
+    }
+    Some(value)
+}
+
+// C99 %a rendering, e.g. 0x1.8p+3 for 12
+pub fn hex_float(value: f64) -> String {
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    let float = Ieee754::double(value);
+    let sign = if float.sign == 1 { "-" } else { "" };
+    match float.class() {
+        "+infinity" | "-infinity" => format!("{}inf", sign),
+        "+0" | "-0" => format!("{}0x0p+0", sign),
+        class => {
+            let lead = if class == "subnormal" { 0 } else { 1 };
+            let digits = format!("{:013x}", float.mantissa);
+            match digits.trim_end_matches('0') {
+                "" => format!("{}0x{}p{:+}", sign, lead, float.unbiased()),
+                digits => format!("{}0x{}.{}p{:+}", sign, lead, digits, float.unbiased()),
+            }
+        }
+    }
+}
+
+// Float literals: 1.5, 1.5f, 0x1.8p0 and Fx3ff8000000000000 are doubles,
+// while 1.5F and fx3fc00000 are singles. Other inputs are read as doubles of their
+// integer value.
+pub fn parse(input: &str, settings: &Settings) -> Result<Ieee754, &'static str> {
+    let bits = |digits: &str| u64::from_str_radix(digits, 16).map_err(|_| "Invalid float bit pattern");
+    if let Some(digits) = input.strip_prefix("Fx") {
+        Ok(Ieee754::double(f64::from_bits(bits(digits)?)))
+    } else if let Some(digits) = input.strip_prefix("fx") {
+        let bits = u32::try_from(bits(digits)?).map_err(|_| "Single-precision patterns have 32 bits")?;
+        Ok(Ieee754::single(f32::from_bits(bits)))
+    } else if let Some(float) = input.strip_suffix('F').and_then(|literal| literal.parse::<f32>().ok()) {
+        Ok(Ieee754::single(float))
+    } else if let Some(float) = input.strip_suffix('f').and_then(|literal| literal.parse::<f64>().ok()) {
+        Ok(Ieee754::double(float))
+    } else if let Some(float) = parse_hex_float(input) {
+        Ok(Ieee754::double(float))
+    } else if input.contains(['.', 'e', 'E']) && !input.starts_with("0x") {
+        input.parse::<f64>().map(Ieee754::double).map_err(|_| "Invalid float literal")
+    } else {
+        convert_input(input, settings, &mut Vec::new()).map(|(num, _)| Ieee754::double(num as f64))
+    }
+}
+
+// Whether `input` is written as a float rather than an integer
+pub fn is_float_literal(input: &str) -> bool {
+    input.starts_with("Fx")
+        || input.starts_with("fx")
+        || parse_hex_float(input).is_some()
+        || input.strip_suffix(['f', 'F']).is_some_and(|literal| literal.parse::<f64>().is_ok())
+        || (input.contains(['.', 'e', 'E']) && !input.starts_with("0x") && input.parse::<f64>().is_ok())
+}
@@ -0,0 +1,3637 @@
+//! Expression evaluator and base converter behind the `rax` command line.
+//! `tokenize`, `parse` and `eval` work on one line at a time; an `Env` holds
+//! the variables and functions that later lines can use.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use tracing::{debug, trace};
+
+#[cfg(feature = "bigint")]
+pub mod big;
+pub mod bitset;
+pub mod builtins;
+pub mod cancel;
+pub mod checksum;
+pub mod codec;
+pub mod color;
+pub mod decimal;
+pub mod diagnostic;
+pub mod diff;
+pub mod duration;
+pub mod fixed;
+pub mod format;
+pub mod hash;
+pub mod highlight;
+pub mod ieee;
+mod precision;
+pub mod settings;
+pub mod table;
+pub mod timestamp;
+pub mod unicode;
+
+use bitset::Bitset;
+use decimal::Decimal;
+use diagnostic::{Diagnostic, Span};
+use fixed::IntType;
+use settings::{Numerics, Settings};
+use table::Table;
+
+#[derive(Debug)]
+pub enum BaseConversionError {
+    ParseIntError,
+    InvalidInputFormat,
+}
+
+impl From<std::num::ParseIntError> for BaseConversionError {
+    fn from(_: std::num::ParseIntError) -> Self {
+        BaseConversionError::ParseIntError
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Number(String),
+    // Decimal fraction literal such as `0.1`, kept as written
+    Float(String),
+    // Imaginary literal such as `4i`, holding the digits before the `i`
+    Imaginary(String),
+    // String literal such as `"abc"`, holding its bytes
+    Bytes(Vec<u8>),
+    // Verilog sized literal such as `8'hFF`, holding its bits and the
+    // declared width, signed for `8'sh` literals
+    Sized(u128, IntType),
+    // Duration literal such as `1h30m` or `500ms`, in nanoseconds
+    Duration(i128),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    PlusMinus,
+    Amp,
+    Pipe,
+    Caret,
+    Shl,
+    Shr,
+    // Inclusive integer range `lo..hi`
+    DotDot,
+    // Comparisons, giving 1 or 0
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Assign,
+    LBrace,
+    RBrace,
+    Semicolon,
+    Ident(String),
+    // Produced by infix_to_postfix for `name[key]` lookups
+    Index(String),
+    // Produced by infix_to_postfix for `expr[i]` on any other operand
+    Subscript,
+    // Produced by infix_to_postfix for `name(args...)` with the argument count
+    Call(String, usize),
+    // Produced by infix_to_postfix for a `[a, b, ...]` literal with its length
+    List(usize),
+    // Produced by infix_to_postfix for a `(a, b, ...)` literal with its length
+    Tuple(usize),
+    // Produced by infix_to_postfix for a `(u13)` prefix cast
+    Cast(IntType),
+    // `{ let a = x; expr }`, folded into one operand by parse_expr
+    Block(Block),
+    // `if cond { a } else { b }`, folded into one operand by parse_expr
+    If(Box<Conditional>),
+    // `for x in xs { ... }` or `while cond { ... }`, folded by parse_expr
+    Loop(Box<Loop>),
+    // Prefix `~x`, the bitwise complement
+    Tilde,
+    // Produced by infix_to_postfix for a `-` that starts an operand, as in
+    // -5 or 3 * -2
+    Neg,
+    // `a % b`, the remainder with the sign of `a`
+    Percent,
+    // `a ** b`, exponentiation
+    StarStar,
+}
+
+// Only the branch selected by the condition is evaluated
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conditional {
+    cond: Vec<Spanned>,
+    then: Vec<Spanned>,
+    otherwise: Vec<Spanned>,
+}
+
+// Loops evaluate to the list of their body values
+#[derive(Debug, Clone, PartialEq)]
+pub enum Loop {
+    For { var: String, items: Vec<Spanned>, body: Block },
+    While { cond: Vec<Spanned>, body: Block },
+}
+
+// Most iterations a single loop may run, so a runaway `while` stops
+const MAX_ITERATIONS: usize = 100_000;
+
+// Statements separated by `;`. A block's value is its final expression;
+// names bound with `let` are only visible inside the block, including in
+// nested blocks, while reassigning an outer name updates it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    statements: Vec<BlockStatement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockStatement {
+    Let(String, Vec<Spanned>),
+    Set(String, Span, Vec<Spanned>),
+    Expr(Vec<Spanned>),
+}
+
+impl Block {
+    fn map(&self, f: impl Fn(Vec<Spanned>) -> Vec<Spanned>) -> Block {
+        let statements = self.statements.iter().map(|statement| match statement {
+            BlockStatement::Let(name, tokens) => BlockStatement::Let(name.clone(), f(tokens.clone())),
+            BlockStatement::Set(name, span, tokens) => BlockStatement::Set(name.clone(), span.clone(), f(tokens.clone())),
+            BlockStatement::Expr(tokens) => BlockStatement::Expr(f(tokens.clone())),
+        });
+        Block { statements: statements.collect() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spanned {
+    pub token: Token,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i128),
+    Float(f64),
+    // Exact base-10 value in decimal numerics mode
+    Decimal(Decimal),
+    // Real and imaginary parts
+    Complex(f64, f64),
+    // Closed interval [lo, hi] in interval mode
+    Interval(f64, f64),
+    List(Vec<Value>),
+    // Several results at once, e.g. from divmod
+    Tuple(Vec<Value>),
+    Bitset(Bitset),
+    // Integer wrapped to a declared width, e.g. `(u13) 0x1FFF`
+    Fixed(i128, IntType),
+    Name(String),
+    // Byte string from a "quoted" literal, shown as hex
+    Bytes(Vec<u8>),
+    // Span of time in nanoseconds, from literals such as `1h30m`
+    Duration(i128),
+    // Integer too wide for 128 bits; results that fit become Int again
+    #[cfg(feature = "bigint")]
+    Big(num_bigint::BigInt),
+}
+
+impl Value {
+    // Integer view of the value, noting when a float had to be truncated
+    pub fn as_int(&self, warnings: &mut Vec<String>) -> Result<i128, &'static str> {
+        match self {
+            Value::Int(num) | Value::Fixed(num, _) => Ok(*num),
+            Value::Float(float) => {
+                if !float.is_finite() || float.abs() >= i128::MAX as f64 {
+                    return Err("Float is out of range for a 128-bit integer");
+                }
+                let truncated = float.trunc() as i128;
+                if float.fract() != 0.0 {
+                    warnings.push(format!("{} truncated to integer {}", float, truncated));
+                }
+                Ok(truncated)
+            }
+            Value::Decimal(decimal) => {
+                let truncated = decimal.trunc();
+                if !decimal.is_integer() {
+                    warnings.push(format!("{} truncated to integer {}", decimal, truncated));
+                }
+                Ok(truncated)
+            }
+            Value::Complex(..) => Err("Expected a real number but found a complex number"),
+            Value::Interval(..) => Err("Expected a number but found an interval"),
+            Value::List(_) => Err("Expected a number but found a list"),
+            Value::Tuple(_) => Err("Expected a number but found a tuple"),
+            Value::Bitset(_) => Err("Expected a number but found a bitset"),
+            Value::Name(_) => Err("Expected a number but found a name"),
+            // Big-endian, so "AB" is 0x4142
+            Value::Bytes(bytes) if bytes.len() > 16 => Err("Byte string is longer than 16 bytes"),
+            Value::Bytes(bytes) => Ok(bytes.iter().fold(0u128, |num, &byte| num << 8 | byte as u128) as i128),
+            Value::Duration(_) => Err("Expected a number but found a duration"),
+            #[cfg(feature = "bigint")]
+            Value::Big(_) => Err("Integer exceeds 128 bits"),
+        }
+    }
+
+    fn as_complex(&self) -> Result<(f64, f64), &'static str> {
+        match self {
+            Value::Complex(re, im) => Ok((*re, *im)),
+            other => other.as_float().map(|re| (re, 0.0)),
+        }
+    }
+
+    pub fn as_float(&self) -> Result<f64, &'static str> {
+        match self {
+            Value::Fixed(num, ty) if !ty.is_signed() => Ok(*num as u128 as f64),
+            Value::Int(num) | Value::Fixed(num, _) => Ok(*num as f64),
+            Value::Float(float) => Ok(*float),
+            Value::Decimal(decimal) => Ok(decimal.to_f64()),
+            Value::Complex(..) => Err("Expected a real number but found a complex number"),
+            Value::Interval(..) => Err("Expected a number but found an interval"),
+            Value::List(_) => Err("Expected a number but found a list"),
+            Value::Tuple(_) => Err("Expected a number but found a tuple"),
+            Value::Bitset(_) => Err("Expected a number but found a bitset"),
+            Value::Name(_) => Err("Expected a number but found a name"),
+            Value::Bytes(_) => self.as_int(&mut Vec::new()).map(|num| num as u128 as f64),
+            Value::Duration(_) => Err("Expected a number but found a duration"),
+            #[cfg(feature = "bigint")]
+            Value::Big(num) => Ok(big::to_f64(num)),
+        }
+    }
+
+    // Conditions are true when nonzero
+    fn is_truthy(&self) -> Result<bool, &'static str> {
+        match self {
+            Value::Decimal(decimal) => Ok(decimal.signum().is_ne()),
+            Value::Int(_) | Value::Fixed(..) | Value::Float(_) => Ok(self.as_float()? != 0.0),
+            // Zero always fits in an Int
+            #[cfg(feature = "bigint")]
+            Value::Big(_) => Ok(true),
+            _ => Err("A condition must be a real number"),
+        }
+    }
+
+    fn compare(&self, other: &Value) -> Result<std::cmp::Ordering, &'static str> {
+        // u128 values above i128::MAX are stored negative but order after the rest
+        let int = |value: &Value| match value {
+            Value::Fixed(num, ty) if !ty.is_signed() && *num < 0 => Some((true, *num)),
+            Value::Int(num) | Value::Fixed(num, _) => Some((false, *num)),
+            _ => None,
+        };
+        if let (Some(a), Some(b)) = (int(self), int(other)) {
+            return Ok(a.cmp(&b));
+        }
+        #[cfg(feature = "bigint")]
+        if let (Some(a), Some(b)) = (self.as_big(), other.as_big()) {
+            return Ok(a.cmp(&b));
+        }
+        if let (Value::Decimal(a), Value::Decimal(b)) = (self, other) {
+            return Ok(a.checked_sub(*b).ok_or("Decimal result out of range")?.signum());
+        }
+        if let (Value::Duration(a), Value::Duration(b)) = (self, other) {
+            return Ok(a.cmp(b));
+        }
+        self.as_float()?.partial_cmp(&other.as_float()?).ok_or("Cannot compare NaN")
+    }
+
+    // Numbers compare by value, so 1 == 1.0; anything else must match exactly
+    fn equals(&self, other: &Value) -> bool {
+        match self.compare(other) {
+            Ok(ordering) => ordering.is_eq(),
+            Err(_) => self == other,
+        }
+    }
+
+    // Exact integer view for mixing bigints with machine integers
+    #[cfg(feature = "bigint")]
+    fn as_big(&self) -> Option<num_bigint::BigInt> {
+        match self {
+            Value::Int(num) => Some((*num).into()),
+            Value::Big(num) => Some(num.clone()),
+            _ => None,
+        }
+    }
+
+    // Element `index` of a list or tuple, counting from the end when negative
+    fn index(&self, index: i128) -> Result<Value, &'static str> {
+        let (Value::List(items) | Value::Tuple(items)) = self else {
+            return Err("Only lists and tuples can be indexed");
+        };
+        let position = if index < 0 { items.len() as i128 + index } else { index };
+        usize::try_from(position)
+            .ok()
+            .and_then(|position| items.get(position))
+            .cloned()
+            .ok_or("List index out of range")
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(num) => write!(f, "{}", num),
+            Value::Float(float) => write!(f, "{}", float),
+            Value::Decimal(decimal) => write!(f, "{}", decimal),
+            Value::Complex(re, im) if *re == 0.0 => write!(f, "{}i", im),
+            Value::Complex(re, im) if im.is_sign_negative() => write!(f, "{}-{}i", re, -im),
+            Value::Complex(re, im) => write!(f, "{}+{}i", re, im),
+            Value::Interval(lo, hi) => write!(f, "[{}, {}]", lo, hi),
+            Value::List(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Value::Tuple(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                write!(f, "({})", items.join(", "))
+            }
+            Value::Bitset(bits) => write!(f, "{}", bits),
+            Value::Fixed(num, ty) => write!(f, "{}", ty.display(*num)),
+            Value::Name(name) => write!(f, "{}", name),
+            Value::Bytes(bytes) if bytes.is_empty() => write!(f, "\"\""),
+            Value::Bytes(bytes) => write!(f, "0x{}", codec::hex(bytes)),
+            Value::Duration(nanos) => write!(f, "{}", duration::human(*nanos)),
+            #[cfg(feature = "bigint")]
+            Value::Big(num) => write!(f, "{}", num),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub params: Vec<String>,
+    // Postfix tokens, evaluated with the parameters bound as locals
+    body: Vec<Spanned>,
+}
+
+pub enum Statement {
+    Expr(Vec<Spanned>),
+    Assign(String, Vec<Spanned>),
+    // `a, b = expr` unpacking a tuple or list
+    Destructure(Vec<String>, Vec<Spanned>),
+    Function(String, Function),
+}
+
+const MAX_CALL_DEPTH: usize = 256;
+
+// Longest list a `lo..hi` range may produce
+pub const MAX_RANGE: i128 = 1 << 16;
+
+#[derive(Clone, Default)]
+pub struct Env {
+    pub tables: HashMap<String, Table>,
+    pub aliases: BTreeMap<String, String>,
+    pub vars: HashMap<String, Value>,
+    pub functions: HashMap<String, Function>,
+    pub settings: Settings,
+}
+
+// In the standard grammar a radix letter always names the base the digits
+// are written in: b1010 and Ox755, the forms binary and octal output take,
+// and 255d for decimal. The b and o suffixes read the same in both grammars.
+fn radix_marked(input: &str) -> Option<(u32, &str)> {
+    let marked = [
+        (2, input.strip_prefix('b')),
+        (8, input.strip_prefix("Ox")),
+        (10, input.strip_suffix('d')),
+    ];
+    marked.into_iter().find_map(|(radix, digits)| {
+        let digits = digits.filter(|digits| !digits.is_empty() && digits.chars().all(|c| c.is_digit(radix)))?;
+        Some((radix, digits))
+    })
+}
+
+pub fn parse_num(input: &str, settings: &Settings) -> Result<String, BaseConversionError> {
+    if let Some(result) = settings.formats.parse(input) {
+        // 128-bit patterns such as IPv6 addresses and UUIDs read unsigned
+        result.map(|num| if num < 0 { (num as u128).to_string() } else { num.to_string() })
+    } else if let Some(stripped) = strip_separators(input) {
+        parse_num(&stripped, settings)
+    } else if let Some(body) = strip_int_suffix(input) {
+        parse_num(body, settings)
+    } else if let Some(sized) = parse_sized(input) {
+        sized.map(|(bits, ty)| ty.display(ty.wrap(bits as i128)))
+    } else if let Some(code) = unicode::parse_codepoint(input) {
+        Ok(code.to_string())
+    } else if let Some(bytes) = codec::parse_string(input) {
+        Value::Bytes(bytes).as_int(&mut Vec::new())
+            .map(|num| num.to_string())
+            .map_err(|_| BaseConversionError::InvalidInputFormat)
+    } else if input.starts_with('\'') {
+        parse_char(input).map(|c| (c as u32).to_string()).ok_or(BaseConversionError::InvalidInputFormat)
+    } else if let Some(float) = ieee::parse_hex_float(input) {
+        Ok(float.to_string())
+    } else if let Some((radix, digits)) = input.split_once('#') {
+        // Any base from 2 to 36, as in 36#ZZ or 3#1201
+        let radix = radix.parse::<u32>().ok().filter(|radix| (2..=36).contains(radix))
+            .ok_or(BaseConversionError::InvalidInputFormat)?;
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return Err(BaseConversionError::ParseIntError);
+        }
+        match i128::from_str_radix(digits, radix) {
+            Ok(num) => Ok(num.to_string()),
+            #[cfg(feature = "bigint")]
+            Err(_) => big::parse(digits, radix)
+                .map(|num| num.to_string())
+                .ok_or(BaseConversionError::ParseIntError),
+            #[cfg(not(feature = "bigint"))]
+            Err(_) => Err(BaseConversionError::ParseIntError),
+        }
+    } else if let Some(digits) = input.strip_prefix("0b").or_else(|| input.strip_prefix("0B")) {
+        // C, Python and Rust binary, as opposed to the b prefix and suffix
+        u128::from_str_radix(digits, 2)
+            .map(|num| num.to_string())
+            .map_err(|_| BaseConversionError::ParseIntError)
+    } else if let Some(digits) = input.strip_prefix("0o").or_else(|| input.strip_prefix("0O")) {
+        u128::from_str_radix(digits, 8)
+            .map(|num| num.to_string())
+            .map_err(|_| BaseConversionError::ParseIntError)
+    } else if let Some(digits) = input.strip_prefix("0x").or_else(|| input.strip_prefix("0X")) {
+        // All 128 bits may be given; those with the top bit set stay unsigned,
+        // so 0xffffffffffffffffffffffffffffffff is 2**128 - 1 rather than -1
+        match u128::from_str_radix(digits, 16) {
+            Ok(num) => Ok(num.to_string()),
+            // Wider literals are plain positive numbers
+            #[cfg(feature = "bigint")]
+            Err(_) if digits.len() > 32 => big::parse(digits, 16)
+                .map(|num| num.to_string())
+                .ok_or(BaseConversionError::ParseIntError),
+            Err(_) => Err(BaseConversionError::ParseIntError),
+        }
+    } else if let Some((radix, digits)) = radix_marked(input).filter(|_| !settings.legacy_literals) {
+        match radix {
+            10 => Ok(digits.to_string()),
+            _ => u128::from_str_radix(digits, radix)
+                .map(|num| num.to_string())
+                .map_err(|_| BaseConversionError::ParseIntError),
+        }
+    } else if let Some(digits) = input.strip_prefix('b').filter(|_| settings.legacy_literals) {
+        digits.parse::<i128>()
+            .map(|num| format!("{:b}b", num))
+            .map_err(|_| BaseConversionError::ParseIntError)
+    } else if let Some(digits) = input.strip_prefix("Fx") {
+        u64::from_str_radix(digits, 16)
+            .map(f64::from_bits)
+            .map(|float| float.to_string())
+            .map_err(|_| BaseConversionError::ParseIntError)
+    } else if let Some(digits) = input.strip_prefix("fx") {
+        // Single precision, as in 4-byte float registers
+        u32::from_str_radix(digits, 16)
+            .map(f32::from_bits)
+            // Widened exactly, rather than via the shortest f32 rendering
+            .map(|float| (float as f64).to_string())
+            .map_err(|_| BaseConversionError::ParseIntError)
+    } else if let Some(digits) = input.strip_prefix("Bx").filter(|_| settings.legacy_literals) {
+        i128::from_str_radix(digits, 16)
+            .map(|num| format!("{:b}", num))
+            .map_err(|_| BaseConversionError::ParseIntError)
+    } else if let Some(digits) = input.strip_prefix("Ox").filter(|_| settings.legacy_literals) {
+        i128::from_str_radix(digits, 16)
+            .map(|num| format!("{:o}", num))
+            .map_err(|_| BaseConversionError::ParseIntError)
+    } else if let Some(digits) = input.strip_suffix('d').filter(|_| settings.legacy_literals) {
+        i128::from_str_radix(digits, 2)
+            .map(|num| num.to_string())
+            .map_err(|_| BaseConversionError::ParseIntError)
+    } else if let Some(digits) = input.strip_suffix('f') {
+        digits.parse::<f64>()
+            .map(|num| format!("0x{:x}", num.to_bits()))
+            .map_err(|_| BaseConversionError::ParseIntError)
+    } else if let Some(digits) = input.strip_suffix('F') {
+        digits.parse::<f32>()
+            .map(|num| format!("0x{:x}", num.to_bits()))
+            .map_err(|_| BaseConversionError::ParseIntError)
+    } else if let Some(digits) = input.strip_suffix('o') {
+        i128::from_str_radix(digits, 8)
+            .map(|num| format!("0x{:x}", num))
+            .map_err(|_| BaseConversionError::ParseIntError)
+    } else if let Some(digits) = input.strip_suffix('b') {
+        i128::from_str_radix(digits, 2)
+            .map(|num| format!("0x{:x}", num))
+            .map_err(|_| BaseConversionError::ParseIntError)
+    } else if let Some(bytes) = parse_byte_size(input) {
+        bytes
+    } else if is_scientific(input) || is_decimal_fraction(input) {
+        // Whole values become integers; others stay decimal fractions
+        input.parse::<f64>().map(|float| float.to_string()).map_err(|_| BaseConversionError::ParseIntError)
+    } else {
+        // Negative numbers stay decimal, as their hex would read back unsigned
+        input.parse::<i128>()
+            .map(|num| if num < 0 { num.to_string() } else { format!("0x{:x}", num) })
+            .map_err(|_| BaseConversionError::ParseIntError)
+    }
+}
+
+// Keep converting until the literal is a plain decimal integer
+pub fn to_int(input: &str, settings: &Settings) -> Result<i128, BaseConversionError> {
+    let mut num = input.to_string();
+    while !num.chars().all(|c| c.is_numeric() || c == '.' || c == '-') {
+        let next = parse_num(&num, settings)?;
+        trace!(from = %num, to = %next, "converted literal");
+        num = next;
+    }
+    Ok(num.parse::<i128>()?)
+}
+
+// The bits of a 128-bit literal with the top bit set, which has no i128
+// value, as for 0xffffffffffffffff8000000000000000 or ffff::
+fn unsigned_literal(input: &str, settings: &Settings) -> Option<u128> {
+    parse_num(input, settings).ok()?.parse::<u128>().ok().filter(|&bits| bits > i128::MAX as u128)
+}
+
+// An integer literal copied from C or Rust with its type suffix dropped,
+// as for 10UL, 0xffu8 or 1_000i64; None without a suffix
+fn strip_int_suffix(word: &str) -> Option<&str> {
+    const RUST: [&str; 12] = ["u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize"];
+    if !word.starts_with(|c: char| c.is_ascii_digit()) || word.contains('#') {
+        return None;
+    }
+    if let Some(body) = RUST.iter().find_map(|suffix| word.strip_suffix(suffix)) {
+        return Some(body);
+    }
+    // C's U and L in either case, with L at most doubled
+    let body = word.trim_end_matches(['u', 'U', 'l', 'L']);
+    let suffix = word[body.len()..].to_ascii_lowercase();
+    matches!(suffix.as_str(), "u" | "l" | "ul" | "lu" | "ll" | "ull" | "llu").then_some(body)
+}
+
+// A Verilog sized literal such as 8'hFF, 16'b1010 or 32'd100 as its bits
+// and declared width; 8'sh and the like make the width signed. None for
+// words without a size before the quote.
+fn parse_sized(word: &str) -> Option<Result<(u128, IntType), BaseConversionError>> {
+    let (size, rest) = word.split_once('\'')?;
+    if size.is_empty() || !size.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let (signed, rest) = match rest.strip_prefix(['s', 'S']) {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let radix = match rest.chars().next().map(|c| c.to_ascii_lowercase()) {
+        Some('b') => 2,
+        Some('o') => 8,
+        Some('d') => 10,
+        Some('h') => 16,
+        _ => return Some(Err(BaseConversionError::InvalidInputFormat)),
+    };
+    let Some(ty) = IntType::parse(&format!("{}{}", if signed { 'i' } else { 'u' }, size)) else {
+        return Some(Err(BaseConversionError::InvalidInputFormat));
+    };
+    // x and z digits have no single value, so fail like any other bad digit
+    let digits = &rest[1..];
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return Some(Err(BaseConversionError::ParseIntError));
+    }
+    Some(u128::from_str_radix(digits, radix).map(|bits| (bits, ty)).map_err(|_| BaseConversionError::ParseIntError))
+}
+
+// The declared width of a sized literal such as 8'shFF, for output that
+// keeps it
+pub fn sized_type(input: &str) -> Option<IntType> {
+    parse_sized(input)?.ok().map(|(_, ty)| ty)
+}
+
+// Whether the comma at byte `i` of `text` sits between groups of three
+// decimal digits, as in 1,000 or 12,345.5
+fn is_thousands_comma(text: &str, i: usize) -> bool {
+    let digits = |group: &str| group.bytes().all(|b| b.is_ascii_digit());
+    let groups: Vec<&str> = text[..i].split(',').collect();
+    let after = text[i + 1..].bytes().take_while(u8::is_ascii_digit).count();
+    (1..=3).contains(&groups[0].len())
+        && digits(groups[0])
+        && groups[1..].iter().all(|group| group.len() == 3 && digits(group))
+        && after == 3
+}
+
+// A number with the digit separators of source code or prose removed, such
+// as 0xDEAD_BEEF, 1_000_000 or 1,000; None if it has none
+fn strip_separators(word: &str) -> Option<String> {
+    if !word.starts_with(|c: char| c.is_ascii_digit()) || !word.contains(['_', ',']) {
+        return None;
+    }
+    let commas = word.match_indices(',').all(|(i, _)| is_thousands_comma(word, i));
+    commas.then(|| word.replace(['_', ','], ""))
+}
+
+pub fn is_identifier(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+// Replaces `( u13 )` directly before an operand with a single Cast token,
+// leaving parenthesised variables such as `(x) + 1` alone
+fn fold_casts(tokens: Vec<Spanned>) -> Vec<Spanned> {
+    let mut folded = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        if let [Spanned { token: Token::LParen, span: open }, Spanned { token: Token::Ident(name), .. }, Spanned { token: Token::RParen, span: close }, operand, ..] =
+            &tokens[i..]
+        {
+            let starts_operand = matches!(
+                operand.token,
+                Token::Number(_)
+                    | Token::Float(_)
+                    | Token::Imaginary(_)
+                    | Token::Bytes(_)
+                    | Token::Sized(..)
+                    | Token::Duration(_)
+                    | Token::Ident(_)
+                    | Token::Block(_)
+                    | Token::If(_)
+                    | Token::Loop(_)
+                    | Token::LParen
+                    | Token::LBracket
+                    | Token::Minus
+                    | Token::Tilde
+            );
+            if let Some(ty) = IntType::parse(name).filter(|_| starts_operand) {
+                folded.push(Spanned { token: Token::Cast(ty), span: open.start..close.end });
+                i += 3;
+                continue;
+            }
+        }
+        folded.push(tokens[i].clone());
+        i += 1;
+    }
+    folded
+}
+
+// How tightly an operator on the stack binds: `**`, then prefix operators
+// (so ~2 ** 3 is ~(2 ** 3) and -2 ** 2 is -4), then as in Rust `* / %`,
+// `+ - ±`, shifts, `&`, `^`, `|`, comparisons and finally `..`. Brackets are
+// 0 so that no operator pops past them.
+fn precedence(token: &Token) -> u8 {
+    match token {
+        Token::StarStar => 10,
+        Token::Cast(_) | Token::Tilde | Token::Neg => 9,
+        Token::Star | Token::Slash | Token::Percent => 8,
+        Token::Plus | Token::Minus | Token::PlusMinus => 7,
+        Token::Shl | Token::Shr => 6,
+        Token::Amp => 5,
+        Token::Caret => 4,
+        Token::Pipe => 3,
+        Token::Eq | Token::Ne | Token::Lt | Token::Le | Token::Gt | Token::Ge => 2,
+        Token::DotDot => 1,
+        _ => 0,
+    }
+}
+
+fn infix_to_postfix(tokens: Vec<Spanned>) -> Vec<Spanned> {
+    // implements shunting yard algorithm to convert Vec<Token>
+    // to reverse polish notation
+    let tokens = fold_casts(tokens);
+
+    let mut output_queue: Vec<Spanned> = Vec::new();
+    let mut operator_stack: Vec<Spanned> = Vec::new();
+    // Argument counts of the function calls currently open
+    let mut arg_counts: Vec<usize> = Vec::new();
+
+    // Whether the previous token ended an operand, making `[` a subscript
+    let mut after_operand = false;
+
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(mut spanned) = tokens.next() {
+        if spanned.token == Token::Minus && !after_operand {
+            spanned.token = Token::Neg;
+        }
+        let next = tokens.peek().map(|next| next.token.clone());
+        let ends_operand = match spanned.token {
+            Token::Ident(_) => !matches!(next, Some(Token::LBracket | Token::LParen)),
+            Token::Number(_)
+            | Token::Float(_)
+            | Token::Imaginary(_)
+            | Token::Bytes(_)
+            | Token::Sized(..)
+            | Token::Duration(_)
+            | Token::Block(_)
+            | Token::If(_)
+            | Token::Loop(_)
+            | Token::RParen
+            | Token::RBracket => true,
+            _ => false,
+        };
+        match &spanned.token {
+            Token::Number(_)
+            | Token::Float(_)
+            | Token::Imaginary(_)
+            | Token::Bytes(_)
+            | Token::Sized(..)
+            | Token::Duration(_) => output_queue.push(spanned),
+            Token::Block(block) => {
+                let block = block.map(infix_to_postfix);
+                output_queue.push(Spanned { token: Token::Block(block), span: spanned.span });
+            }
+            Token::Loop(repeat) => {
+                let repeat = match repeat.as_ref() {
+                    Loop::For { var, items, body } => Loop::For {
+                        var: var.clone(),
+                        items: infix_to_postfix(items.clone()),
+                        body: body.map(infix_to_postfix),
+                    },
+                    Loop::While { cond, body } => Loop::While {
+                        cond: infix_to_postfix(cond.clone()),
+                        body: body.map(infix_to_postfix),
+                    },
+                };
+                output_queue.push(Spanned { token: Token::Loop(Box::new(repeat)), span: spanned.span });
+            }
+            Token::If(conditional) => {
+                let conditional = Conditional {
+                    cond: infix_to_postfix(conditional.cond.clone()),
+                    then: infix_to_postfix(conditional.then.clone()),
+                    otherwise: infix_to_postfix(conditional.otherwise.clone()),
+                };
+                output_queue.push(Spanned { token: Token::If(Box::new(conditional)), span: spanned.span });
+            }
+            // Folded into blocks by parse_expr
+            Token::LBrace | Token::RBrace | Token::Semicolon => {}
+            Token::Ident(name) => {
+                if next == Some(Token::LBracket) {
+                    operator_stack.push(Spanned { token: Token::Index(name.clone()), span: spanned.span });
+                } else if next == Some(Token::LParen) {
+                    let lparen = tokens.next().unwrap();
+                    let argc = if tokens.peek().is_some_and(|t| t.token == Token::RParen) { 0 } else { 1 };
+                    arg_counts.push(argc);
+                    operator_stack.push(Spanned { token: Token::Call(name.clone(), argc), span: spanned.span });
+                    operator_stack.push(lparen);
+                } else {
+                    output_queue.push(spanned);
+                }
+            }
+            Token::Comma => {
+                while let Some(op) = operator_stack.last() {
+                    if !matches!(op.token, Token::LParen | Token::LBracket) {
+                        output_queue.push(operator_stack.pop().unwrap());
+                    } else {
+                        break;
+                    }
+                }
+                if let Some(count) = arg_counts.last_mut() {
+                    *count += 1;
+                }
+            }
+            Token::Plus
+            | Token::Minus
+            | Token::Star
+            | Token::Slash
+            | Token::Percent
+            | Token::StarStar
+            | Token::PlusMinus
+            | Token::Amp
+            | Token::Pipe
+            | Token::Caret
+            | Token::Shl
+            | Token::Shr
+            | Token::DotDot
+            | Token::Eq
+            | Token::Ne
+            | Token::Lt
+            | Token::Le
+            | Token::Gt
+            | Token::Ge => {
+                // Left-associative operators pop equal precedence too, so
+                // 8 - 2 - 1 is (8 - 2) - 1, while 2 ** 3 ** 2 is 2 ** (3 ** 2)
+                let rank = precedence(&spanned.token);
+                let right_associative = spanned.token == Token::StarStar;
+                while let Some(op) = operator_stack.last() {
+                    let top = precedence(&op.token);
+                    if top > rank || (top == rank && !right_associative) {
+                        output_queue.push(operator_stack.pop().unwrap());
+                    } else {
+                        break;
+                    }
+                }
+                operator_stack.push(spanned);
+            }
+            Token::LBracket => {
+                if after_operand {
+                    operator_stack.push(Spanned { token: Token::Subscript, span: spanned.span.clone() });
+                }
+                let count = if next == Some(Token::RBracket) { 0 } else { 1 };
+                arg_counts.push(count);
+                operator_stack.push(spanned);
+            }
+            Token::LParen => {
+                // Counts the elements of a `(a, b)` tuple
+                arg_counts.push(1);
+                operator_stack.push(spanned);
+            }
+            Token::Index(_) => operator_stack.push(spanned),
+            Token::RParen => {
+                let mut start = spanned.span.start;
+                while let Some(op) = operator_stack.pop() {
+                    if op.token == Token::LParen {
+                        start = op.span.start;
+                        break;
+                    } else {
+                        output_queue.push(op);
+                    }
+                }
+                let count = arg_counts.pop().unwrap_or(0);
+                if let Some(Token::Call(name, _)) = operator_stack.last().map(|op| &op.token) {
+                    let token = Token::Call(name.clone(), count);
+                    let call = operator_stack.pop().unwrap();
+                    output_queue.push(Spanned { token, span: call.span.start..spanned.span.end });
+                } else if count > 1 {
+                    output_queue.push(Spanned { token: Token::Tuple(count), span: start..spanned.span.end });
+                }
+            }
+            // Only produced here, never by the tokenizer
+            Token::Call(..) | Token::List(_) | Token::Tuple(_) | Token::Subscript | Token::Assign => {
+                output_queue.push(spanned)
+            }
+            // Applies to the operand that follows, so waits on the stack
+            Token::Cast(_) | Token::Tilde | Token::Neg => operator_stack.push(spanned),
+            Token::RBracket => {
+                let mut start = spanned.span.start;
+                while let Some(op) = operator_stack.pop() {
+                    if op.token == Token::LBracket {
+                        start = op.span.start;
+                        break;
+                    } else {
+                        output_queue.push(op);
+                    }
+                }
+                let count = arg_counts.pop().unwrap_or(0);
+                if let Some(Token::Index(_) | Token::Subscript) = operator_stack.last().map(|op| &op.token) {
+                    let mut index = operator_stack.pop().unwrap();
+                    index.span.end = spanned.span.end;
+                    output_queue.push(index);
+                } else {
+                    output_queue.push(Spanned { token: Token::List(count), span: start..spanned.span.end });
+                }
+            }
+        }
+        after_operand = ends_operand;
+    }
+
+    while let Some(op) = operator_stack.pop() {
+        output_queue.push(op);
+    }
+
+    debug!(postfix = ?output_queue.iter().map(|t| &t.token).collect::<Vec<_>>(), "converted to postfix");
+    output_queue
+}
+
+fn word_token(word: &str, settings: &Settings) -> Result<Token, BaseConversionError> {
+    if word.starts_with('"') {
+        return codec::parse_string(word).map(Token::Bytes).ok_or(BaseConversionError::InvalidInputFormat);
+    }
+    let stripped = strip_separators(word);
+    let word = stripped.as_deref().unwrap_or(word);
+    if let Some(sized) = parse_sized(word) {
+        return sized.map(|(bits, ty)| Token::Sized(bits, ty));
+    }
+    if is_scientific(word) {
+        debug!(word, "parsed scientific notation literal");
+        return Ok(Token::Float(word.to_string()));
+    }
+    if is_decimal_fraction(word) {
+        debug!(word, "parsed decimal fraction literal");
+        return Ok(Token::Float(word.to_string()));
+    }
+    if let Some(nanos) = duration::parse(word) {
+        debug!(word, nanos, "parsed duration literal");
+        return Ok(Token::Duration(nanos));
+    }
+    if let Some(bits) = unsigned_literal(word, settings) {
+        return Ok(Token::Sized(bits, IntType::U128));
+    }
+    match parse_num(word, settings) {
+        Ok(num) => {
+            debug!(word, converted = %num, "parsed number literal");
+            Ok(Token::Number(num))
+        }
+        Err(_) if is_identifier(word) => {
+            debug!(word, "not a number literal, treating as identifier");
+            Ok(Token::Ident(word.to_string()))
+        }
+        Err(_) if word.strip_suffix('i').is_some_and(|digits| {
+            digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') && digits.parse::<f64>().is_ok()
+        }) => {
+            debug!(word, "parsed imaginary literal");
+            Ok(Token::Imaginary(word[..word.len() - 1].to_string()))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+// Character literals such as 'A', '\n' or '\u{1F600}', which stand for
+// their code point
+pub fn parse_char(input: &str) -> Option<char> {
+    let body = input.strip_prefix('\'')?.strip_suffix('\'')?;
+    let mut chars = body.chars();
+    let c = match (chars.next()?, chars.as_str()) {
+        ('\\', "n") => '\n',
+        ('\\', "r") => '\r',
+        ('\\', "t") => '\t',
+        ('\\', "0") => '\0',
+        ('\\', escaped @ ("\\" | "'" | "\"")) => escaped.chars().next()?,
+        ('\\', unicode) => {
+            let digits = unicode.strip_prefix("u{")?.strip_suffix('}')?;
+            char::from_u32(u32::from_str_radix(digits, 16).ok()?)?
+        }
+        (c, "") => c,
+        _ => return None,
+    };
+    Some(c)
+}
+
+// The literal parse_char reads back, e.g. 'A' or '\n'
+pub fn char_literal(c: char) -> String {
+    match c {
+        '"' => "'\"'".to_string(),
+        c => format!("'{}'", c.escape_debug()),
+    }
+}
+
+// The sign of an exponent, as in a hex float's 0x1p-3 or a decimal's 2E-3
+fn expects_exponent_sign(word: &str) -> bool {
+    if word.starts_with("0x") || word.starts_with("0X") {
+        return word.ends_with(['p', 'P']) && word.matches(['p', 'P']).count() == 1;
+    }
+    word.strip_suffix(['e', 'E']).is_some_and(is_decimal_mantissa)
+}
+
+// Digits with at most one decimal point, such as 12, 1.5 or .5
+fn is_decimal_mantissa(text: &str) -> bool {
+    text.bytes().any(|b| b.is_ascii_digit())
+        && text.bytes().all(|b| b.is_ascii_digit() || b == b'.')
+        && text.matches('.').count() <= 1
+}
+
+// A byte count with a unit, as in 4MiB or 1.5GB: KiB, MiB and so on are
+// powers of 1024 and kB, MB and so on powers of 1000. Fractions of a byte
+// stay decimal fractions. None for words without a byte unit.
+fn parse_byte_size(input: &str) -> Option<Result<String, BaseConversionError>> {
+    let (mantissa, unit) = input.split_at(input.find(|c: char| c.is_ascii_alphabetic())?);
+    let multiplier: u128 = match unit {
+        "B" => 1,
+        "kB" | "KB" => 1000,
+        "MB" => 1000u128.pow(2),
+        "GB" => 1000u128.pow(3),
+        "TB" => 1000u128.pow(4),
+        "PB" => 1000u128.pow(5),
+        "EB" => 1000u128.pow(6),
+        "KiB" => 1 << 10,
+        "MiB" => 1 << 20,
+        "GiB" => 1 << 30,
+        "TiB" => 1 << 40,
+        "PiB" => 1 << 50,
+        "EiB" => 1 << 60,
+        _ => return None,
+    };
+    if !is_decimal_mantissa(mantissa) {
+        return None;
+    }
+    // Exact arithmetic on the digits, so that 1.5GB is exactly 1500000000
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let scale = 10u128.checked_pow(fraction.len() as u32);
+    let product = format!("{}{}", whole, fraction).parse::<u128>().ok().and_then(|digits| digits.checked_mul(multiplier));
+    let (Some(product), Some(scale)) = (product, scale) else {
+        return Some(Err(BaseConversionError::ParseIntError));
+    };
+    Some(Ok(match product % scale {
+        0 => (product / scale).to_string(),
+        _ => (product as f64 / scale as f64).to_string(),
+    }))
+}
+
+// Whether `rest` starts with the four to six hex digits of a code point
+// and nothing else of the same word
+fn is_codepoint_digits(rest: &str) -> bool {
+    let digits = rest.bytes().take_while(u8::is_ascii_hexdigit).count();
+    (4..=6).contains(&digits) && !rest[digits..].starts_with(|c: char| c.is_alphanumeric() || c == '_')
+}
+
+// A decimal fraction such as 0.0047 or .5
+fn is_decimal_fraction(word: &str) -> bool {
+    word.contains('.') && is_decimal_mantissa(word)
+}
+
+// Decimal scientific notation such as 1.5e6 or 2E-3
+fn is_scientific(word: &str) -> bool {
+    word.split_once(['e', 'E']).is_some_and(|(mantissa, exponent)| {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        is_decimal_mantissa(mantissa) && !exponent.is_empty() && exponent.bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+fn push_word(input: &str, span: Span, tokens: &mut Vec<Spanned>, settings: &Settings) -> Result<(), Diagnostic> {
+    let word = &input[span.clone()];
+    match word_token(word, settings) {
+        Ok(token) => {
+            tokens.push(Spanned { token, span });
+            Ok(())
+        }
+        Err(_) => Err(Diagnostic::new(format!("Could not parse number {}", word), span)),
+    }
+}
+
+// Tokenizes the whole input even after a bad literal so that every
+// problem on the line is reported at once
+fn parse_expr(input: &str, settings: &Settings) -> Result<Vec<Spanned>, Vec<Diagnostic>> {
+    let (tokens, diagnostics) = tokenize(input, settings);
+    if diagnostics.is_empty() {
+        check_brackets(&tokens).and_then(|()| fold_blocks(tokens)).map_err(|diagnostic| vec![diagnostic])
+    } else {
+        Err(diagnostics)
+    }
+}
+
+// Every token that could be read, plus a diagnostic for each bad literal,
+// reading literals in the grammar `settings` selects
+pub fn tokenize(input: &str, settings: &Settings) -> (Vec<Spanned>, Vec<Diagnostic>) {
+    let mut tokens = Vec::<Spanned>::new();
+    let mut diagnostics = Vec::<Diagnostic>::new();
+    let mut word_start: Option<usize> = None;
+    // Brackets open around the current character; commas inside them
+    // separate arguments and items rather than digits
+    let mut depth = 0usize;
+    let mut push_word = |span: Span, tokens: &mut Vec<Spanned>| {
+        if let Err(diagnostic) = push_word(input, span, tokens, settings) {
+            diagnostics.push(diagnostic);
+        }
+    };
+
+    let mut chars = input.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, next)| next);
+        let token = match c {
+            // A character or string literal is one word even if it holds a
+            // space or an operator, as in ' ' or "a + b"
+            quote @ ('\'' | '"') if word_start.is_none() => {
+                let (mut end, mut escaped) = (input.len(), false);
+                for (j, c) in chars.by_ref() {
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        _ if c == quote => {
+                            end = j + 1;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                push_word(i..end, &mut tokens);
+                continue;
+            }
+            // The sign of an exponent, as in 0x1p-3 or 2E-3
+            '+' | '-' if word_start.is_some_and(|start| expects_exponent_sign(&input[start..i])) => continue,
+            // A thousands separator, as in 1,000, outside any brackets
+            ',' if depth == 0 && word_start.is_some_and(|start| is_thousands_comma(&input[start..], i - start)) => {
+                continue
+            }
+            // The + of a code point, as in U+1F600. Code points have four to
+            // six hex digits, as Unicode writes them, so U+1 adds 1 to U.
+            '+' if word_start.is_some_and(|start| &input[start..i] == "U") && is_codepoint_digits(&input[i + 1..]) => {
+                continue
+            }
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '/' => Token::Slash,
+            '*' if next == Some('*') => Token::StarStar,
+            '*' => Token::Star,
+            '%' => Token::Percent,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            ',' => Token::Comma,
+            '=' if next == Some('=') => Token::Eq,
+            '!' if next == Some('=') => Token::Ne,
+            '<' if next == Some('=') => Token::Le,
+            '>' if next == Some('=') => Token::Ge,
+            '=' => Token::Assign,
+            '±' => Token::PlusMinus,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            ';' => Token::Semicolon,
+            '&' => Token::Amp,
+            '|' => Token::Pipe,
+            '^' => Token::Caret,
+            '~' => Token::Tilde,
+            '<' if next == Some('<') => Token::Shl,
+            '>' if next == Some('>') => Token::Shr,
+            '.' if next == Some('.') => Token::DotDot,
+            '<' => Token::Lt,
+            '>' => Token::Gt,
+            c if c.is_whitespace() => {
+                if let Some(start) = word_start.take() {
+                    push_word(start..i, &mut tokens);
+                }
+                continue;
+            }
+            _ => {
+                word_start.get_or_insert(i);
+                continue;
+            }
+        };
+        if let Some(start) = word_start.take() {
+            push_word(start..i, &mut tokens);
+        }
+        match token {
+            Token::LParen | Token::LBracket | Token::LBrace => depth += 1,
+            Token::RParen | Token::RBracket | Token::RBrace => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        let mut end = i + c.len_utf8();
+        if matches!(
+            token,
+            Token::Shl | Token::Shr | Token::DotDot | Token::Eq | Token::Ne | Token::Le | Token::Ge | Token::StarStar
+        ) {
+            chars.next();
+            end += 1;
+        }
+        tokens.push(Spanned { token, span: i..end });
+    }
+    if let Some(start) = word_start {
+        push_word(start..input.len(), &mut tokens);
+    }
+    (tokens, diagnostics)
+}
+
+// A ) or ] with nothing open to close, or a comma outside any brackets,
+// would otherwise be dropped by the shunting yard, so 1,2 gave 2. Commas
+// before an = list destructuring targets, as in q, r = divmod(17, 5).
+fn check_brackets(tokens: &[Spanned]) -> Result<(), Diagnostic> {
+    let targets = tokens.iter().position(|t| t.token == Token::Assign).unwrap_or(0);
+    let mut depth = 0usize;
+    for (i, spanned) in tokens.iter().enumerate() {
+        match spanned.token {
+            Token::LParen | Token::LBracket => depth += 1,
+            Token::RParen | Token::RBracket if depth == 0 => {
+                let bracket = if spanned.token == Token::RParen { ")" } else { "]" };
+                return Err(Diagnostic::new(format!("Unmatched {}", bracket), spanned.span.clone()));
+            }
+            Token::RParen | Token::RBracket => depth -= 1,
+            Token::Comma if depth == 0 && i >= targets => {
+                let message = "Commas only separate arguments, list items and tuple elements";
+                return Err(Diagnostic::new(message, spanned.span.clone()));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Replaces each `{ ... }` with a single Block token
+fn fold_blocks(tokens: Vec<Spanned>) -> Result<Vec<Spanned>, Diagnostic> {
+    let mut folded = Vec::new();
+    let mut tokens = tokens.into_iter();
+    while let Some(spanned) = tokens.next() {
+        match spanned.token {
+            Token::LBrace => folded.push(parse_block(spanned.span.start, &mut tokens)?),
+            Token::RBrace => return Err(Diagnostic::new("Unmatched }", spanned.span)),
+            Token::Semicolon => return Err(Diagnostic::new("; is only allowed inside a { } block", spanned.span)),
+            _ => folded.push(spanned),
+        }
+    }
+    fold_control(folded)
+}
+
+fn is_keyword(spanned: Option<&Spanned>, keyword: &str) -> bool {
+    matches!(spanned, Some(Spanned { token: Token::Ident(name), .. }) if name == keyword)
+}
+
+// Replaces each `if cond { a } else { b }`, `for x in xs { ... }` and
+// `while cond { ... }` around already folded blocks with a single token
+fn fold_control(tokens: Vec<Spanned>) -> Result<Vec<Spanned>, Diagnostic> {
+    let mut folded = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if is_keyword(tokens.get(i), "if") {
+            let (conditional, next) = parse_if(&tokens, i)?;
+            folded.push(conditional);
+            i = next;
+        } else if is_keyword(tokens.get(i), "for") || is_keyword(tokens.get(i), "while") {
+            let (repeat, next) = parse_loop(&tokens, i)?;
+            folded.push(repeat);
+            i = next;
+        } else {
+            folded.push(tokens[i].clone());
+            i += 1;
+        }
+    }
+    Ok(folded)
+}
+
+// Parses the `if` at `start`, returning it and the index after its last branch
+fn parse_if(tokens: &[Spanned], start: usize) -> Result<(Spanned, usize), Diagnostic> {
+    let keyword = &tokens[start];
+    let Some(then) = (start + 1..tokens.len()).find(|&i| matches!(tokens[i].token, Token::Block(_))) else {
+        return Err(Diagnostic::new("Expected { after the if condition", keyword.span.clone()));
+    };
+    let cond = tokens[start + 1..then].to_vec();
+    if cond.is_empty() {
+        return Err(Diagnostic::new("Expected a condition after if", keyword.span.clone()));
+    }
+    if !is_keyword(tokens.get(then + 1), "else") {
+        let span = tokens[then].span.clone();
+        return Err(Diagnostic::new("An if expression needs an else branch", span));
+    }
+    let (otherwise, end) = match tokens.get(then + 2) {
+        Some(block @ Spanned { token: Token::Block(_), .. }) => (block.clone(), then + 3),
+        Some(Spanned { token: Token::Ident(name), .. }) if name == "if" => parse_if(tokens, then + 2)?,
+        _ => return Err(Diagnostic::new("Expected { or if after else", tokens[then + 1].span.clone())),
+    };
+    let span = keyword.span.start..otherwise.span.end;
+    let conditional = Conditional { cond: fold_control(cond)?, then: vec![tokens[then].clone()], otherwise: vec![otherwise] };
+    Ok((Spanned { token: Token::If(Box::new(conditional)), span }, end))
+}
+
+// Parses the `for` or `while` at `start`, returning it and the index after
+// its body
+fn parse_loop(tokens: &[Spanned], start: usize) -> Result<(Spanned, usize), Diagnostic> {
+    let keyword = &tokens[start];
+    let Some(end) = (start + 1..tokens.len()).find(|&i| matches!(tokens[i].token, Token::Block(_))) else {
+        return Err(Diagnostic::new("Expected { after the loop header", keyword.span.clone()));
+    };
+    let Token::Block(body) = &tokens[end].token else { unreachable!() };
+    let (header, body) = (&tokens[start + 1..end], body.clone());
+    let span = keyword.span.start..tokens[end].span.end;
+    let repeat = if is_keyword(Some(keyword), "for") {
+        match header {
+            [Spanned { token: Token::Ident(var), .. }, Spanned { token: Token::Ident(word), .. }, items @ ..]
+                if word == "in" && !items.is_empty() =>
+            {
+                Loop::For { var: var.clone(), items: fold_control(items.to_vec())?, body }
+            }
+            _ => return Err(Diagnostic::new("Expected `for name in values`", covering_span(header, span.start))),
+        }
+    } else {
+        if header.is_empty() {
+            return Err(Diagnostic::new("Expected a condition after while", keyword.span.clone()));
+        }
+        Loop::While { cond: fold_control(header.to_vec())?, body }
+    };
+    Ok((Spanned { token: Token::Loop(Box::new(repeat)), span }, end + 1))
+}
+
+// Parses the rest of a block opened at `start`: `let name = expr` bindings
+// separated by `;`, then the expression giving the block's value
+fn parse_block(start: usize, tokens: &mut impl Iterator<Item = Spanned>) -> Result<Spanned, Diagnostic> {
+    let mut segments = vec![Vec::new()];
+    let end = loop {
+        let Some(spanned) = tokens.next() else {
+            return Err(Diagnostic::new("Unclosed {", start..start + 1));
+        };
+        match spanned.token {
+            Token::LBrace => {
+                let inner = parse_block(spanned.span.start, tokens)?;
+                segments.last_mut().unwrap().push(inner);
+            }
+            Token::Semicolon => segments.push(Vec::new()),
+            Token::RBrace => break spanned.span.end,
+            _ => segments.last_mut().unwrap().push(spanned),
+        }
+    };
+    // Empty statements come from blank lines in multi-line blocks
+    segments.retain(|segment| !segment.is_empty());
+    let mut statements = Vec::new();
+    for segment in segments {
+        let segment = fold_control(segment)?;
+        let statement = match segment.as_slice() {
+            [Spanned { token: Token::Ident(keyword), .. }, Spanned { token: Token::Ident(name), .. }, Spanned { token: Token::Assign, .. }, value @ ..]
+                if keyword == "let" && !value.is_empty() =>
+            {
+                BlockStatement::Let(name.clone(), value.to_vec())
+            }
+            [Spanned { token: Token::Ident(name), span }, Spanned { token: Token::Assign, .. }, value @ ..] if !value.is_empty() => {
+                BlockStatement::Set(name.clone(), span.clone(), value.to_vec())
+            }
+            _ if segment.iter().any(|t| t.token == Token::Assign) || is_keyword(segment.first(), "let") => {
+                return Err(Diagnostic::new("Expected `let name = value` or `name = value`", covering_span(&segment, start)));
+            }
+            _ => BlockStatement::Expr(segment),
+        };
+        statements.push(statement);
+    }
+    Ok(Spanned { token: Token::Block(Block { statements }), span: start..end })
+}
+
+// Smallest span covering every token, or an empty span at `fallback`
+fn covering_span(tokens: &[Spanned], fallback: usize) -> Span {
+    let start = tokens.iter().map(|t| t.span.start).min();
+    let end = tokens.iter().map(|t| t.span.end).max();
+    match (start, end) {
+        (Some(start), Some(end)) => start..end,
+        _ => fallback..fallback,
+    }
+}
+
+fn pop(stack: &mut Vec<Value>) -> Result<Value, &'static str> {
+    stack.pop().ok_or("Invalid expression")
+}
+
+fn pop_int(stack: &mut Vec<Value>, warnings: &mut Vec<String>) -> Result<i128, &'static str> {
+    pop(stack)?.as_int(warnings)
+}
+
+fn interval_bounds(value: &Value) -> Result<(f64, f64), &'static str> {
+    match value {
+        Value::Interval(lo, hi) => Ok((*lo, *hi)),
+        other => other.as_float().map(|x| (x, x)),
+    }
+}
+
+// Interval arithmetic: the result holds every value the operation could
+// produce for operands anywhere within their bounds
+fn interval_op(op: &Token, lhs: &Value, rhs: &Value) -> Result<Value, &'static str> {
+    let (a, b) = interval_bounds(lhs)?;
+    let (c, d) = interval_bounds(rhs)?;
+    let products = |(c, d): (f64, f64)| {
+        let candidates = [a * c, a * d, b * c, b * d];
+        let lo = candidates.iter().copied().fold(f64::INFINITY, f64::min);
+        let hi = candidates.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Value::Interval(lo, hi)
+    };
+    Ok(match op {
+        Token::Plus => Value::Interval(a + c, b + d),
+        Token::Minus => Value::Interval(a - d, b - c),
+        Token::Star => products((c, d)),
+        Token::Slash if c <= 0.0 && d >= 0.0 => return Err("Division by an interval containing zero"),
+        Token::Slash => products((1.0 / d, 1.0 / c)),
+        Token::Percent => return Err("Remainder is not defined for intervals"),
+        Token::StarStar if c != d || c < 0.0 || c.fract() != 0.0 => {
+            return Err("Intervals can only be raised to a whole non-negative power")
+        }
+        Token::StarStar if c == 0.0 => Value::Interval(1.0, 1.0),
+        Token::StarStar => {
+            let (lo, hi) = (a.powf(c), b.powf(c));
+            // Odd powers keep the order; even ones fold negative bounds over
+            if c % 2.0 == 1.0 || a >= 0.0 {
+                Value::Interval(lo, hi)
+            } else if b <= 0.0 {
+                Value::Interval(hi, lo)
+            } else {
+                Value::Interval(0.0, lo.max(hi))
+            }
+        }
+        _ => unreachable!(), // Only called for arithmetic operators
+    })
+}
+
+fn decimal_op(op: &Token, a: Decimal, b: Decimal) -> Result<Value, &'static str> {
+    let result = match op {
+        Token::Plus => a.checked_add(b),
+        Token::Minus => a.checked_sub(b),
+        Token::Star => a.checked_mul(b),
+        Token::Slash if b == Decimal::from_int(0) => return Err("Division by zero"),
+        Token::Slash => a.checked_div(b),
+        Token::Percent if b == Decimal::from_int(0) => return Err("Division by zero"),
+        Token::Percent => a.checked_rem(b),
+        Token::StarStar if !b.is_integer() || b.signum().is_lt() => {
+            return Err("Decimal powers need a whole non-negative exponent")
+        }
+        Token::StarStar => u64::try_from(b.trunc()).ok().and_then(|exp| a.checked_pow(exp)),
+        _ => unreachable!(), // Only called for arithmetic operators
+    };
+    result.map(Value::Decimal).ok_or("Decimal result out of range")
+}
+
+fn complex_op(op: &Token, lhs: &Value, rhs: &Value) -> Result<Value, &'static str> {
+    let (a, b) = lhs.as_complex()?;
+    let (c, d) = rhs.as_complex()?;
+    let (re, im) = match op {
+        Token::Plus => (a + c, b + d),
+        Token::Minus => (a - c, b - d),
+        Token::Star => (a * c - b * d, a * d + b * c),
+        Token::Slash if c == 0.0 && d == 0.0 => return Err("Division by zero"),
+        Token::Slash => {
+            let norm = c * c + d * d;
+            ((a * c + b * d) / norm, (b * c - a * d) / norm)
+        }
+        Token::Percent => return Err("Remainder is not defined for complex numbers"),
+        Token::StarStar if a == 0.0 && b == 0.0 => (if c == 0.0 && d == 0.0 { 1.0 } else { 0.0 }, 0.0),
+        // z ** w = exp(w ln z)
+        Token::StarStar => {
+            let (ln_r, theta) = (a.hypot(b).ln(), b.atan2(a));
+            let (x, y) = (c * ln_r - d * theta, c * theta + d * ln_r);
+            (x.exp() * y.cos(), x.exp() * y.sin())
+        }
+        _ => unreachable!(), // Only called for arithmetic operators
+    };
+    Ok(Value::Complex(re, im))
+}
+
+// Integers stay integers (wrapping with a warning on overflow); decimals
+// absorb integers; anything involving a float is computed in floating point.
+// Lists apply the operation element-wise.
+fn binary_op(
+    op: &Token,
+    lhs: Value,
+    rhs: Value,
+    settings: &Settings,
+    warnings: &mut Vec<String>,
+) -> Result<Value, &'static str> {
+    match (lhs, rhs) {
+        (Value::List(a), Value::List(b)) => {
+            if a.len() != b.len() {
+                return Err("Lists must have the same length");
+            }
+            let items = a.into_iter().zip(b).map(|(a, b)| binary_op(op, a, b, settings, warnings));
+            items.collect::<Result<_, _>>().map(Value::List)
+        }
+        (Value::List(a), b) => {
+            let items = a.into_iter().map(|a| binary_op(op, a, b.clone(), settings, warnings));
+            items.collect::<Result<_, _>>().map(Value::List)
+        }
+        (a, Value::List(b)) => {
+            let items = b.into_iter().map(|b| binary_op(op, a.clone(), b, settings, warnings));
+            items.collect::<Result<_, _>>().map(Value::List)
+        }
+        (lhs, rhs) => match settings.wrap {
+            Some(modulus)
+                if matches!(op, Token::Plus | Token::Minus | Token::Star | Token::Slash | Token::Percent | Token::StarStar) =>
+            {
+                modular_op(op, lhs, rhs, modulus, settings, warnings)
+            }
+            _ => scalar_op(op, lhs, rhs, settings, warnings),
+        },
+    }
+}
+
+// Arithmetic under `:set wrap N`: integer sums and products are reduced
+// exactly; other results are reduced after the usual operation
+fn modular_op(
+    op: &Token,
+    lhs: Value,
+    rhs: Value,
+    modulus: i64,
+    settings: &Settings,
+    warnings: &mut Vec<String>,
+) -> Result<Value, &'static str> {
+    if let (Value::Int(a), Value::Int(b)) = (&lhs, &rhs) {
+        // Residues are below 2^63, so sums and products cannot overflow
+        let modulus = modulus as i128;
+        let (a, b, exp) = (a.rem_euclid(modulus), b.rem_euclid(modulus), *b);
+        let exact = match op {
+            Token::Plus => Some(a + b),
+            Token::Minus => Some(a - b),
+            Token::Star => Some(a * b),
+            // Modular exponentiation, exact for any exponent
+            Token::StarStar if exp >= 0 => {
+                let (mut base, mut exp, mut result) = (a, exp, 1 % modulus);
+                while exp > 0 {
+                    if exp & 1 == 1 {
+                        result = result * base % modulus;
+                    }
+                    base = base * base % modulus;
+                    exp >>= 1;
+                }
+                Some(result)
+            }
+            _ => None,
+        };
+        if let Some(exact) = exact {
+            return Ok(Value::Int(exact.rem_euclid(modulus)));
+        }
+    }
+    let modulus = modulus as i128;
+    Ok(match scalar_op(op, lhs, rhs, settings, warnings)? {
+        Value::Int(num) => Value::Int(num.rem_euclid(modulus)),
+        #[cfg(feature = "bigint")]
+        Value::Big(num) => {
+            let modulus = num_bigint::BigInt::from(modulus);
+            big::demote((num % &modulus + &modulus) % modulus)
+        }
+        Value::Float(float) => Value::Float(float.rem_euclid(modulus as f64)),
+        Value::Decimal(decimal) => Value::Decimal(
+            decimal.rem_euclid(Decimal::from_int(modulus)).ok_or("Decimal result out of range")?,
+        ),
+        Value::Fixed(num, ty) => Value::Fixed(ty.wrap(num.rem_euclid(modulus)), ty),
+        // Intervals and complex numbers have no single residue
+        other => other,
+    })
+}
+
+// Bitwise operators on integers and bitsets. Integers combined with a
+// bitset are treated as the bitset of their set bits.
+fn bitwise_op(op: &Token, lhs: &Value, rhs: &Value, warnings: &mut Vec<String>) -> Result<Value, &'static str> {
+    if let (Value::Int(a), Value::Int(b)) = (lhs, rhs) {
+        let (result, overflowed) = int_op(op, *a, *b)?;
+        if overflowed {
+            warnings.push(format!("Result exceeds 128 bits and wrapped to {}", result));
+        }
+        return Ok(Value::Int(result));
+    }
+    let as_bitset = |value: &Value| match value {
+        Value::Bitset(bits) => Ok(bits.clone()),
+        Value::Int(num) => Bitset::from_int(*num),
+        _ => Err("Bitwise operations need integers or bitsets"),
+    };
+    let a = as_bitset(lhs)?;
+    Ok(Value::Bitset(match op {
+        Token::Amp => a.and(&as_bitset(rhs)?),
+        Token::Pipe => a.or(&as_bitset(rhs)?),
+        Token::Caret => a.xor(&as_bitset(rhs)?),
+        Token::Shl | Token::Shr => {
+            let Value::Int(amount) = rhs else {
+                return Err("Shift amount must be an integer");
+            };
+            let amount = usize::try_from(*amount).map_err(|_| "Shift amount must not be negative")?;
+            if *op == Token::Shl {
+                a.shl(amount)?
+            } else {
+                a.shr(amount)
+            }
+        }
+        _ => unreachable!(), // Only called for bitwise operators
+    }))
+}
+
+// 128-bit integer arithmetic and bitwise operators, flagging results that
+// wrapped or lost bits
+fn int_op(op: &Token, a: i128, b: i128) -> Result<(i128, bool), &'static str> {
+    Ok(match op {
+        Token::Plus => a.overflowing_add(b),
+        Token::Minus => a.overflowing_sub(b),
+        Token::Star => a.overflowing_mul(b),
+        Token::Slash if b == 0 => return Err("Division by zero"),
+        Token::Slash => a.overflowing_div(b),
+        Token::Percent if b == 0 => return Err("Division by zero"),
+        Token::Percent => a.overflowing_rem(b),
+        Token::StarStar if b < 0 => return Err("Integer powers need a non-negative exponent; use a float base such as 2.0"),
+        // Exponents past u32 only matter for 0, 1 and -1, so keep their parity
+        Token::StarStar => a.overflowing_pow(u32::try_from(b).unwrap_or(u32::MAX - 1 + (b & 1) as u32)),
+        Token::Amp => (a & b, false),
+        Token::Pipe => (a | b, false),
+        Token::Caret => (a ^ b, false),
+        Token::Shl | Token::Shr if !(0..128).contains(&b) => return Err("Shift amount must be between 0 and 127"),
+        Token::Shl => (a << b, (a << b) >> b != a),
+        Token::Shr => (a >> b, false),
+        _ => unreachable!(), // Only called for binary operators
+    })
+}
+
+// Unsigned counterpart of int_op, so that division, remainder and shifts
+// treat the top bit as magnitude rather than sign
+fn uint_op(op: &Token, a: u128, b: u128) -> Result<(u128, bool), &'static str> {
+    Ok(match op {
+        Token::Plus => a.overflowing_add(b),
+        Token::Minus => a.overflowing_sub(b),
+        Token::Star => a.overflowing_mul(b),
+        Token::Slash if b == 0 => return Err("Division by zero"),
+        Token::Slash => (a / b, false),
+        Token::Percent if b == 0 => return Err("Division by zero"),
+        Token::Percent => (a % b, false),
+        Token::StarStar => a.overflowing_pow(u32::try_from(b).unwrap_or(u32::MAX - 1 + (b & 1) as u32)),
+        Token::Shl | Token::Shr if b >= 128 => return Err("Shift amount must be between 0 and 127"),
+        Token::Shl => (a << b, (a << b) >> b != a),
+        Token::Shr => (a >> b, false),
+        _ => int_op(op, a as i128, b as i128).map(|(result, overflowed)| (result as u128, overflowed))?,
+    })
+}
+
+// Fixed-width integers compute in 128 bits, then wrap to the operand type
+fn fixed_op(op: &Token, lhs: &Value, rhs: &Value, warnings: &mut Vec<String>) -> Result<Value, &'static str> {
+    let ty = match (lhs, rhs) {
+        (Value::Fixed(_, a), Value::Fixed(_, b)) => a.common(*b),
+        (Value::Fixed(_, ty), _) | (_, Value::Fixed(_, ty)) => *ty,
+        _ => unreachable!(), // Only called with a fixed-width operand
+    };
+    let (a, b) = (lhs.as_int(warnings)?, rhs.as_int(warnings)?);
+    let (result, overflowed) = match ty.is_signed() {
+        true => int_op(op, a, b)?,
+        false => uint_op(op, a as u128, b as u128).map(|(result, overflowed)| (result as i128, overflowed))?,
+    };
+    let wrapped = ty.wrap(result);
+    if overflowed || wrapped != result {
+        warnings.push(format!("Result wrapped to {} in {}", ty.display(wrapped), ty));
+    }
+    Ok(Value::Fixed(wrapped, ty))
+}
+
+fn scalar_op(
+    op: &Token,
+    lhs: Value,
+    rhs: Value,
+    settings: &Settings,
+    warnings: &mut Vec<String>,
+) -> Result<Value, &'static str> {
+    // Byte strings take part in arithmetic as big-endian integers
+    let int = |value: Value| match value {
+        Value::Bytes(_) => value.as_int(&mut Vec::new()).map(Value::Int),
+        value => Ok(value),
+    };
+    let (lhs, rhs) = (int(lhs)?, int(rhs)?);
+    if matches!(lhs, Value::Duration(_)) || matches!(rhs, Value::Duration(_)) {
+        return duration_op(op, &lhs, &rhs);
+    }
+    let bitwise = matches!(op, Token::Amp | Token::Pipe | Token::Caret | Token::Shl | Token::Shr);
+    // Intervals pass through word mode, taking fixed-width operands at their value
+    if !bitwise && (matches!(lhs, Value::Interval(..)) || matches!(rhs, Value::Interval(..))) {
+        return interval_op(op, &lhs, &rhs);
+    }
+    if matches!(lhs, Value::Fixed(..)) || matches!(rhs, Value::Fixed(..)) {
+        return fixed_op(op, &lhs, &rhs, warnings);
+    }
+    #[cfg(feature = "bigint")]
+    if let (Value::Int(a), Value::Int(b)) = (&lhs, &rhs) {
+        if big::needs_promotion(op, *a, *b) {
+            return big::op(op, (*a).into(), (*b).into());
+        }
+    }
+    #[cfg(feature = "bigint")]
+    if matches!(lhs, Value::Big(_)) || matches!(rhs, Value::Big(_)) {
+        if let (Some(a), Some(b)) = (lhs.as_big(), rhs.as_big()) {
+            return big::op(op, a, b);
+        }
+    }
+    if bitwise {
+        return bitwise_op(op, &lhs, &rhs, warnings);
+    }
+    if matches!(lhs, Value::Bitset(_)) || matches!(rhs, Value::Bitset(_)) {
+        return Err("Bitsets support only & | ^ << >>");
+    }
+    if matches!(lhs, Value::Complex(..)) || matches!(rhs, Value::Complex(..)) {
+        return complex_op(op, &lhs, &rhs);
+    }
+    match (&lhs, &rhs) {
+        (Value::Decimal(a), Value::Decimal(b)) => return decimal_op(op, *a, *b),
+        (Value::Decimal(a), Value::Int(b)) => return decimal_op(op, *a, Decimal::from_int(*b)),
+        (Value::Int(a), Value::Decimal(b)) => return decimal_op(op, Decimal::from_int(*a), *b),
+        // Keep the fractional part of inexact quotients in decimal mode
+        (Value::Int(a), Value::Int(b)) if settings.numerics == Numerics::Decimal
+            && *op == Token::Slash && *b != 0 && a % b != 0 =>
+        {
+            return decimal_op(op, Decimal::from_int(*a), Decimal::from_int(*b));
+        }
+        _ => {}
+    }
+    if let (Value::Int(a), Value::Int(b)) = (&lhs, &rhs) {
+        let (result, overflowed) = int_op(op, *a, *b)?;
+        if overflowed {
+            warnings.push(format!("Result exceeds 128 bits and wrapped to {}", result));
+        }
+        return Ok(Value::Int(result));
+    }
+    let (a, b) = (lhs.as_float()?, rhs.as_float()?);
+    Ok(Value::Float(match op {
+        Token::Plus => a + b,
+        Token::Minus => a - b,
+        Token::Star => a * b,
+        Token::Slash if b == 0.0 => return Err("Division by zero"),
+        Token::Slash => a / b,
+        Token::Percent if b == 0.0 => return Err("Division by zero"),
+        Token::Percent => a % b,
+        Token::StarStar => a.powf(b),
+        _ => unreachable!(), // Only called for arithmetic operators
+    }))
+}
+
+// Durations add to and subtract from each other and scale by plain numbers;
+// one divided by another is their ratio
+fn duration_op(op: &Token, lhs: &Value, rhs: &Value) -> Result<Value, &'static str> {
+    // Exact for whole numbers; fractions round to the nearest nanosecond
+    let scale = |nanos: i128, factor: &Value, divide: bool| -> Result<i128, &'static str> {
+        match (factor, divide) {
+            (Value::Int(0) | Value::Fixed(0, _), true) => return Err("Division by zero"),
+            (Value::Int(factor) | Value::Fixed(factor, _), true) => return Ok(nanos / factor),
+            (Value::Int(factor) | Value::Fixed(factor, _), false) => {
+                return nanos.checked_mul(*factor).ok_or("Duration out of range")
+            }
+            _ => {}
+        }
+        let factor = factor.as_float()?;
+        if divide && factor == 0.0 {
+            return Err("Division by zero");
+        }
+        let scaled = if divide { nanos as f64 / factor } else { nanos as f64 * factor }.round();
+        match scaled.is_finite() && scaled.abs() < i128::MAX as f64 {
+            true => Ok(scaled as i128),
+            false => Err("Duration out of range"),
+        }
+    };
+    let nanos = match (op, lhs, rhs) {
+        (Token::Plus, Value::Duration(a), Value::Duration(b)) => a.checked_add(*b).ok_or("Duration out of range")?,
+        (Token::Minus, Value::Duration(a), Value::Duration(b)) => a.checked_sub(*b).ok_or("Duration out of range")?,
+        (Token::Slash | Token::Percent, Value::Duration(_), Value::Duration(0)) => return Err("Division by zero"),
+        (Token::Slash, Value::Duration(a), Value::Duration(b)) => return Ok(Value::Float(*a as f64 / *b as f64)),
+        (Token::Percent, Value::Duration(a), Value::Duration(b)) => a % b,
+        (Token::Star, Value::Duration(a), factor) | (Token::Star, factor, Value::Duration(a)) => {
+            scale(*a, factor, false)?
+        }
+        (Token::Slash, Value::Duration(a), divisor) => scale(*a, divisor, true)?,
+        (Token::Plus | Token::Minus, ..) => {
+            return Err("Only a duration can be added to a duration; give it a unit, as in 5s")
+        }
+        _ => return Err("Durations can only be added, subtracted, multiplied or divided by a number"),
+    };
+    Ok(Value::Duration(nanos))
+}
+
+fn cast(value: Value, ty: IntType, warnings: &mut Vec<String>) -> Result<Value, &'static str> {
+    if let Value::List(items) = value {
+        let items = items.into_iter().map(|item| cast(item, ty, warnings));
+        return items.collect::<Result<_, _>>().map(Value::List);
+    }
+    let num = value.as_int(warnings)?;
+    let wrapped = ty.wrap(num);
+    // Reinterpreting a bit pattern, as in (i13) 0x1FFF, loses nothing
+    if wrapped != num && num as u128 & !ty.mask() != 0 {
+        warnings.push(format!("{} wrapped to {} in {}", value, ty.display(wrapped), ty));
+    }
+    Ok(Value::Fixed(wrapped, ty))
+}
+
+// Flips every bit of an integer, staying within the width of fixed types
+fn complement(value: Value, warnings: &mut Vec<String>) -> Result<Value, &'static str> {
+    match value {
+        Value::Fixed(num, ty) => Ok(Value::Fixed(ty.wrap(!num), ty)),
+        Value::List(items) => {
+            let items = items.into_iter().map(|item| complement(item, warnings));
+            items.collect::<Result<_, _>>().map(Value::List)
+        }
+        Value::Bitset(_) => Err("Cannot complement a bitset of unbounded width; xor it with a mask instead"),
+        value => Ok(Value::Int(!value.as_int(warnings)?)),
+    }
+}
+
+fn negate(value: Value) -> Result<Value, &'static str> {
+    Ok(match value {
+        Value::Int(num) => Value::Int(num.checked_neg().ok_or("Result exceeds 128 bits")?),
+        Value::Float(float) => Value::Float(-float),
+        Value::Decimal(decimal) => Value::Decimal(Decimal::from_int(0).checked_sub(decimal).ok_or("Decimal out of range")?),
+        Value::Complex(re, im) => Value::Complex(-re, -im),
+        Value::Interval(lo, hi) => Value::Interval(-hi, -lo),
+        Value::List(items) => Value::List(items.into_iter().map(negate).collect::<Result<_, _>>()?),
+        Value::Fixed(num, ty) => Value::Fixed(ty.wrap(num.wrapping_neg()), ty),
+        Value::Duration(nanos) => Value::Duration(nanos.checked_neg().ok_or("Duration out of range")?),
+        #[cfg(feature = "bigint")]
+        Value::Big(num) => big::demote(-num),
+        Value::Tuple(_) => return Err("Expected a number but found a tuple"),
+        Value::Bitset(_) => return Err("Expected a number but found a bitset"),
+        Value::Name(_) => return Err("Expected a number but found a name"),
+        Value::Bytes(_) => return Err("Expected a number but found a byte string"),
+    })
+}
+
+fn eval_expr(tokens: &[Spanned], env: &Env, warnings: &mut Vec<Diagnostic>) -> Result<Value, Diagnostic> {
+    eval_with_locals(tokens, env, &mut HashMap::new(), 0, warnings)
+}
+
+fn eval_with_locals(
+    tokens: &[Spanned],
+    env: &Env,
+    locals: &mut HashMap<String, Value>,
+    depth: usize,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<Value, Diagnostic> {
+    let mut stack: Vec<Value> = Vec::new();
+
+    for Spanned { token, span } in tokens {
+        trace!(?token, ?stack, depth, "eval step");
+        cancel::check().map_err(|message| Diagnostic::new(message, span.clone()))?;
+        if let Token::Block(block) = token {
+            let value = eval_block(block, env, locals, depth, warnings)?;
+            stack.push(value.ok_or_else(|| Diagnostic::new("This block has no value", span.clone()))?);
+            continue;
+        }
+        if let Token::Loop(repeat) = token {
+            stack.push(eval_loop(repeat, span, env, locals, depth, warnings)?);
+            continue;
+        }
+        if let Token::If(conditional) = token {
+            let cond = eval_with_locals(&conditional.cond, env, locals, depth, warnings)?;
+            let truthy = cond.is_truthy().map_err(|message| Diagnostic::new(message, covering_span(&conditional.cond, span.start)))?;
+            let branch = if truthy { &conditional.then } else { &conditional.otherwise };
+            stack.push(eval_with_locals(branch, env, locals, depth, warnings)?);
+            continue;
+        }
+        let mut token_warnings = Vec::new();
+        let result = eval_token(token, &mut stack, env, &*locals, depth, &mut token_warnings);
+        warnings.extend(
+            token_warnings
+                .into_iter()
+                .map(|message| Diagnostic::new(format!("warning: {}", message), span.clone())),
+        );
+        result.map_err(|message| Diagnostic::new(message, span.clone()))?;
+    }
+    let end = tokens.iter().map(|t| t.span.end).max().unwrap_or(0);
+    stack.pop().ok_or_else(|| Diagnostic::new("Invalid expression", covering_span(tokens, end)))
+}
+
+// Runs a block's statements, returning the value of a final expression.
+// Outer names the block reassigned are written back to `locals`.
+fn eval_block(
+    block: &Block,
+    env: &Env,
+    locals: &mut HashMap<String, Value>,
+    depth: usize,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<Option<Value>, Diagnostic> {
+    let mut scope = locals.clone();
+    let mut declared = Vec::new();
+    let mut value = None;
+    for statement in &block.statements {
+        value = None;
+        match statement {
+            BlockStatement::Let(name, tokens) => {
+                let value = eval_with_locals(tokens, env, &mut scope, depth, warnings)?;
+                scope.insert(name.clone(), value);
+                declared.push(name);
+            }
+            BlockStatement::Set(name, span, tokens) => {
+                if !scope.contains_key(name) {
+                    let message = format!("{} is not declared in this block; use let {} = ...", name, name);
+                    return Err(Diagnostic::new(message, span.clone()));
+                }
+                let value = eval_with_locals(tokens, env, &mut scope, depth, warnings)?;
+                scope.insert(name.clone(), value);
+            }
+            BlockStatement::Expr(tokens) => value = Some(eval_with_locals(tokens, env, &mut scope, depth, warnings)?),
+        }
+    }
+    for (name, outer) in locals.iter_mut() {
+        if !declared.contains(&name) {
+            *outer = scope[name].clone();
+        }
+    }
+    Ok(value)
+}
+
+fn eval_loop(
+    repeat: &Loop,
+    span: &Span,
+    env: &Env,
+    locals: &mut HashMap<String, Value>,
+    depth: usize,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<Value, Diagnostic> {
+    let mut values = Vec::new();
+    match repeat {
+        Loop::For { var, items, body } => {
+            let items = match eval_with_locals(items, env, locals, depth, warnings)? {
+                Value::List(items) | Value::Tuple(items) => items,
+                _ => return Err(Diagnostic::new("for loops need a list such as 0..15", covering_span(items, span.start))),
+            };
+            // The loop variable shadows any outer name of the same name
+            let shadowed = locals.remove(var);
+            for item in items {
+                cancel::check().map_err(|message| Diagnostic::new(message, span.clone()))?;
+                locals.insert(var.clone(), item);
+                let value = eval_block(body, env, locals, depth, warnings);
+                locals.remove(var);
+                values.extend(value?);
+            }
+            if let Some(shadowed) = shadowed {
+                locals.insert(var.clone(), shadowed);
+            }
+        }
+        Loop::While { cond, body } => {
+            let mut iterations = 0;
+            loop {
+                cancel::check().map_err(|message| Diagnostic::new(message, span.clone()))?;
+                let truthy = eval_with_locals(cond, env, locals, depth, warnings)?
+                    .is_truthy()
+                    .map_err(|message| Diagnostic::new(message, covering_span(cond, span.start)))?;
+                if !truthy {
+                    break;
+                }
+                if iterations == MAX_ITERATIONS {
+                    let message = format!("Loop stopped after {} iterations", MAX_ITERATIONS);
+                    return Err(Diagnostic::new(message, span.clone()));
+                }
+                iterations += 1;
+                values.extend(eval_block(body, env, locals, depth, warnings)?);
+            }
+        }
+    }
+    Ok(Value::List(values))
+}
+
+fn eval_token(
+    token: &Token,
+    stack: &mut Vec<Value>,
+    env: &Env,
+    locals: &HashMap<String, Value>,
+    depth: usize,
+    warnings: &mut Vec<String>,
+) -> Result<(), String> {
+    match token {
+        Token::Number(num) => {
+            // keep converting it until it's an int; float bit patterns
+            // (Fx...) end up as decimal fractions instead
+            let value = match to_int(num, &env.settings) {
+                Ok(num) => Value::Int(num),
+                #[cfg(feature = "bigint")]
+                Err(_) if num.bytes().all(|b| b.is_ascii_digit()) => {
+                    Value::Big(big::parse(num, 10).ok_or("Could not parse number")?)
+                }
+                Err(_) => Value::Float(num.parse::<f64>().map_err(|_| "Could not parse number")?),
+            };
+            match (env.settings.word, value) {
+                (Some(ty), Value::Int(num)) => stack.push(cast(Value::Int(num), ty, warnings)?),
+                (_, value) => stack.push(value),
+            }
+        },
+
+        Token::Bytes(bytes) => stack.push(Value::Bytes(bytes.clone())),
+
+        Token::Duration(nanos) => stack.push(Value::Duration(*nanos)),
+
+        // The declared width wins over --word; bits past it wrap with a warning
+        Token::Sized(bits, ty) => stack.push(cast(Value::Fixed(*bits as i128, IntType::U128), *ty, warnings)?),
+
+        Token::Imaginary(digits) => {
+            let im = digits.parse::<f64>().map_err(|_| "Could not parse number")?;
+            stack.push(Value::Complex(0.0, im));
+        },
+
+        // Integers past i128::MAX still fit an unsigned 128-bit word
+        Token::Float(literal) if literal.bytes().all(|b| b.is_ascii_digit())
+            && env.settings.word.is_some_and(|ty| !ty.is_signed()) =>
+        {
+            let num = literal.parse::<u128>().map_err(|_| "Integer does not fit in 128 bits")?;
+            stack.push(cast(Value::Fixed(num as i128, IntType::U128), env.settings.word.unwrap_or(IntType::U128), warnings)?);
+        },
+
+        #[cfg(feature = "bigint")]
+        Token::Float(literal) if literal.bytes().all(|b| b.is_ascii_digit()) => {
+            stack.push(Value::Big(big::parse(literal, 10).ok_or("Could not parse number")?));
+        },
+
+        Token::Float(literal) if env.settings.numerics == Numerics::Decimal => {
+            let decimal = Decimal::parse(literal).ok_or("Decimal literal out of range")?;
+            stack.push(Value::Decimal(decimal));
+        },
+
+        Token::Float(literal) => {
+            let float = literal.parse::<f64>().map_err(|_| "Could not parse number")?;
+            if !precision::is_exact(literal, float) {
+                warnings.push(format!(
+                    "{} is not exactly representable in binary; stored as {}",
+                    literal,
+                    precision::exact_decimal(float)
+                ));
+            }
+            stack.push(Value::Float(float));
+        },
+
+        Token::Index(name) => {
+            let key = pop_int(stack, warnings)?;
+            if let Some(table) = env.tables.get(name) {
+                stack.push(table.get(key).cloned().ok_or("No table entry for key")?);
+            } else {
+                let list = locals.get(name).or_else(|| env.vars.get(name)).ok_or("Unknown table or list")?;
+                stack.push(list.index(key)?);
+            }
+        },
+
+        Token::Subscript => {
+            let index = pop_int(stack, warnings)?;
+            let list = pop(stack)?;
+            stack.push(list.index(index)?);
+        },
+
+        Token::Plus
+        | Token::Minus
+        | Token::Star
+        | Token::Slash
+        | Token::Percent
+        | Token::StarStar
+        | Token::Amp
+        | Token::Pipe
+        | Token::Caret
+        | Token::Shl
+        | Token::Shr => {
+            let (a, b) = (pop(stack)?, pop(stack)?);
+            stack.push(binary_op(token, b, a, &env.settings, warnings)?);
+        },
+
+        Token::Cast(ty) => {
+            let value = pop(stack)?;
+            stack.push(cast(value, *ty, warnings)?);
+        },
+
+        Token::Tilde => {
+            let value = pop(stack)?;
+            stack.push(complement(value, warnings)?);
+        },
+
+        Token::Neg => {
+            let value = pop(stack)?;
+            stack.push(negate(value)?);
+        },
+
+        Token::Eq | Token::Ne | Token::Lt | Token::Le | Token::Gt | Token::Ge => {
+            let (b, a) = (pop(stack)?, pop(stack)?);
+            let result = match token {
+                Token::Eq => a.equals(&b),
+                Token::Ne => !a.equals(&b),
+                Token::Lt => a.compare(&b)?.is_lt(),
+                Token::Le => a.compare(&b)?.is_le(),
+                Token::Gt => a.compare(&b)?.is_gt(),
+                _ => a.compare(&b)?.is_ge(),
+            };
+            stack.push(Value::Int(result as i128));
+        },
+
+        Token::DotDot => {
+            let (hi, lo) = (pop_int(stack, warnings)?, pop_int(stack, warnings)?);
+            if hi < lo {
+                return Err("Range end is before its start".into());
+            }
+            if hi - lo >= MAX_RANGE {
+                return Err(format!("Ranges are limited to {} values", MAX_RANGE));
+            }
+            stack.push(Value::List((lo..=hi).map(Value::Int).collect()));
+        },
+
+        Token::PlusMinus => {
+            if !env.settings.interval {
+                return Err("± needs interval mode (:set interval on)".into());
+            }
+            let (radius, center) = (pop(stack)?.as_float()?.abs(), pop(stack)?.as_float()?);
+            stack.push(Value::Interval(center - radius, center + radius));
+        },
+
+        Token::Tuple(count) => {
+            if stack.len() < *count {
+                return Err("Invalid expression".into());
+            }
+            let items = stack.split_off(stack.len() - count);
+            stack.push(Value::Tuple(items));
+        },
+
+        Token::List(count) => {
+            if stack.len() < *count {
+                return Err("Invalid expression".into());
+            }
+            let items = stack.split_off(stack.len() - count);
+            match items.as_slice() {
+                [lo, hi] if env.settings.interval => {
+                    let (lo, hi) = (lo.as_float()?, hi.as_float()?);
+                    if lo > hi {
+                        return Err("Interval lower bound exceeds upper bound".into());
+                    }
+                    stack.push(Value::Interval(lo, hi));
+                }
+                _ if env.settings.interval => return Err("An interval needs exactly two bounds".into()),
+                _ => stack.push(Value::List(items)),
+            }
+        },
+        Token::Ident(name) => {
+            let value = locals.get(name).or_else(|| env.vars.get(name));
+            stack.push(value.cloned().ok_or("Unknown identifier")?);
+        },
+
+        Token::Call(name, argc) => {
+            if stack.len() < *argc {
+                return Err("Invalid expression".into());
+            }
+            let Some(function) = env.functions.get(name) else {
+                let args = stack.split_off(stack.len() - argc);
+                debug!(function = %name, ?args, "calling builtin");
+                stack.push(builtins::call(name, &args).ok_or("Unknown function")??);
+                return Ok(());
+            };
+            if function.params.len() != *argc {
+                return Err("Wrong number of arguments".into());
+            }
+            if depth >= MAX_CALL_DEPTH {
+                return Err("Maximum call depth exceeded".into());
+            }
+            let args = stack.split_off(stack.len() - argc);
+            debug!(function = %name, ?args, depth, "calling user function");
+            let mut locals = function.params.iter().cloned().zip(args).collect();
+            let mut body_warnings = Vec::new();
+            let result = eval_with_locals(&function.body, env, &mut locals, depth + 1, &mut body_warnings);
+            warnings.extend(body_warnings.into_iter().map(|w| {
+                format!("{} (in {})", w.message.trim_start_matches("warning: "), name)
+            }));
+            stack.push(result.map_err(|e| format!("{} (in {})", e.message, name))?);
+        },
+        _ => return Err("Unexpected token".into())
+    }
+    Ok(())
+}
+
+fn parse_statement(tokens: Vec<Spanned>) -> Result<Statement, Diagnostic> {
+    if let Some(first) = tokens.first().filter(|first| first.token == Token::Ident("let".into())) {
+        return Err(Diagnostic::new("let bindings must be inside a { } block", first.span.clone()));
+    }
+    let Some(assign) = tokens.iter().position(|t| t.token == Token::Assign) else {
+        return Ok(Statement::Expr(tokens));
+    };
+    let body = tokens[assign + 1..].to_vec();
+    let target = &tokens[..assign];
+    let target_span = covering_span(target, tokens[assign].span.start);
+    let kinds: Vec<&Token> = target.iter().map(|t| &t.token).collect();
+    match kinds.as_slice() {
+        [Token::Ident(name)] => Ok(Statement::Assign(name.clone(), body)),
+        [Token::Ident(name), Token::LParen, params @ .., Token::RParen] => {
+            let mut names = Vec::new();
+            for (i, param) in params.iter().enumerate() {
+                match param {
+                    Token::Ident(param) if i % 2 == 0 && !names.contains(param) => names.push(param.clone()),
+                    Token::Comma if i % 2 == 1 && i + 1 < params.len() => {}
+                    _ => return Err(Diagnostic::new("Invalid parameter list", target_span)),
+                }
+            }
+            let function = Function { params: names, body: infix_to_postfix(body) };
+            Ok(Statement::Function(name.clone(), function))
+        }
+        [Token::Ident(_), Token::Comma, ..] => {
+            let mut names = Vec::new();
+            for (i, kind) in kinds.iter().enumerate() {
+                match kind {
+                    Token::Ident(name) if i % 2 == 0 && !names.contains(name) => names.push(name.clone()),
+                    Token::Comma if i % 2 == 1 && i + 1 < kinds.len() => {}
+                    _ => return Err(Diagnostic::new("Invalid assignment target", target_span)),
+                }
+            }
+            Ok(Statement::Destructure(names, body))
+        }
+        _ => Err(Diagnostic::new("Invalid assignment target", target_span)),
+    }
+}
+
+// Runs a statement, returning the value of expressions and assignments
+fn execute(
+    statement: Statement,
+    env: &mut Env,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<Option<Value>, Diagnostic> {
+    match statement {
+        Statement::Expr(tokens) => eval_expr(&infix_to_postfix(tokens), env, warnings).map(Some),
+        Statement::Assign(name, tokens) => {
+            let value = eval_expr(&infix_to_postfix(tokens), env, warnings)?;
+            env.vars.insert(name, value.clone());
+            Ok(Some(value))
+        }
+        Statement::Destructure(names, tokens) => {
+            let postfix = infix_to_postfix(tokens);
+            let value = eval_expr(&postfix, env, warnings)?;
+            let (Value::Tuple(items) | Value::List(items)) = &value else {
+                return Err(Diagnostic::new("Only tuples and lists can be unpacked", covering_span(&postfix, 0)));
+            };
+            if items.len() != names.len() {
+                let message = format!("Expected {} values to unpack but found {}", names.len(), items.len());
+                return Err(Diagnostic::new(message, covering_span(&postfix, 0)));
+            }
+            env.vars.extend(names.into_iter().zip(items.iter().cloned()));
+            Ok(Some(value))
+        }
+        Statement::Function(name, function) => {
+            env.functions.insert(name, function);
+            Ok(None)
+        }
+    }
+}
+
+// Expands aliases in a line and runs it, returning the expanded text that
+// diagnostics refer to, any warnings, and the result
+pub fn evaluate_line(
+    input: &str,
+    env: &mut Env,
+) -> (String, Vec<Diagnostic>, Result<Option<Value>, Vec<Diagnostic>>) {
+    let expanded = expand_aliases(input, &env.aliases);
+    if expanded != input {
+        debug!(%expanded, "expanded aliases");
+    }
+    let mut warnings = Vec::new();
+    let result = parse(&expanded, &env.settings)
+        .and_then(|statement| execute(statement, env, &mut warnings).map_err(|diagnostic| vec![diagnostic]));
+    (expanded, warnings, result)
+}
+
+// Parses one line into an expression, assignment or definition
+pub fn parse(input: &str, settings: &Settings) -> Result<Statement, Vec<Diagnostic>> {
+    parse_expr(input, settings).and_then(|tokens| parse_statement(tokens).map_err(|diagnostic| vec![diagnostic]))
+}
+
+// Evaluates one line in `env`, which keeps any variables and functions it
+// defines. Warnings are dropped; evaluate_line returns them as well.
+pub fn eval(input: &str, env: &mut Env) -> Result<Option<Value>, Vec<Diagnostic>> {
+    evaluate_line(input, env).2
+}
+
+pub fn display_value(value: &Value, settings: &Settings) -> String {
+    match value {
+        Value::Complex(re, im) if settings.polar => {
+            format!("{}∠{}°", re.hypot(*im), im.atan2(*re).to_degrees())
+        }
+        // Long masks wrap between 32-bit groups
+        Value::Bitset(bits) => {
+            let text = bits.to_string();
+            let groups: Vec<&str> = text.trim_start_matches("0x").split_inclusive('_').collect();
+            format::wrap_chunks("0x", &groups, &settings.style)
+        }
+        Value::Duration(nanos) => duration::summary(*nanos, &settings.style),
+        Value::Int(_) | Value::Fixed(..) => format::group_decimal(value.to_string(), &settings.style),
+        #[cfg(feature = "bigint")]
+        Value::Big(_) => format::group_decimal(value.to_string(), &settings.style),
+        _ => value.to_string(),
+    }
+}
+
+// Imports the definitions in a library file. Returns how many were loaded
+// along with warnings to show, such as for definitions that replace a
+// different existing one. The whole file is parsed, then evaluated in a
+// copy of `env`, so an error anywhere leaves `env` as it was.
+pub fn load_library(path: &Path, env: &mut Env) -> Result<(usize, Vec<String>), String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let mut statements = Vec::new();
+    for line in logical_lines(&contents) {
+        let location = format!("{}:{}", path.display(), line.number);
+        let line = expand_aliases(&line.text, &env.aliases);
+        let settings = &env.settings;
+        let tokens = parse_expr(&line, settings)
+            .map_err(|diagnostics| format!("{}\n{}", location, diagnostic::render_all(&line, &diagnostics, settings)))?;
+        let statement = parse_statement(tokens).map_err(|e| format!("{}\n{}", location, e.render(&line, settings)))?;
+        if let Statement::Expr(_) = statement {
+            return Err(format!("{}: expected a definition", location));
+        }
+        statements.push((location, line, statement));
+    }
+    let (loaded, mut staged, mut notes) = (statements.len(), env.clone(), Vec::new());
+    for (location, line, statement) in statements {
+        let error = |diagnostic: Diagnostic| format!("{}\n{}", location, diagnostic.render(&line, &env.settings));
+        let mut warnings = Vec::new();
+        let (name, conflict) = match &statement {
+            Statement::Expr(_) => unreachable!(), // Rejected while parsing
+            Statement::Assign(name, tokens) => {
+                let value = eval_expr(&infix_to_postfix(tokens.clone()), &staged, &mut warnings).map_err(error)?;
+                (name.clone(), staged.vars.get(name).is_some_and(|old| *old != value))
+            }
+            Statement::Destructure(names, tokens) => {
+                let value = eval_expr(&infix_to_postfix(tokens.clone()), &staged, &mut warnings).map_err(error)?;
+                let items = match &value {
+                    Value::Tuple(items) | Value::List(items) => items.as_slice(),
+                    _ => &[],
+                };
+                let conflict = names.iter().zip(items).any(|(name, item)| staged.vars.get(name).is_some_and(|old| old != item));
+                (names.join(", "), conflict)
+            }
+            Statement::Function(name, function) => {
+                (name.clone(), staged.functions.get(name).is_some_and(|old| old != function))
+            }
+        };
+        if conflict {
+            notes.push(format!("Warning: {} redefines {}", location, name));
+        }
+        execute(statement, &mut staged, &mut Vec::new()).map_err(error)?;
+        if !warnings.is_empty() {
+            notes.push(format!("{}\n{}", location, diagnostic::render_all(&line, &warnings, &env.settings)));
+        }
+    }
+    *env = staged;
+    Ok((loaded, notes))
+}
+
+// A statement as read from a file, possibly joined from several lines
+pub struct LogicalLine {
+    // 1-based number of the first line
+    pub number: usize,
+    pub text: String,
+    // Where each joined line starts: (offset in text, 0-based line, byte column)
+    pieces: Vec<(usize, usize, usize)>,
+}
+
+impl LogicalLine {
+    // 0-based line and byte column in the file of an offset into `text`
+    pub fn locate(&self, offset: usize) -> (usize, usize) {
+        let piece = self.pieces.iter().rev().find(|piece| piece.0 <= offset).unwrap_or(&self.pieces[0]);
+        (piece.1, piece.2 + offset.saturating_sub(piece.0))
+    }
+
+    // Offset into `text` of a 0-based line and byte column in the file
+    pub fn offset_of(&self, line: usize, column: usize) -> Option<usize> {
+        let piece = self.pieces.iter().find(|piece| piece.1 == line)?;
+        Some(piece.0 + column.checked_sub(piece.2)?)
+    }
+}
+
+// Non-blank, non-comment lines of a file. A line that leaves a `{` open
+// continues onto the following lines, joined with `;`.
+pub fn logical_lines(contents: &str) -> Vec<LogicalLine> {
+    let mut lines = Vec::new();
+    let mut pending: Option<LogicalLine> = None;
+    for (i, raw) in contents.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let column = raw.len() - raw.trim_start().len();
+        let mut logical = match pending.take() {
+            Some(mut logical) => {
+                logical.text.push_str("; ");
+                logical
+            }
+            None => LogicalLine { number: i + 1, text: String::new(), pieces: Vec::new() },
+        };
+        logical.pieces.push((logical.text.len(), i, column));
+        logical.text.push_str(line);
+        let depth = logical.text.matches('{').count() as isize - logical.text.matches('}').count() as isize;
+        if depth > 0 {
+            pending = Some(logical);
+        } else {
+            lines.push(logical);
+        }
+    }
+    // An unclosed block is reported when the line is parsed
+    lines.extend(pending);
+    lines
+}
+
+// Replaces every whole-word alias with its text. Expansion is a single
+// pass, so an alias may not refer to another alias.
+pub fn expand_aliases(input: &str, aliases: &BTreeMap<String, String>) -> String {
+    if aliases.is_empty() {
+        return input.to_string();
+    }
+    let mut expanded = String::new();
+    let mut word = String::new();
+    for c in input.chars().chain(std::iter::once(' ')) {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        match aliases.get(&word) {
+            Some(text) => expanded.push_str(text),
+            None => expanded.push_str(&word),
+        }
+        word.clear();
+        expanded.push(c);
+    }
+    expanded.pop();
+    expanded
+}
+
+pub fn check_force_output(args: &[String], settings: &Settings) -> Option<&'static str> {
+    for arg in args {
+        let arg_str = arg.as_str();
+        if let Some(c) = arg_str.chars().next(){
+            if c == '=' {
+                let parts: Vec<&str> = arg_str.split('=').collect();
+                if let Some(name) = output_name(parts[1], &settings.formats) {
+                    return Some(name)
+                }
+            }
+        }
+    }
+    None
+}
+
+// The output `name` selects among the built-in bases and the formats in
+// `formats`
+pub(crate) fn output_name(name: &str, formats: &format::Registry) -> Option<&'static str> {
+    let bases: [&'static str; 16] =
+        ["f", "a", "e", "si", "bytes", "2", "8", "10", "16", "text", "unicode", "c", "rust", "python", "-2", "bal3"];
+    if let Some(&base) = bases.iter().find(|&&base| base == name) {
+        return Some(base)
+    }
+    if let Some(name) = format::scientific_name(name) {
+        return Some(name)
+    }
+    if let Some(name) = format::representation_name(name) {
+        return Some(name)
+    }
+    if let Some(name) = format::radix_name(name) {
+        return Some(name)
+    }
+    formats.lookup_name(name)
+}
+
+// Converts one input to an integer, adding a warning for inexact literals.
+// Also returns parse_num's rendering for bases without an integer form.
+pub fn convert_input(
+    input: &str,
+    settings: &Settings,
+    warnings: &mut Vec<String>,
+) -> Result<(i128, String), &'static str> {
+    let result = match parse_num(input, settings) {
+        Ok(result) => result,
+        Err(BaseConversionError::ParseIntError) => return Err("Failed to parse input"),
+        Err(BaseConversionError::InvalidInputFormat) => return Err("Invalid input format"),
+    };
+    debug!(input = %input, converted = %result, "parsed input");
+    // Label bit patterns that are not ordinary numbers rather than printing
+    // NaN or a long run of zeros
+    if input.starts_with("Fx") || input.starts_with("fx") {
+        if let Ok(float) = ieee::parse(input, settings) {
+            match float.class() {
+                "normal" => {}
+                "+0" | "-0" | "subnormal" => {
+                    warnings.push(format!("{} is {}", input, float.describe()));
+                    return Ok((0, result));
+                }
+                _ => {
+                    warnings.push(format!("{} is {}", input, float.describe()));
+                    return Err("NaN and infinity have no integer value");
+                }
+            }
+        }
+    }
+    let float = match (input.strip_suffix('f'), input.strip_suffix('F')) {
+        (Some(literal), _) => literal.parse::<f64>().ok().map(|float| (literal, float)),
+        (_, Some(literal)) => literal.parse::<f32>().ok().map(|float| (literal, float as f64)),
+        _ => None,
+    };
+    if let Some((literal, float)) = float {
+        if !precision::is_exact(literal, float) {
+            warnings.push(format!(
+                "{} is not exactly representable in binary; stored as {}",
+                literal,
+                precision::exact_decimal(float)
+            ));
+        }
+    }
+    match to_int(&result, settings) {
+        Ok(num) => Ok((num, result)),
+        // format_base reads the unsigned value back from `result`
+        Err(_) if unsigned_literal(input, settings).is_some() => {
+            Ok((result.parse::<u128>().unwrap_or_default() as i128, result))
+        }
+        Err(_) => {
+            // Float bit patterns (Fx...) convert to decimal fractions
+            let float = result.parse::<f64>().map_err(|_| "Not a number");
+            float.and_then(|float| Value::Float(float).as_int(warnings)).map(|num| (num, result)).map_err(|_| "Failed to convert expression result")
+        }
+    }
+}
+
+// Integer output for inputs too wide for 128 bits, such as 512-bit hex
+// digests; these need the bigint feature
+pub fn convert_wide(input: &str, base: &str, settings: &Settings) -> Option<String> {
+    #[cfg(feature = "bigint")]
+    {
+        let num = big::parse(input, 10).or_else(|| big::parse(&parse_num(input, settings).ok()?, 10))?;
+        big::format_base(base, &num, &settings.style)
+    }
+    #[cfg(not(feature = "bigint"))]
+    {
+        let _ = (input, base, settings);
+        None
+    }
+}
+
+// Output bases that show the input's value as a float, keeping fractions
+pub fn is_float_base(base: &str) -> bool {
+    matches!(base, "a" | "e" | "si") || format::scientific_name(base).is_some()
+}
+
+// Float output such as =e or =si of an input with a fractional value, as in
+// 2E-3 or 0.0047, which converts as written rather than truncated to an
+// integer. None for other bases and for inputs with integer values.
+pub fn convert_float(input: &str, base: &str, settings: &Settings) -> Option<String> {
+    if !is_float_base(base) {
+        return None;
+    }
+    let result = parse_num(input, settings).ok()?;
+    result.parse::<f64>().ok().filter(|float| float.fract() != 0.0)?;
+    Some(format_base(base, 0, &result, settings))
+}
+
+pub fn format_base(base: &str, num: i128, result: &str, settings: &Settings) -> String {
+    let style = &settings.style;
+    // 128-bit literals with the top bit set keep all their bits, where -1
+    // would otherwise show its 64-bit pattern
+    let unsigned = result.parse::<u128>().ok().filter(|&bits| bits > i128::MAX as u128);
+    // A word size shows patterns at its width, so 8'shFF in hex is 0xff
+    let pattern = settings.word.map_or_else(|| format::bit_pattern(num), |ty| num as u128 & ty.mask());
+    let bits = unsigned.unwrap_or(pattern);
+    match base {
+        "f" => format!("{:.5}", num),
+        // Hex float of the input's value, which may be a fraction
+        "a" => ieee::hex_float(result.parse().unwrap_or(num as f64)),
+        // Scientific notation of the input's value, also keeping fractions
+        "e" => format::scientific(result.parse().unwrap_or(num as f64), None),
+        "si" => format::si(result.parse().unwrap_or(num as f64)),
+        "bytes" => format::byte_size(num),
+        name if format::scientific_name(name).is_some() => {
+            format::scientific(result.parse().unwrap_or(num as f64), format::scientific_digits(name))
+        }
+        "2" => format!("b{}", format::group_nibbles(format::zero_pad(format!("{:b}", bits), style), style)),
+        "8" => format!("Ox{}", format::zero_pad(format!("{:o}", bits), style)),
+        "10" => format::group_decimal(unsigned.map_or_else(|| num.to_string(), |bits| bits.to_string()), style),
+        "16" => format::hex_pattern(bits, style),
+        "text" => codec::escape(&codec::pattern_bytes(bits)),
+        "c" | "rust" | "python" => format::byte_array(base, &codec::pattern_bytes(bits), style).unwrap_or_default(),
+        "unicode" => unicode::describe(num).unwrap_or_else(|| format!("Error: {} is not a Unicode code point", num)),
+        "-2" => format::negabinary(num),
+        "bal3" => format::balanced_ternary(num),
+        name => match format::representation_bits(name) {
+            Some(bits) => format::representation(name, num, bits, style)
+                .unwrap_or_else(|| format!("Error: {} does not fit in {} bits", num, bits)),
+            None => match format::radix_name(name).and_then(|name| name.parse().ok()) {
+                Some(radix) => format::radix(num, radix),
+                None => settings.formats.format_as(name, num).unwrap_or_else(|| result.to_string()),
+            },
+        },
+    }
+}
+
+pub fn base_title(base: &str) -> &str {
+    match base {
+        "f" => "Float",
+        "a" => "Hex float",
+        "e" => "Scientific",
+        "si" => "SI",
+        "bytes" => "Bytes",
+        "2" => "Binary",
+        "8" => "Octal",
+        "10" => "Decimal",
+        "16" => "Hex",
+        "text" => "Text",
+        "unicode" => "Unicode",
+        "c" => "C",
+        "rust" => "Rust",
+        "python" => "Python",
+        "-2" => "Negabinary",
+        "bal3" => "Balanced ternary",
+        name => format::radix_title(name).unwrap_or(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval_str(input: &str) -> String {
+        match eval(input, &mut Env::default()) {
+            Ok(Some(value)) => value.to_string(),
+            Ok(None) => String::new(),
+            Err(diagnostics) => panic!("{} failed: {}", input, diagnostics[0].message),
+        }
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        assert_eq!(eval_str("2 + 3 * 4"), "14");
+        assert_eq!(eval_str("2 * 3 + 4"), "10");
+        assert_eq!(eval_str("20 - 12 / 4"), "17");
+        assert_eq!(eval_str("1 + 2 * 3 - 4 / 2"), "5");
+    }
+
+    #[test]
+    fn equal_precedence_is_left_associative() {
+        assert_eq!(eval_str("8 - 2 - 1"), "5");
+        assert_eq!(eval_str("64 / 4 / 2"), "8");
+        assert_eq!(eval_str("2 * 6 / 3"), "4");
+        assert_eq!(eval_str("1 << 2 << 3"), "32");
+    }
+
+    #[test]
+    fn parentheses_override_precedence() {
+        assert_eq!(eval_str("(2 + 3) * 4"), "20");
+        assert_eq!(eval_str("2 * (3 + 4) * 5"), "70");
+        assert_eq!(eval_str("((1 + 1) * (2 + 2))"), "8");
+    }
+
+    #[test]
+    fn bitwise_and_shift_precedence() {
+        assert_eq!(eval_str("1 << 2 + 1"), "8");
+        assert_eq!(eval_str("0xf0 | 0x0f & 0x3"), "243");
+        assert_eq!(eval_str("6 ^ 3 & 1"), "7");
+        assert_eq!(eval_str("1 | 2 ^ 3"), "1");
+    }
+
+    #[test]
+    fn remainder_binds_like_multiplication() {
+        assert_eq!(eval_str("0x1234 % 16"), "4");
+        assert_eq!(eval_str("1 + 10 % 4"), "3");
+        assert_eq!(eval_str("7 % 4 * 2"), "6");
+        assert_eq!(eval_str("-7 % 3"), "-1");
+        assert_eq!(eval_str("7.5 % 2"), "1.5");
+    }
+
+    #[test]
+    fn power_is_right_associative_and_binds_tightest() {
+        assert_eq!(eval_str("2 ** 32 - 1"), "4294967295");
+        assert_eq!(eval_str("2 ** 3 ** 2"), "512");
+        assert_eq!(eval_str("3 * 2 ** 2"), "12");
+        assert_eq!(eval_str("~2 ** 2"), "-5");
+        assert_eq!(eval_str("2.0 ** 0.5 > 1.41"), "1");
+    }
+
+    #[test]
+    fn rotates_within_width() {
+        assert_eq!(eval_str("rol(0x81, 1, 8)"), "3");
+        assert_eq!(eval_str("ror(0x81, 1, 8)"), "192");
+        assert_eq!(eval_str("rol(0xdeadbeef, 36, 32)"), "3940282109");
+        assert_eq!(eval_str("ror((u16) 0x0001, 1)"), "32768");
+    }
+
+    #[test]
+    fn complement_applies_before_binary_operators() {
+        assert_eq!(eval_str("~0"), "-1");
+        assert_eq!(eval_str("~1 & 0xff"), "254");
+        assert_eq!(eval_str("0xff & ~0x0f"), "240");
+        assert_eq!(eval_str("~~5"), "5");
+        assert_eq!(eval_str("~(u8) 1"), "254");
+    }
+
+    #[test]
+    fn prefix_minus_negates_the_next_operand() {
+        assert_eq!(eval_str("-5"), "-5");
+        assert_eq!(eval_str("3 * -2"), "-6");
+        assert_eq!(eval_str("4 - -3"), "7");
+        assert_eq!(eval_str("--3"), "3");
+        assert_eq!(eval_str("-2 ** 2"), "-4");
+        assert_eq!(eval_str("2.0 ** -1"), "0.5");
+        assert_eq!(eval_str("-(1 + 2) * 2"), "-6");
+        assert_eq!(eval_str("[1, -2]"), "[1, -2]");
+        assert_eq!(eval_str("-[1, 2]"), "[-1, -2]");
+        assert_eq!(eval_str("-0x10 + 1"), "-15");
+        assert_eq!(eval_str("~-1"), "0");
+        assert_eq!(eval_str("(i8) -128"), "-128");
+        assert!(eval("2 ** -1", &mut Env::default()).is_err());
+        assert!(eval("-(1, 2)", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn comparisons_and_ranges_bind_loosest() {
+        assert_eq!(eval_str("1 + 1 == 2"), "1");
+        assert_eq!(eval_str("2 * 3 > 5 + 0"), "1");
+        assert_eq!(eval_str("0..1 + 2"), "[0, 1, 2, 3]");
+    }
+
+    #[test]
+    fn precedence_inside_calls_and_lists() {
+        assert_eq!(eval_str("abs(2 - 3 * 4)"), "10");
+        assert_eq!(eval_str("[1 + 2 * 3, 4 * 5 - 6]"), "[7, 14]");
+        assert_eq!(eval_str("(u8) 200 + 100"), "44");
+    }
+
+    #[test]
+    fn word_size_wraps_integer_arithmetic() {
+        let mut env = Env::default();
+        env.settings.set("word", "8").unwrap();
+        let mut eval_word = |input: &str| eval(input, &mut env).unwrap().unwrap().to_string();
+        assert_eq!(eval_word("0xff + 1"), "0");
+        assert_eq!(eval_word("0 - 1"), "255");
+        assert_eq!(eval_word("-1"), "255");
+        assert_eq!(eval_word("16 * 17"), "16");
+        env.settings.set("word", "i16").unwrap();
+        assert_eq!(eval("0x7fff + 1", &mut env).unwrap().unwrap().to_string(), "-32768");
+        assert!(env.settings.set("word", "129").is_err());
+    }
+
+    #[test]
+    fn intervals_pass_through_word_mode() {
+        let mut env = Env::default();
+        env.settings.set("word", "64").unwrap();
+        env.settings.set("interval", "on").unwrap();
+        let mut eval_word = |input: &str| eval(input, &mut env).unwrap().unwrap().to_string();
+        assert_eq!(eval_word("[0x10, 0x20] * 2"), "[32, 64]");
+        assert_eq!(eval_word("1 - [0x10, 0x20]"), "[-31, -15]");
+        assert_eq!(eval_word("[1, 2] + [3, 4]"), "[4, 6]");
+        assert!(eval("[1, 2] & 1", &mut env).is_err());
+    }
+
+    #[test]
+    fn unsigned_mode_treats_the_top_bit_as_magnitude() {
+        let mut env = Env::default();
+        env.settings.set("unsigned", "on").unwrap();
+        let mut eval_unsigned = |input: &str| eval(input, &mut env).unwrap().unwrap().to_string();
+        assert_eq!(eval_unsigned("0xffffffffffffffff"), "18446744073709551615");
+        assert_eq!(eval_unsigned("18446744073709551615 / 2"), "9223372036854775807");
+        assert_eq!(eval_unsigned("0x8000000000000000 >> 63"), "1");
+        assert_eq!(eval_unsigned("0x8000000000000000 > 1"), "1");
+        env.settings.set("word", "i32").unwrap();
+        env.settings.set("unsigned", "on").unwrap();
+        assert_eq!(eval("0xffffffff", &mut env).unwrap().unwrap().to_string(), "4294967295");
+        env.settings.set("unsigned", "off").unwrap();
+        assert_eq!(eval("0xffffffff", &mut env).unwrap().unwrap().to_string(), "-1");
+    }
+
+    #[test]
+    fn integers_hold_128_bits() {
+        let settings = Settings::default();
+        assert_eq!(eval_str("0xffffffffffffffff + 1"), "18446744073709551616");
+        assert_eq!(eval_str("1 << 126 >> 120"), "64");
+        assert_eq!(eval_str("0xffffffffffffffffffffffffffffffff"), "340282366920938463463374607431768211455");
+        assert_eq!(eval_str("0xffffffffffffffffffffffffffffffff == (u128) 0 - 1"), "1");
+        assert_eq!(eval_str("0x7fffffffffffffffffffffffffffffff"), "170141183460469231731687303715884105727");
+        assert_eq!(eval_str("(u128) 0 - 1"), "340282366920938463463374607431768211455");
+        assert_eq!(format_base("16", 0x0123_4567_89ab_cdef_0011_2233_4455_6677, "", &settings), "0x123456789abcdef0011223344556677");
+        assert_eq!(format_base("16", -1, "", &settings), "0xffffffffffffffff");
+        assert_eq!(format_base("16", -(1 << 64), "", &settings), "0xffffffffffffffff0000000000000000");
+        // A word size keeps patterns to its width, as for the sized literal 8'shFF
+        let settings = Settings { word: sized_type("8'shFF"), ..Settings::default() };
+        assert_eq!(format_base("16", -1, "", &settings), "0xff");
+        assert_eq!(format_base("2", -128, "", &settings), "b10000000");
+    }
+
+    #[test]
+    fn wide_literals_keep_all_128_bits() {
+        let settings = Settings::default();
+        let convert = |base: &str, input: &str| {
+            let (num, result) = convert_input(input, &settings, &mut Vec::new()).unwrap();
+            format_base(base, num, &result, &settings)
+        };
+        assert_eq!(convert("16", "0xffffffffffffffff8000000000000000"), "0xffffffffffffffff8000000000000000");
+        assert_eq!(convert("10", "0xffffffffffffffffffffffffffffffff"), "340282366920938463463374607431768211455");
+        assert_eq!(convert("8", "0xc0000000000000000000000000000000"), "Ox3000000000000000000000000000000000000000000");
+        assert_eq!(convert("16", "0x8000000000000000"), "0x8000000000000000");
+        assert_eq!(convert("10", "0xffffffffffffffff"), "18446744073709551615");
+        assert_eq!(convert("text", "0xff00000000000000000000000000000f"), format!("\"\\xff{}\\x0f\"", "\\x00".repeat(14)));
+        assert_eq!(convert("text", "-1"), "\"\\xff\\xff\\xff\\xff\\xff\\xff\\xff\\xff\"");
+        #[cfg(feature = "ip")]
+        assert_eq!(convert("16", "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"), "0xffffffffffffffffffffffffffffffff");
+        #[cfg(feature = "uuid")]
+        assert_eq!(convert("16", "ffffffff-ffff-ffff-ffff-ffffffffffff"), "0xffffffffffffffffffffffffffffffff");
+    }
+
+    #[test]
+    fn twos_complement_output_covers_the_width() {
+        let settings = Settings::default();
+        assert_eq!(format_base("t8", -1, "", &settings), "0xff");
+        assert_eq!(format_base("t16", -2, "", &settings), "0xfffe");
+        assert_eq!(format_base("t12", 5, "", &settings), "0x005");
+        assert_eq!(format_base("t8", 255, "", &settings), "0xff");
+        assert_eq!(format_base("t8", -129, "", &settings), "Error: -129 does not fit in 8 bits");
+        assert_eq!(check_force_output(&["=t32".to_string()], &settings), Some("t32"));
+        assert_eq!(check_force_output(&["=t129".to_string()], &settings), None);
+    }
+
+    #[test]
+    fn ones_complement_and_sign_magnitude_outputs() {
+        let settings = Settings::default();
+        assert_eq!(format_base("o8", -1, "", &settings), "0xfe");
+        assert_eq!(format_base("s8", -1, "", &settings), "0x81");
+        assert_eq!(format_base("o16", -5, "", &settings), "0xfffa");
+        assert_eq!(format_base("s16", -5, "", &settings), "0x8005");
+        assert_eq!(format_base("s8", 200, "", &settings), "0xc8");
+        assert_eq!(format_base("o8", -127, "", &settings), "0x80");
+        assert_eq!(format_base("o8", -128, "", &settings), "Error: -128 does not fit in 8 bits");
+        assert_eq!(format_base("s128", -1, "", &settings), "0x80000000000000000000000000000001");
+        assert_eq!(check_force_output(&["=s32".to_string()], &settings), Some("s32"));
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn overflowing_integers_promote_to_bigints() {
+        assert_eq!(eval_str("2 ** 130"), "1361129467683753853853498429727072845824");
+        assert_eq!(eval_str("2 ** 130 - 2 ** 130 + 1"), "1");
+        assert_eq!(eval_str("(1 << 200) >> 199"), "2");
+        assert_eq!(eval_str("170141183460469231731687303715884105727 + 1 > 0"), "1");
+        assert_eq!(eval_str("0x100000000000000000000000000000000 % 7"), "4");
+        assert_eq!(eval_str("len([2 ** 4096])"), "1");
+        let wide = convert_wide("0x100000000000000000000000000000000", "10", &Settings::default());
+        assert_eq!(wide.as_deref(), Some("340282366920938463463374607431768211456"));
+    }
+
+    #[test]
+    fn single_precision_bit_patterns() {
+        assert_eq!(eval_str("fx3fc00000"), "1.5");
+        assert_eq!(eval_str("fxc0490fdb"), "-3.1415927410125732");
+        assert_eq!(eval_str("1.5F"), "1069547520");
+        assert_eq!(convert_input("1F", &Settings::default(), &mut Vec::new()).map(|(num, _)| num), Ok(0x3f80_0000));
+    }
+
+    #[test]
+    fn explains_float_fields() {
+        let settings = Settings::default();
+        let float = ieee::parse("Fx3ff8000000000000", &settings).unwrap_or_else(|message| panic!("{}", message));
+        let lines = float.explain();
+        assert_eq!(lines[2], "  exponent  0x3ff: biased 1023, unbiased 0");
+        assert_eq!(lines[3], "  mantissa  0x8000000000000");
+        assert!(lines[4].ends_with("b × 2^0 (implied leading 1)"));
+        let single = ieee::parse("-0.75F", &settings).unwrap_or_else(|message| panic!("{}", message));
+        assert_eq!(single.explain()[0], "  bits      1 01111110 10000000000000000000000");
+        assert_eq!(ieee::parse("fx00000001", &settings).map(|float| float.class()), Ok("subnormal"));
+    }
+
+    #[test]
+    fn classifies_special_floats() {
+        let class = |input: &str| ieee::parse(input, &Settings::default()).map(|float| float.describe());
+        assert_eq!(class("Fx7ff8000000000000"), Ok("a quiet NaN".to_string()));
+        assert_eq!(class("Fx7ff0000000000001"), Ok("a signaling NaN".to_string()));
+        assert_eq!(class("fxff800000"), Ok("-infinity".to_string()));
+        assert_eq!(class("Fx8000000000000000"), Ok("-0".to_string()));
+        assert_eq!(class("Fx0000000000000001"), Ok("subnormal (5e-324)".to_string()));
+        assert_eq!(class("1.5"), Ok("normal (1.5)".to_string()));
+    }
+
+    #[test]
+    fn ulp_counts_representable_floats_between_values() {
+        assert_eq!(eval_str("ulp(1.0, 1.0)"), "0");
+        assert_eq!(eval_str("ulp(1.0, 1.0000000000000002)"), "1");
+        assert_eq!(eval_str("ulp(Fx8000000000000001, Fx0000000000000001)"), "2");
+        assert_eq!(eval_str("ulp(Fx8000000000000000, 0)"), "0");
+        assert_eq!(eval_str("ulpf(1.0, 2.0)"), "8388608");
+        assert!(eval("ulp(Fx7ff8000000000000, 1.0)", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn nextup_and_nextdown_step_to_adjacent_floats() {
+        assert_eq!(eval_str("nextup(1.0)"), "(1.0000000000000002, Fx3ff0000000000001)");
+        assert_eq!(eval_str("nextdown(1.0)"), "(0.9999999999999999, Fx3fefffffffffffff)");
+        assert_eq!(eval_str("nextup(Fx7fefffffffffffff)"), "(inf, Fx7ff0000000000000)");
+        assert_eq!(eval_str("nextupf(1.0)"), "(1.0000001192092896, fx3f800001)");
+        assert_eq!(eval_str("nextdownf(2.0)"), "(1.9999998807907104, fx3fffffff)");
+    }
+
+    #[test]
+    fn hex_float_literals() {
+        assert_eq!(eval_str("0x1.8p3"), "12");
+        assert_eq!(eval_str("0x1p-2 + 1"), "1.25");
+        assert_eq!(eval_str("0x.8P+1"), "1");
+        assert_eq!(eval_str("0x1p+1 - 1"), "1");
+        assert_eq!(ieee::parse_hex_float("0x1.fffffffffffff8p0"), Some(2.0));
+        assert_eq!(ieee::parse_hex_float("0x1p-1074"), Some(f64::from_bits(1)));
+        assert_eq!(ieee::parse_hex_float("0x1.8"), None);
+        assert_eq!(ieee::hex_float(12.0), "0x1.8p+3");
+        assert_eq!(ieee::hex_float(-0.25), "-0x1p-2");
+        assert_eq!(ieee::hex_float(f64::from_bits(1)), "0x0.0000000000001p-1022");
+    }
+
+    #[test]
+    fn any_radix_from_2_to_36() {
+        let settings = Settings::default();
+        assert_eq!(eval_str("36#ZZ"), "1295");
+        assert_eq!(eval_str("3#1201 + 36#z"), "81");
+        assert_eq!(format_base("3", 46, "", &settings), "3#1201");
+        assert_eq!(format_base("36", 1295, "", &settings), "36#ZZ");
+        assert_eq!(format_base("7", -8, "", &settings), "-7#11");
+        assert_eq!(check_force_output(&["=36".to_string()], &settings), Some("36"));
+        assert_eq!(check_force_output(&["=37".to_string()], &settings), None);
+        assert!(parse_num("3#123", &settings).is_err());
+        assert!(parse_num("37#1", &settings).is_err());
+    }
+
+    #[test]
+    fn custom_alphabets() {
+        let mut env = Env::default();
+        let digits = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+        env.settings.formats.define_alphabet("b62", digits).unwrap();
+        assert_eq!(eval("b62#10 + b62#z", &mut env).unwrap().unwrap().to_string(), "123");
+        assert_eq!(format_base("b62", 3843, "", &env.settings), "b62#zz");
+        assert_eq!(format_base("b62", 0, "", &env.settings), "b62#0");
+        let formats = &mut env.settings.formats;
+        assert!(formats.define_alphabet("b62", "01").is_err());
+        assert!(formats.define_alphabet("dup", "0120").is_err());
+        assert!(formats.define_alphabet("one", "0").is_err());
+        assert!(formats.define_alphabet("36", "01").is_err());
+        // Other environments never see it
+        assert!(eval("b62#10", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn character_literals_and_chr_ord() {
+        assert_eq!(eval_str("'A' + 1"), "66");
+        assert_eq!(eval_str("' ' + '+'"), "75");
+        assert_eq!(eval_str("'\\n'"), "10");
+        assert_eq!(eval_str("'\\''"), "39");
+        assert_eq!(eval_str("'\\u{1F600}'"), "128512");
+        assert_eq!(eval_str("chr(0x41)"), "'A'");
+        assert_eq!(eval_str("chr(10)"), "'\\n'");
+        assert_eq!(eval_str("ord(chr(97))"), "97");
+        assert!(eval("'ab'", &mut Env::default()).is_err());
+        assert!(eval("chr(0xd800)", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn unicode_code_points() {
+        let settings = Settings::default();
+        assert_eq!(eval_str("U+1F600 + 1"), "128513");
+        assert_eq!(eval_str("U+0041"), "65");
+        // Fewer than four digits is U plus a number, for a variable named U
+        assert_eq!(eval_str("{ let U = 5; U+1 }"), "6");
+        assert_eq!(eval_with(&[], &["U = 0x10", "U+41"]), Ok("57".to_string()));
+        assert!(parse_num("U+110000", &settings).is_err());
+        assert_eq!(format_base("unicode", 0x20ac, "", &settings), if cfg!(feature = "unicode") {
+            "U+20AC € EURO SIGN, block Currency Symbols, UTF-8 e2 82 ac, UTF-16 20ac"
+        } else {
+            "U+20AC € UTF-8 e2 82 ac, UTF-16 20ac"
+        });
+        assert_eq!(unicode::describe(0xd800), Some("U+D800 surrogate, not encodable on its own".to_string()));
+        assert_eq!(unicode::describe(0x110000), None);
+    }
+
+    #[test]
+    fn surrogate_pairs_in_both_directions() {
+        assert_eq!(eval_str("surrogates(U+1F600) == (0xd83d, 0xde00)"), "1");
+        assert_eq!(eval_str("unsurrogate(0xd83d, 0xde00) == U+1F600"), "1");
+        assert_eq!(eval_str("surrogates(U+10FFFF)"), "(56319, 57343)");
+        assert!(eval("surrogates(U+FFFF)", &mut Env::default()).is_err());
+        assert!(eval("unsurrogate(0xde00, 0xd83d)", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn string_literals_are_byte_strings() {
+        assert_eq!(eval_str("\"abc\""), "0x616263");
+        assert_eq!(eval_str("\"a b\\n\\xff\""), "0x6120620aff");
+        assert_eq!(eval_str("\"AB\" + 1"), "16707");
+        assert_eq!(eval_str("\"é\""), "0xc3a9");
+        assert_eq!(eval_str("text(0x616263)"), "\"abc\"");
+        assert_eq!(eval_str("text(\"tab\\there\\x00\")"), "\"tab\\there\\x00\"");
+        assert_eq!(format_base("text", 0x68690a, "", &Settings::default()), "\"hi\\n\"");
+        assert!(eval("\"0123456789abcdefg\" + 1", &mut Env::default()).is_err());
+        assert!(eval("\"\\q\"", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn byte_array_outputs() {
+        let settings = Settings::default();
+        assert_eq!(format_base("c", 0xdead, "", &settings), "{0xde, 0xad}");
+        assert_eq!(format_base("rust", 0xdead, "", &settings), "[0xde, 0xad]");
+        assert_eq!(format_base("python", 0xdead, "", &settings), "b\"\\xde\\xad\"");
+        assert_eq!(format_base("c", 0, "", &settings), "{0x00}");
+        assert!(settings.formats.clone().define_alphabet("rust", "01").is_err());
+    }
+
+    #[test]
+    fn byte_swaps() {
+        assert_eq!(eval_str("bswap32(0xDEADBEEF) & 0xFF00 == 0xAD00"), "1");
+        assert_eq!(eval_str("bswap16(0x1234) == 0x3412"), "1");
+        assert_eq!(eval_str("bswap64(0x0102030405060708) == 0x0807060504030201"), "1");
+        assert_eq!(eval_str("bswap32(0xff)"), "4278190080");
+        assert_eq!(eval_str("bswap16((i16) 0x80)"), "-32768");
+        assert!(eval("bswap16(0x10000)", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn byte_order_shows_both_endians() {
+        let style = format::Style::default();
+        assert_eq!(
+            format::byte_order(0xdeadbeef, None, &style),
+            ["  byte           0  1  2  3", "  big-endian    de ad be ef", "  little-endian ef be ad de"]
+        );
+        assert_eq!(format::byte_order(0x123, None, &style)[2], "  little-endian 23 01");
+        assert_eq!(format::byte_order(0x1, Some(4), &style)[1], "  big-endian    00 00 00 01");
+        assert_eq!(format::byte_order(0, None, &style)[1], "  big-endian    00");
+    }
+
+    #[test]
+    fn bit_reversal_within_a_width() {
+        assert_eq!(eval_str("brev(1101b, 4)"), "11");
+        assert_eq!(eval_str("brev(1, 8)"), "128");
+        assert_eq!(eval_str("brev(0x04C11DB7, 32) == 0xEDB88320"), "1");
+        assert_eq!(eval_str("brev((u8) 3)"), "192");
+        assert!(eval("brev(16, 4)", &mut Env::default()).is_err());
+        assert!(eval("brev(1, 0)", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn leading_and_trailing_zero_counts() {
+        assert_eq!(eval_str("clz(1, 32)"), "31");
+        assert_eq!(eval_str("clz(0, 16)"), "16");
+        assert_eq!(eval_str("clz((u8) 0x10)"), "3");
+        assert_eq!(eval_str("ctz(0x50)"), "4");
+        assert_eq!(eval_str("ctz(0, 32)"), "32");
+        assert_eq!(eval_str("ctz((i16) 0)"), "16");
+        assert_eq!(eval_str("popcount(0xF0F0)"), "8");
+        assert!(eval("ctz(0)", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn parity_bits() {
+        assert_eq!(eval_str("parity(1011b)"), "1");
+        assert_eq!(eval_str("parity(0x41)"), "0");
+        assert_eq!(eval_str("even_parity(7)"), "1");
+        assert_eq!(eval_str("odd_parity(7)"), "0");
+        assert_eq!(eval_str("odd_parity(0)"), "1");
+    }
+
+    #[test]
+    fn packed_bcd() {
+        assert_eq!(eval_str("bcd(1234) == 0x1234"), "1");
+        assert_eq!(eval_str("unbcd(0x1234)"), "1234");
+        assert_eq!(eval_str("unbcd(bcd(99999999999999999999999999999999))"), "99999999999999999999999999999999");
+        assert_eq!(eval_str("bcd(99999999999999999999999999999999)"), "204169420152563078078024764459060926873");
+        assert!(eval("unbcd(0x1A)", &mut Env::default()).is_err());
+        assert!(eval("bcd(100000000000000000000000000000000)", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn negabinary_and_balanced_ternary() {
+        let settings = Settings::default();
+        assert_eq!(format_base("-2", 6, "", &settings), "-2#11010");
+        assert_eq!(format_base("-2", -3, "", &settings), "-2#1101");
+        assert_eq!(format_base("-2", 0, "", &settings), "-2#0");
+        assert_eq!(format_base("bal3", 5, "", &settings), "bal3#1TT");
+        assert_eq!(format_base("bal3", -5, "", &settings), "bal3#T11");
+        assert_eq!(check_force_output(&["=bal3".to_string()], &settings), Some("bal3"));
+        assert_eq!(format_base("16", i128::MIN, "", &settings), format::hex(i128::MIN, &format::Style::default()));
+        assert_eq!(format::radix(i128::MIN, 16), "-16#80000000000000000000000000000000");
+        assert!(format::negabinary(i128::MIN).starts_with("-2#1"));
+        assert!(format::balanced_ternary(i128::MAX).starts_with("bal3#1"));
+    }
+
+    #[test]
+    fn q_format_fixed_point() {
+        assert_eq!(eval_str("q15(0x4000)"), eval_str("0.5"));
+        assert_eq!(eval_str("q15(0xc000)"), eval_str("1 - 1.5"));
+        assert_eq!(eval_str("toq15(0.5) == 0x4000"), "1");
+        assert_eq!(eval_str("toq15(1 - 1.5) == 0xc000"), "1");
+        assert_eq!(eval_str("uq16(0xc000)"), eval_str("0.75"));
+        assert_eq!(eval_str("q(0x180, 7, 8)"), eval_str("1.5"));
+        assert_eq!(eval_str("touq(1.5, 8, 8) == 0x180"), "1");
+        assert!(eval("toq15(1.0)", &mut Env::default()).is_err());
+        assert!(eval("q15(0x10000)", &mut Env::default()).is_err());
+        assert!(eval("q(1, 100, 100)", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn zigzag_round_trip() {
+        assert_eq!(eval_str("zigzag(-1)"), "1");
+        assert_eq!(eval_str("zigzag(2)"), "4");
+        assert_eq!(eval_str("zigzag(-3)"), "5");
+        assert_eq!(eval_str("unzigzag(5)"), "-3");
+        let min = "-170141183460469231731687303715884105727 - 1";
+        assert_eq!(eval_str(&format!("unzigzag(zigzag({}))", min)), eval_str(min));
+        assert!(eval("unzigzag(-1)", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn digit_grouping() {
+        assert_eq!(format::group("11111111000000001111", 4, '_'), "1111_1111_0000_0000_1111");
+        assert_eq!(format::group("1234567", 3, ','), "1,234,567");
+        assert_eq!(format::group("123", 3, ','), "123");
+        assert_eq!(format::group("fff", 4, '_'), "fff");
+        // Each environment groups by its own setting
+        assert_eq!(eval_with(&[("group", "on")], &["1234567"]), Ok("1_234_567".to_string()));
+        assert_eq!(eval_with(&[], &["1234567"]), Ok("1234567".to_string()));
+        let settings = Settings { style: format::Style { group: Some(','), ..format::Style::default() }, ..Settings::default() };
+        assert_eq!(format_base("16", 0xdeadbeef, "", &settings), "0xdead_beef");
+    }
+
+    #[test]
+    fn separators_in_input() {
+        let settings = Settings::default();
+        assert_eq!(eval_str("0xDEAD_BEEF == 0xdeadbeef"), "1");
+        assert_eq!(eval_str("1_000_000 + 1"), "1000001");
+        assert_eq!(eval_str("1,000 + 12,345,678"), "12346678");
+        assert_eq!(eval_str("1,234.5 * 2"), eval_str("2469.0"));
+        assert_eq!(eval_str("len([1,000])"), "2");
+        assert_eq!(eval_str("len([1,2])"), "2");
+        assert_eq!(parse_num("1,000", &settings).ok(), parse_num("1000", &settings).ok());
+        assert_eq!(parse_num("1101_0000b", &settings).ok(), parse_num("11010000b", &settings).ok());
+        assert!(parse_num("1,00", &settings).is_err());
+        assert!(parse_num("1234,567", &settings).is_err());
+    }
+
+    #[test]
+    fn c_and_rust_literals() {
+        let settings = Settings::default();
+        assert_eq!(eval_str("0b1010"), "10");
+        assert_eq!(eval_str("0o755"), "493");
+        assert_eq!(eval_str("0XFF"), "255");
+        assert_eq!(eval_str("10UL + 0xffu8 + 1_000i64 + 7ull + 2L"), "1274");
+        assert_eq!(eval_str("0b1111_0000u8"), "240");
+        assert!(parse_num("10uu", &settings).is_err());
+        assert!(parse_num("0b102", &settings).is_err());
+    }
+
+    #[test]
+    fn verilog_sized_literals() {
+        let settings = Settings::default();
+        assert_eq!(eval_str("8'hFF"), "255");
+        assert_eq!(eval_str("8'shFF"), "-1");
+        assert_eq!(eval_str("16'b1010_1010"), "170");
+        assert_eq!(eval_str("32'd100 + 1"), "101");
+        assert_eq!(eval_str("8'hFF + 1"), "0");
+        assert_eq!(eval_str("4'hFF"), "15");
+        assert_eq!(parse_num("8'shFF", &settings).ok(), Some("-1".to_string()));
+        assert!(parse_num("8'hFG", &settings).is_err());
+        assert!(parse_num("8'q1", &settings).is_err());
+        assert!(parse_num("0'h1", &settings).is_err());
+    }
+
+    #[test]
+    fn radix_letters_name_the_digits() {
+        let settings = Settings::default();
+        assert_eq!(eval_str("b101 + 101b"), "10");
+        assert_eq!(eval_str("Ox17 + 17o"), "30");
+        assert_eq!(eval_str("255d"), "255");
+        assert_eq!(to_int(&format_base("2", 10, "", &settings), &settings).ok(), Some(10));
+        assert_eq!(to_int(&format_base("8", 493, "", &settings), &settings).ok(), Some(493));
+        assert!(parse_num("Bx5", &settings).is_err());
+        assert!(parse_num("b102", &settings).is_err());
+    }
+
+    #[test]
+    fn scientific_notation() {
+        let settings = Settings::default();
+        assert_eq!(eval_str("1.5e6 == 1500000"), "1");
+        assert_eq!(eval_str("2E-3 * 1000"), "2");
+        assert_eq!(eval_str("1e+2 - 1"), "99");
+        assert_eq!(convert_input("1.5e6", &settings, &mut Vec::new()).map(|(num, _)| num), Ok(1_500_000));
+        assert_eq!(format_base("e", 1_500_000, "1500000", &settings), "1.5e6");
+        assert_eq!(format_base("e", 0, "0.002", &settings), "2e-3");
+        assert_eq!(format_base("e3", 123_456, "123456", &settings), "1.23e5");
+        // Fractions reach float outputs as written, plain or scientific
+        assert_eq!(convert_float("2E-3", "e", &settings), Some("2e-3".to_string()));
+        assert_eq!(convert_float("1.5", "e", &settings), Some("1.5e0".to_string()));
+        assert_eq!(convert_float("1.5", "a", &settings), Some("0x1.8p+0".to_string()));
+        assert_eq!(convert_float("1.5", "16", &settings), None);
+        assert_eq!(convert_float("1500", "e", &settings), None);
+        assert_eq!(check_force_output(&["=e17".to_string()], &settings), Some("e17"));
+        assert_eq!(check_force_output(&["=e18".to_string()], &settings), None);
+    }
+
+    #[test]
+    fn si_prefix_output() {
+        assert_eq!(format::si(1_234_000.0), "1.234 M");
+        assert_eq!(format::si(56_700.0), "56.7 k");
+        assert_eq!(format::si(890e-9), "890 n");
+        assert_eq!(format::si(4.7e-6), "4.7 µ");
+        assert_eq!(format::si(999_960.0), "1 M");
+        assert_eq!(format::si(-2200.0), "-2.2 k");
+        assert_eq!(format::si(12.5), "12.5");
+        assert_eq!(format_base("si", 0, "0.0033", &Settings::default()), "3.3 m");
+        assert_eq!(convert_float("4.7e-3", "si", &Settings::default()), Some("4.7 m".to_string()));
+        assert_eq!(convert_float("0.0047", "si", &Settings::default()), Some("4.7 m".to_string()));
+    }
+
+    #[test]
+    fn byte_size_units() {
+        let settings = Settings::default();
+        assert_eq!(eval_str("4MiB"), "4194304");
+        assert_eq!(eval_str("1.5GB"), "1500000000");
+        assert_eq!(eval_str("2KiB + 512B"), "2560");
+        assert_eq!(eval_str("1TB / 4KiB"), eval_str("1000000000000 / 4096"));
+        assert_eq!(format_base("bytes", 1536, "", &settings), "1.5 KiB (1.536 kB)");
+        assert_eq!(format_base("bytes", 4 << 30, "", &settings), "4 GiB (4.295 GB)");
+        assert_eq!(format_base("bytes", 512, "", &settings), "512 B");
+        assert!(parse_num("4MIB", &settings).is_err());
+    }
+
+    #[test]
+    fn duration_arithmetic() {
+        assert_eq!(eval_str("1h30m + 45s - 500ms"), "1h30m44.5s");
+        assert_eq!(eval_str("90s"), "1m30s");
+        assert_eq!(eval_str("1h / 4"), "15m");
+        assert_eq!(eval_str("0.5 * 1ms"), "500us");
+        assert_eq!(eval_str("1h / 30m"), "2");
+        assert_eq!(eval_str("3s > 2500ms"), "1");
+        assert_eq!(eval_str("1s - 2s"), "-1s");
+        assert!(eval("1h + 5", &mut Env::default()).is_err());
+    }
+
+    #[cfg(feature = "ip")]
+    #[test]
+    fn ipv4_addresses() {
+        let settings = Settings::default();
+        assert_eq!(parse_num("192.168.1.1", &settings).ok(), parse_num("0xC0A80101", &settings).ok());
+        assert_eq!(eval_str("10.0.0.1 + 1"), "167772162");
+        assert_eq!(format_base("ip", 0xC0A80101, "", &settings), "192.168.1.1");
+        assert_eq!(format_base("ip", 0, "", &settings), "0.0.0.0");
+        assert_eq!(format_base("ip", 1 << 32, "", &settings), "Error: 4294967296 is not an IPv4 address (0 to 0xffffffff)");
+        assert_eq!(check_force_output(&["=ip".to_string()], &settings), Some("ip"));
+        assert!(parse_num("256.0.0.1", &settings).is_err());
+        assert!(parse_num("1.2.3", &settings).is_err());
+    }
+
+    #[cfg(feature = "ip")]
+    #[test]
+    fn ipv6_addresses() {
+        let settings = Settings::default();
+        assert_eq!(parse_num("2001:db8::1", &settings).ok(), parse_num("0x20010db8000000000000000000000001", &settings).ok());
+        assert_eq!(to_int("::1", &settings).ok(), Some(1));
+        assert_eq!(format_base("ip6", 0x20010db8 << 96 | 1, "", &settings), "2001:db8::1");
+        assert_eq!(format_base("ip6full", 0x20010db8 << 96 | 1, "", &settings), "2001:0db8:0000:0000:0000:0000:0000:0001");
+        assert_eq!(format_base("ip6", -1, "", &settings), "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff");
+        assert!(parse_num("1::2::3", &settings).is_err());
+    }
+
+    #[cfg(feature = "mac")]
+    #[test]
+    fn mac_addresses() {
+        let settings = Settings::default();
+        assert_eq!(to_int("aa:bb:cc:dd:ee:ff", &settings).ok(), Some(0xaabbccddeeff));
+        assert_eq!(to_int("AA-BB-CC-DD-EE-FF", &settings).ok(), Some(0xaabbccddeeff));
+        assert_eq!(to_int("aabb.ccdd.eeff", &settings).ok(), Some(0xaabbccddeeff));
+        assert_eq!(format_base("mac", 0x001b638445e6, "", &settings), "00:1b:63:84:45:e6");
+        assert_eq!(format_base("macdash", 0x001b638445e6, "", &settings), "00-1B-63-84-45-E6");
+        assert_eq!(format_base("macdot", 0x001b638445e6, "", &settings), "001b.6384.45e6");
+        assert_eq!(format_base("mac", -1, "", &settings), "Error: -1 is not a MAC address (0 to 0xffffffffffff)");
+        assert_eq!(eval_str("oui(00:1b:63:84:45:e6)"), "00:1b:63");
+        assert!(parse_num("aa:bb:cc:dd:ee", &settings).is_err());
+        assert!(parse_num("aa:bb-cc:dd:ee:ff", &settings).is_err());
+    }
+
+    #[cfg(feature = "ip")]
+    #[test]
+    fn subnets() {
+        let lines = format::subnet("10.0.1.7/22", &format::Style::default()).unwrap_or_default();
+        assert_eq!(lines[0], "  netmask     255.255.252.0 (0xfffffc00)");
+        assert_eq!(lines[1], "  wildcard    0.0.3.255 (0x3ff)");
+        assert_eq!(lines[2], "  network     10.0.0.0");
+        assert_eq!(lines[3], "  broadcast   10.0.3.255");
+        assert_eq!(lines[4], "  first host  10.0.0.1");
+        assert_eq!(lines[5], "  last host   10.0.3.254");
+        assert_eq!(lines[6], "  hosts       1022");
+        let hosts = |cidr| format::subnet(cidr, &format::Style::default()).map(|lines| lines[6].clone()).unwrap_or_default();
+        assert_eq!(hosts("192.168.0.1/32"), "  hosts       1");
+        assert_eq!(hosts("0.0.0.0/0"), "  hosts       4294967294");
+        assert!(format::subnet("10.0.0.0/33", &format::Style::default()).is_err());
+        assert!(format::subnet("10.0.0.0", &format::Style::default()).is_err());
+    }
+
+    #[cfg(feature = "roman")]
+    #[test]
+    fn roman_numerals() {
+        let settings = Settings::default();
+        assert_eq!(eval_str("0rMMXXIV + 1"), "2025");
+        assert_eq!(eval_str("0rX"), "10");
+        assert_eq!(eval_str("roman(1994)"), "MCMXCIV");
+        assert_eq!(eval_str("roman(\"MMXXIV\")"), "2024");
+        assert_eq!(format_base("roman", 3999, "", &settings), "MMMCMXCIX");
+        assert_eq!(format_base("roman", 0, "", &settings), "Error: 0 has no Roman numeral (1 to 3999)");
+        assert_eq!(check_force_output(&["=roman".to_string()], &settings), Some("roman"));
+        assert!(parse_num("0rIIII", &settings).is_err());
+        assert!(parse_num("0rIM", &settings).is_err());
+        assert!(eval("roman(4000)", &mut Env::default()).is_err());
+        assert!(eval("roman(\"IIII\")", &mut Env::default()).is_err());
+    }
+
+    #[cfg(feature = "roman")]
+    #[test]
+    fn numeral_shaped_names_are_variables() {
+        let mut env = Env::default();
+        for name in ["XL", "CD", "DC", "MIX", "CV", "X"] {
+            eval(&format!("{} = 7", name), &mut env).unwrap();
+            assert_eq!(eval(&format!("{} + 1", name), &mut env).unwrap().unwrap().to_string(), "8");
+        }
+    }
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn network_byte_order() {
+        assert_eq!(eval_str("htons(0x1234) == 0x3412"), "1");
+        assert_eq!(eval_str("htonl(0xC0A80101) == 0x0101A8C0"), "1");
+        assert_eq!(eval_str("ntohl(htonl(0xDEADBEEF)) == 0xDEADBEEF"), "1");
+        assert_eq!(eval_str("ntohs((u16) 0x0050)"), "20480");
+        assert!(eval("htons(0x10000)", &mut Env::default()).is_err());
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuids() {
+        let settings = Settings::default();
+        assert_eq!(to_int("550e8400-e29b-41d4-a716-446655440000", &settings).ok(), Some(0x550e8400e29b41d4a716446655440000));
+        assert_eq!(format_base("uuid", 0x550e8400e29b41d4a716446655440000, "", &settings), "550e8400-e29b-41d4-a716-446655440000");
+        let fields = format::uuid_fields(0x550e8400e29b41d4a716446655440000);
+        assert_eq!(fields[2], "  version  4 (random)");
+        assert_eq!(fields[3], "  variant  RFC 9562 (10x)");
+        assert_eq!(fields.len(), 4);
+        // Examples from RFC 9562, appendix A
+        let time = |uuid: u128| format::uuid_fields(uuid).get(4).cloned().unwrap_or_default();
+        assert_eq!(time(0xC232AB00_9414_11EC_B3C8_9F6BDECED846), "  time     2022-02-22T19:22:22.000000Z");
+        assert_eq!(time(0x1EC9414C_232A_6B00_B3C8_9F6BDECED846), "  time     2022-02-22T19:22:22.000000Z");
+        assert_eq!(time(0x017F22E2_79B0_7CC3_98C4_DC0C0C07398F), "  time     2022-02-22T19:22:22.000Z");
+        assert!(parse_num("550e8400-e29b-41d4-a716-44665544000", &settings).is_err());
+    }
+
+    #[test]
+    fn crc_builtins() {
+        let checks = [
+            ("crc8", 0xf4u32),
+            ("crc8_maxim", 0xa1),
+            ("crc16", 0xbb3d),
+            ("crc16_modbus", 0x4b37),
+            ("crc16_ccitt", 0x29b1),
+            ("crc16_xmodem", 0x31c3),
+            ("crc16_kermit", 0x2189),
+            ("crc32", 0xcbf43926),
+            ("crc32c", 0xe3069283),
+        ];
+        for (name, check) in checks {
+            assert_eq!(eval_str(&format!("{}(\"123456789\")", name)), check.to_string(), "{}", name);
+        }
+        assert_eq!(eval_str("crc32(0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39)"), "3421780262");
+        assert_eq!(eval_str("crc32([0x31, 0x32, 0x33], \"456789\")"), "3421780262");
+        assert_eq!(eval_str("crc32(\"\")"), "0");
+        assert!(eval("crc8(256)", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn digest_builtins() {
+        assert_eq!(eval_str("md5(\"abc\")"), "0x900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(eval_str("sha1(0x61, 0x62, 0x63) == sha1(\"abc\")"), "1");
+        assert_eq!(eval_str("sha256(\"\") == sha256(unhex(\"\"))"), "1");
+    }
+
+    #[test]
+    fn adler_and_fletcher_builtins() {
+        assert_eq!(eval_str("adler32(\"Wikipedia\") == 0x11E60398"), "1");
+        assert_eq!(eval_str("fletcher16(\"abcde\") == 0xC8F0"), "1");
+        assert_eq!(eval_str("fletcher32(\"abcde\") == 0xF04FC729"), "1");
+        assert_eq!(eval_str("adler32(0x61, 0x62, 0x63) == adler32(\"abc\")"), "1");
+        assert_eq!(eval_str("adler32(\"\")"), "1");
+    }
+
+    #[test]
+    fn library_calls_return_warnings_instead_of_printing() {
+        let settings = Settings::default();
+        let mut warnings = Vec::new();
+        assert_eq!(convert_input("0.1f", &settings, &mut warnings).map(|(num, _)| num), Ok(0x3fb9_9999_9999_999a));
+        assert_eq!(warnings, ["0.1 is not exactly representable in binary; stored as 0.1000000000000000055511151231257827021181583404541015625"]);
+        warnings.clear();
+        assert!(convert_input("Fx7ff0000000000000", &settings, &mut warnings).is_err());
+        assert_eq!(warnings.len(), 1);
+        warnings.clear();
+        assert!(convert_input("0xff", &settings, &mut warnings).is_ok() && warnings.is_empty());
+        assert_eq!(format!("{:?}", parse_num("0xzz", &settings).unwrap_err()), "ParseIntError");
+    }
+
+    #[test]
+    fn libraries_load_all_or_nothing() {
+        let path = std::env::temp_dir().join(format!("raxrs-library-{}.rax", std::process::id()));
+        let load = |contents: &str, env: &mut Env| {
+            std::fs::write(&path, contents).unwrap();
+            load_library(&path, env)
+        };
+        let mut env = Env::default();
+        assert_eq!(load("a = 1\nf(x) = x + a\nb = f(2)\n", &mut env).map(|(count, _)| count), Ok(3));
+        assert_eq!(eval("f(b)", &mut env).unwrap().unwrap().to_string(), "4");
+        let (count, notes) = load("a = 2\nb = 3\n", &mut env).unwrap();
+        assert_eq!(count, 2);
+        assert!(notes.len() == 1 && notes[0].ends_with(":1 redefines a"), "{:?}", notes);
+        // A bad later line keeps the earlier ones from taking effect
+        assert!(load("c = 5\nd = missing + 1\n", &mut env).unwrap_err().contains(":2\n"));
+        assert!(load("c = 5\nd = 1 +* 2\n", &mut env).is_err());
+        assert!(load("c = 5\na + 1\n", &mut env).unwrap_err().ends_with(":2: expected a definition"));
+        assert!(eval("c", &mut Env::default()).is_err() && eval("c", &mut env).is_err());
+        assert_eq!(eval("a", &mut env).unwrap().unwrap().to_string(), "2");
+        assert_eq!(load("", &mut env).map(|(count, _)| count), Ok(0));
+        std::fs::remove_file(&path).unwrap();
+        assert!(load_library(&path, &mut env).unwrap_err().starts_with("Cannot read"));
+    }
+
+    #[test]
+    fn checksums_of_hex_strings_and_files() {
+        assert_eq!(eval_str("adler32(unhex(\"57 69 6b 69 70 65 64 69 61\")) == 0x11E60398"), "1");
+        assert_eq!(eval_str("unhex(\"0xDEADBEEF\")"), "0xdeadbeef");
+        assert!(eval("unhex(\"ABC\")", &mut Env::default()).is_err());
+        let path = std::env::temp_dir().join(format!("raxrs-checksum-{}", std::process::id()));
+        std::fs::write(&path, "abcde").unwrap();
+        let sum = eval_str(&format!("fletcher32(file(\"{}\")) == 0xF04FC729", path.display()));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(sum, "1");
+        assert!(eval("file(\"/nonexistent/raxrs\")", &mut Env::default()).is_err());
+    }
+
+    // Evaluates each line in one environment with `settings` applied first,
+    // returning the last result or the first error message
+    fn eval_with(settings: &[(&str, &str)], lines: &[&str]) -> Result<String, String> {
+        let mut env = Env::default();
+        for (name, value) in settings {
+            env.settings.set(name, value).unwrap();
+        }
+        let mut last = String::new();
+        for line in lines {
+            match eval(line, &mut env) {
+                Ok(value) => last = value.map(|value| display_value(&value, &env.settings)).unwrap_or_default(),
+                Err(diagnostics) => return Err(diagnostics[0].message.clone()),
+            }
+        }
+        Ok(last)
+    }
+
+    #[test]
+    fn each_env_reads_literals_in_its_own_grammar() {
+        assert_eq!(eval_with(&[("literals", "legacy")], &["b101"]), Ok("101".to_string()));
+        assert_eq!(eval_with(&[], &["b101"]), Ok("5".to_string()));
+        // A library is read in the grammar of the environment it loads into
+        let path = std::env::temp_dir().join(format!("raxrs-literals-{}.rax", std::process::id()));
+        std::fs::write(&path, "x = 11d\n").unwrap();
+        let (mut legacy, mut standard) = (Env::default(), Env::default());
+        legacy.settings.set("literals", "legacy").unwrap();
+        assert!(load_library(&path, &mut legacy).is_ok() && load_library(&path, &mut standard).is_ok());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(eval("x", &mut legacy).unwrap().unwrap().to_string(), "3");
+        assert_eq!(eval("x", &mut standard).unwrap().unwrap().to_string(), "11");
+    }
+
+    #[test]
+    fn interval_arithmetic_propagates_bounds() {
+        let interval = |input: &str| eval_with(&[("interval", "on")], &[input]);
+        assert_eq!(interval("100 ± 5"), Ok("[95, 105]".to_string()));
+        assert_eq!(interval("[0x10, 0x20] + 1"), Ok("[17, 33]".to_string()));
+        assert_eq!(interval("[1, 2] * [3, 4]"), Ok("[3, 8]".to_string()));
+        // Mixed signs take the extreme products
+        assert_eq!(interval("[-1, 2] * [-3, 4]"), Ok("[-6, 8]".to_string()));
+        assert_eq!(interval("[1, 2] - [1, 2]"), Ok("[-1, 1]".to_string()));
+        assert_eq!(interval("[1, 2] / [1, 2]"), Ok("[0.5, 2]".to_string()));
+        assert_eq!(interval("[3, 3]"), Ok("[3, 3]".to_string()));
+        assert_eq!(interval("1 / [0, 1]"), Err("Division by an interval containing zero".to_string()));
+        assert_eq!(interval("[2, 1]"), Err("Interval lower bound exceeds upper bound".to_string()));
+        // Without the mode, brackets make lists
+        assert_eq!(eval_str("[1, 2] * 2"), "[2, 4]");
+    }
+
+    #[test]
+    fn exact_decimals() {
+        let decimal = |input: &str| eval_with(&[("numerics", "decimal")], &[input]);
+        assert_eq!(decimal("0.1 + 0.2"), Ok("0.3".to_string()));
+        assert_eq!(decimal("0.1 * 3 == 0.3"), Ok("1".to_string()));
+        assert_eq!(decimal("2.50 * 4"), Ok("10".to_string()));
+        assert_eq!(decimal("1 / 3"), Ok("0.3333333333333333333333333333".to_string()));
+        assert_eq!(eval_str("0.1 + 0.2"), "0.30000000000000004");
+        let mut env = Env::default();
+        assert!(env.settings.set("numerics", "binary").is_err());
+    }
+
+    #[test]
+    fn complex_numbers() {
+        assert_eq!(eval_str("3+4i"), "3+4i");
+        assert_eq!(eval_str("abs(3+4i)"), "5");
+        assert_eq!(eval_str("(1+2i) * (3-4i)"), "11+2i");
+        assert_eq!(eval_str("1i * 1i"), "-1+0i");
+        assert_eq!(eval_str("conj(3+4i)"), "3-4i");
+        assert_eq!(eval_str("arg(1i)"), std::f64::consts::FRAC_PI_2.to_string());
+        assert_eq!(eval_str("re(3+4i) + im(3+4i)"), "7");
+        assert_eq!(eval_with(&[("complex", "polar")], &["1+1i"]), Ok("1.4142135623730951∠45°".to_string()));
+        assert_eq!(eval_with(&[("complex", "polar"), ("complex", "rect")], &["1+1i"]), Ok("1+1i".to_string()));
+    }
+
+    #[test]
+    fn lists_operate_element_wise() {
+        assert_eq!(eval_str("[1, 2, 3] * 4"), "[4, 8, 12]");
+        assert_eq!(eval_str("[1, 2] + [10, 20]"), "[11, 22]");
+        assert_eq!(eval_str("[1, 2, 3][1]"), "2");
+        assert_eq!(eval_str("len([1, 2, 3])"), "3");
+        assert_eq!(eval_str("len([])"), "0");
+        assert_eq!(eval_str("0..3"), "[0, 1, 2, 3]");
+        assert_eq!(eval_with(&[], &["[1, 2] + [1]"]), Err("Lists must have the same length".to_string()));
+        assert!(eval("[1, 2][2]", &mut Env::default()).is_err());
+        // Stray closers and bare commas are errors rather than dropped
+        let error = |input: &str| {
+            let diagnostics = eval(input, &mut Env::default()).unwrap_err();
+            (diagnostics[0].message.clone(), diagnostics[0].span.clone())
+        };
+        assert_eq!(error("]"), ("Unmatched ]".to_string(), 0..1));
+        assert_eq!(error("[1, 2]]"), ("Unmatched ]".to_string(), 6..7));
+        assert_eq!(error("1)"), ("Unmatched )".to_string(), 1..2));
+        assert_eq!(error("1,2"), ("Commas only separate arguments, list items and tuple elements".to_string(), 1..2));
+    }
+
+    #[test]
+    fn bitsets_wider_than_128_bits() {
+        assert_eq!(eval_str("set(0..7, 32)"), "0x00000001_000000ff");
+        assert_eq!(eval_str("popcount(set(0..7, 32))"), "9");
+        assert_eq!(eval_str("popcount(set(0..255))"), "256");
+        assert_eq!(eval_str("set(100) >> 99"), "0x00000002");
+        assert_eq!(eval_str("popcount(set(1) << 300)"), "1");
+        assert_eq!(eval_str("set(0..3) & set(2..5)"), "0x0000000c");
+        assert_eq!(eval_str("set(0..3) | set(2..5)"), "0x0000003f");
+        assert_eq!(eval_str("set(0..3) ^ set(2..5)"), "0x00000033");
+        assert_eq!(eval_str("(set(200) | set(0)) >> 200"), "0x00000001");
+    }
+
+    #[test]
+    fn bounded_loops() {
+        assert_eq!(eval_str("{ let s = 0; for i in 0..4 { s = s + i }; s }"), "10");
+        assert_eq!(eval_str("{ let n = 1; while n < 100 { n = n * 3 }; n }"), "243");
+        assert_eq!(eval_str("for i in 0..3 { i * i }"), "[0, 1, 4, 9]");
+        assert_eq!(eval_str("for i in [5, 6] { i }"), "[5, 6]");
+        // The loop variable shadows an outer one only inside the loop
+        assert_eq!(eval_str("{ let i = 9; for i in 0..2 { i }; i }"), "9");
+        assert_eq!(eval_str("{ let n = 5; while n < 1 { n = n + 1 }; n }"), "5");
+        let error = |input: &str| eval_with(&[], &[input]).unwrap_err();
+        assert_eq!(error("{ let n = 0; while 1 { n = n + 1 }; n }"), "Loop stopped after 100000 iterations");
+        assert_eq!(error("for i in 5 { i }"), "for loops need a list such as 0..15");
+        assert_eq!(error("{ x = 1 }"), "x is not declared in this block; use let x = ...");
+    }
+
+    #[test]
+    fn library_api_tokenizes_parses_and_evaluates() {
+        let settings = Settings::default();
+        let (tokens, diagnostics) = tokenize("0x10 + width", &settings);
+        assert!(diagnostics.is_empty());
+        assert_eq!(tokens.iter().map(|spanned| spanned.token.clone()).collect::<Vec<_>>()[1..], [
+            Token::Plus,
+            Token::Ident("width".to_string())
+        ]);
+        assert_eq!(tokens[2].span, 7..12);
+        let (_, diagnostics) = tokenize("3 + 12q * 4zz", &settings);
+        assert_eq!(diagnostics.iter().map(|diagnostic| diagnostic.span.clone()).collect::<Vec<_>>(), [4..7, 10..13]);
+        assert!(matches!(parse("width = 0x10", &settings), Ok(Statement::Assign(name, _)) if name == "width"));
+        assert!(matches!(parse("f(x) = x", &settings), Ok(Statement::Function(..))));
+        assert!(matches!(parse("1 + 1", &settings), Ok(Statement::Expr(_))));
+        assert!(parse("3 + 12q", &settings).is_err_and(|diagnostics| diagnostics[0].span == (4..7)));
+        let mut env = Env::default();
+        assert_eq!(eval("width = 0x10", &mut env).unwrap(), Some(Value::Int(16)));
+        assert_eq!(eval("width * 2", &mut env).unwrap(), Some(Value::Int(32)));
+        let (expanded, warnings, result) = evaluate_line("width + 0.1", &mut env);
+        assert_eq!(expanded, "width + 0.1");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(result.unwrap().unwrap().to_string(), "16.1");
+    }
+
+    #[test]
+    fn aliases_expand_whole_words_once() {
+        let mut env = Env::default();
+        env.aliases.insert("kb".to_string(), "* 1024".to_string());
+        env.aliases.insert("mb".to_string(), "kb kb".to_string());
+        assert_eq!(eval("4 kb", &mut env).unwrap().unwrap().to_string(), "4096");
+        // Not inside longer words, and not recursively
+        assert_eq!(expand_aliases("kbs + kb", &env.aliases), "kbs + * 1024");
+        assert_eq!(expand_aliases("1 mb", &env.aliases), "1 kb kb");
+        assert_eq!(expand_aliases("", &env.aliases), "");
+        assert_eq!(expand_aliases("kb", &BTreeMap::new()), "kb");
+    }
+
+    #[test]
+    fn every_bad_literal_is_reported_at_once() {
+        let Err(diagnostics) = eval("3 + 12q * 4zz", &mut Env::default()) else { panic!("3 + 12q * 4zz parsed") };
+        let found: Vec<_> = diagnostics.iter().map(|diagnostic| (diagnostic.span.clone(), &diagnostic.message[..])).collect();
+        assert_eq!(found, [(4..7, "Could not parse number 12q"), (10..13, "Could not parse number 4zz")]);
+        assert_eq!(eval("f(1) + 12q", &mut Env::default()).unwrap_err().len(), 1);
+        assert!(eval("1 + 2", &mut Env::default()).is_ok());
+    }
+
+    #[test]
+    fn lossy_steps_are_warned_about() {
+        let warnings = |input: &str| {
+            let (_, warnings, result) = evaluate_line(input, &mut Env::default());
+            assert!(result.is_ok(), "{} failed", input);
+            warnings.into_iter().map(|warning| (warning.span, warning.message)).collect::<Vec<_>>()
+        };
+        assert_eq!(warnings("0.5 + 0.25"), []);
+        assert_eq!(warnings("0.1 + 1"), [(
+            0..3,
+            "warning: 0.1 is not exactly representable in binary; stored as \
+             0.1000000000000000055511151231257827021181583404541015625"
+                .to_string()
+        )]);
+        assert_eq!(warnings("(u8) 0.5"), [(0..4, "warning: 0.5 truncated to integer 0".to_string())]);
+        assert_eq!(warnings("(u8) 300"), [(0..4, "warning: 300 wrapped to 44 in u8".to_string())]);
+        assert_eq!(warnings("1e400")[0].1, "warning: 1e400 is not exactly representable in binary; stored as inf");
+    }
+
+    #[test]
+    fn casts_to_odd_widths() {
+        assert_eq!(eval_str("(u7) 100"), "100");
+        assert_eq!(eval_str("(u7) 200"), "72");
+        assert_eq!(eval_str("(u7) 100 + (u7) 100"), "72");
+        assert_eq!(eval_str("(u8) 255 + 1"), "0");
+        // A full bit pattern reinterprets rather than wraps
+        assert_eq!(eval_str("(i13) 0x1FFF"), "-1");
+        assert_eq!(eval_str("(i13) 4095 + 1"), "-4096");
+        assert_eq!(eval_str("(u1) 3"), "1");
+        assert_eq!(eval_str("(u3) [7, 8, 9]"), "[7, 0, 1]");
+        assert_eq!(eval_str("~(u4) 5"), "10");
+    }
+
+    #[test]
+    fn results_wrap_at_a_modulus() {
+        let wrap = |modulus: &str, input: &str| eval_with(&[("wrap", modulus)], &[input]);
+        assert_eq!(wrap("360", "350 + 20"), Ok("10".to_string()));
+        assert_eq!(wrap("360", "10 - 20"), Ok("350".to_string()));
+        assert_eq!(wrap("7", "3 * 5"), Ok("1".to_string()));
+        assert_eq!(wrap("7", "2 ** 10"), Ok("2".to_string()));
+        assert_eq!(wrap("off", "350 + 20"), Ok("370".to_string()));
+        let mut env = Env::default();
+        assert!(env.settings.set("wrap", "0").is_err());
+        assert!(env.settings.set("wrap", "-5").is_err());
+    }
+
+    #[test]
+    fn tuples_hold_several_results() {
+        assert_eq!(eval_str("divmod(7, 2)"), "(3, 1)");
+        assert_eq!(eval_str("(1, 2 + 3)"), "(1, 5)");
+        assert_eq!(eval_str("divmod(7, 2)[1]"), "1");
+        assert_eq!(eval_with(&[], &["q, r = divmod(17, 5)", "q * 10 + r"]), Ok("32".to_string()));
+        assert_eq!(eval_with(&[], &["a, b = (1, 2)", "a, b = (b, a)", "a"]), Ok("2".to_string()));
+        assert_eq!(eval_with(&[], &["a, b = (1, 2, 3)"]).unwrap_err(), "Expected 2 values to unpack but found 3");
+        assert_eq!(eval_with(&[], &["a, a = (1, 2)"]).unwrap_err(), "Invalid assignment target");
+        assert!(eval("(1, 2) + 1", &mut Env::default()).is_err());
+    }
+
+    #[test]
+    fn let_bindings_are_scoped_to_their_block() {
+        assert_eq!(eval_str("{ let x = 2; x * 3 }"), "6");
+        assert_eq!(eval_str("{ let x = 1; { let x = 2; x } + x }"), "3");
+        assert_eq!(eval_str("{ let x = 1; { x = 5; x }; x }"), "5");
+        assert_eq!(eval_with(&[], &["x = 4", "{ let x = 1; x }", "x"]), Ok("4".to_string()));
+        assert_eq!(eval_with(&[], &["y = { let t = 2; t * 3 }", "t"]).unwrap_err(), "Unknown identifier");
+        assert_eq!(eval_with(&[], &["{ let x = 1; { let x = 2 }; x }"]).unwrap_err(), "This block has no value");
+    }
+
+    #[test]
+    fn if_else_picks_one_branch() {
+        assert_eq!(eval_str("if 1 > 2 { 5 } else { 6 }"), "6");
+        assert_eq!(eval_str("if 1 { 2 } else if 0 { 3 } else { 4 }"), "2");
+        assert_eq!(eval_str("if 0 { 2 } else if 0.5 { 3 } else { 4 }"), "3");
+        // The other branch is never evaluated
+        assert_eq!(eval_str("if 1 { 1 } else { 1 / 0 }"), "1");
+        assert_eq!(eval_str("2 * if 0 { 1 } else { 5 }"), "10");
+        let error = |input: &str| eval_with(&[], &[input]).unwrap_err();
+        assert_eq!(error("if 0 { 1 }"), "An if expression needs an else branch");
+        assert_eq!(error("if [1] { 1 } else { 2 }"), "A condition must be a real number");
+    }
+
+    #[test]
+    fn clock_builtins() {
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i128;
+        let value = |input: &str| eval(input, &mut Env::default()).unwrap().unwrap().as_int(&mut Vec::new()).unwrap();
+        assert!((value("now()") - now).abs() < 5);
+        assert!((value("now_ns() / 1000000000") - now).abs() < 5);
+        assert_eq!(value("today()") % 86400, 0);
+        assert!(now - value("today()") < 86400);
+        assert!(eval("now(1)", &mut Env::default()).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value as Json};
+
+use raxrs::diagnostic::{Diagnostic, Span};
+use raxrs::settings::Settings;
+use raxrs::{builtins, display_value, evaluate_line, logical_lines, Env, LogicalLine};
+
+const KEYWORDS: &[&str] = &["let", "if", "else", "for", "in", "while"];
+
+// An open .rax file and what was learned by running it top to bottom
+struct Document {
+    text: String,
+    lines: Vec<LogicalLine>,
+    // Displayed value of each logical line that produced one
+    values: Vec<Option<String>>,
+    // Variables and functions once the whole file has run
+    env: Env,
+}
+
+impl Document {
+    // Runs every statement in a fresh environment, collecting LSP diagnostics.
+    // `:` commands are skipped since they may print or touch other files.
+    fn analyze(text: String, settings: &Settings) -> (Document, Vec<Json>) {
+        let mut env = Env { settings: settings.clone(), ..Env::default() };
+        let lines = logical_lines(&text);
+        let mut values = Vec::new();
+        let mut diagnostics = Vec::new();
+        let raw: Vec<&str> = text.lines().collect();
+        for line in &lines {
+            if line.text.starts_with(':') {
+                values.push(None);
+                continue;
+            }
+            let (_, warnings, result) = evaluate_line(&line.text, &mut env);
+            let to_json = |diagnostic: &Diagnostic, severity: u8| {
+                json!({
+                    "range": range(&raw, line, &diagnostic.span),
+                    "severity": severity,
+                    "source": "rax",
+                    "message": diagnostic.message.trim_start_matches("warning: "),
+                })
+            };
+            diagnostics.extend(warnings.iter().map(|warning| to_json(warning, 2)));
+            match result {
+                Ok(value) => values.push(value.map(|value| display_value(&value, &env.settings))),
+                Err(errors) => {
+                    diagnostics.extend(errors.iter().map(|error| to_json(error, 1)));
+                    values.push(None);
+                }
+            }
+        }
+        (Document { text, lines, values, env }, diagnostics)
+    }
+
+    fn hover(&self, line: usize, character: usize) -> Option<String> {
+        let raw = self.text.lines().nth(line)?;
+        let column = byte_column(raw, character);
+        let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        let start = raw[..column].rfind(|c| !is_word(c)).map_or(0, |i| i + 1);
+        let end = raw[column..].find(|c| !is_word(c)).map_or(raw.len(), |i| column + i);
+        let word = &raw[start..end];
+
+        if let Some(value) = self.env.vars.get(word) {
+            return Some(format!("{} = {}", word, display_value(value, &self.env.settings)));
+        }
+        if let Some(function) = self.env.functions.get(word) {
+            return Some(format!("{}({})", word, function.params.join(", ")));
+        }
+        if builtins::NAMES.contains(&word) {
+            return Some(format!("{}(...) (builtin)", word));
+        }
+        let index = self.lines.iter().position(|logical| logical.offset_of(line, column).is_some())?;
+        self.values[index].as_ref().map(|value| format!("= {}", value))
+    }
+
+    fn completions(&self) -> Vec<Json> {
+        let item = |label: &str, kind: u8| json!({ "label": label, "kind": kind });
+        let mut items: Vec<Json> = builtins::NAMES.iter().map(|name| item(name, 3)).collect();
+        items.extend(self.env.functions.keys().map(|name| item(name, 3)));
+        items.extend(self.env.vars.keys().map(|name| item(name, 6)));
+        items.extend(KEYWORDS.iter().map(|keyword| item(keyword, 14)));
+        items
+    }
+}
+
+// LSP ranges count UTF-16 code units within a line
+fn utf16_column(raw: &str, byte_column: usize) -> usize {
+    raw.get(..byte_column).unwrap_or(raw).encode_utf16().count()
+}
+
+fn byte_column(raw: &str, utf16_column: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in raw.char_indices() {
+        if units >= utf16_column {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    raw.len()
+}
+
+fn range(raw: &[&str], line: &LogicalLine, span: &Span) -> Json {
+    let position = |offset: usize| {
+        let (line, column) = line.locate(offset);
+        json!({ "line": line, "character": utf16_column(raw.get(line).unwrap_or(&""), column) })
+    };
+    json!({ "start": position(span.start), "end": position(span.end) })
+}
+
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let length = length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(io::Error::from)
+}
+
+fn send(output: &mut impl Write, message: Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn publish(output: &mut impl Write, uri: &str, diagnostics: Vec<Json>) -> io::Result<()> {
+    send(output, json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    }))
+}
+
+// Serves diagnostics, hover and completion until the client sends `exit`
+pub fn run(settings: &Settings) -> io::Result<()> {
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    let mut documents: HashMap<String, Document> = HashMap::new();
+
+    while let Some(message) = read_message(&mut input)? {
+        let id = message.get("id").cloned();
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        tracing::debug!(method, "lsp message");
+
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "completionProvider": {},
+                },
+                "serverInfo": { "name": "rax", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "shutdown" => Json::Null,
+            "exit" => return Ok(()),
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                let text = match method {
+                    "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+                    _ => params["contentChanges"].as_array().and_then(|changes| changes.last()?["text"].as_str()),
+                };
+                let (document, diagnostics) = Document::analyze(text.unwrap_or_default().to_string(), settings);
+                documents.insert(uri.clone(), document);
+                publish(&mut output, &uri, diagnostics)?;
+                continue;
+            }
+            "textDocument/didClose" => {
+                documents.remove(&uri);
+                publish(&mut output, &uri, Vec::new())?;
+                continue;
+            }
+            "textDocument/hover" => {
+                let position = &params["position"];
+                let line = position["line"].as_u64().unwrap_or_default() as usize;
+                let character = position["character"].as_u64().unwrap_or_default() as usize;
+                match documents.get(&uri).and_then(|document| document.hover(line, character)) {
+                    Some(text) => json!({ "contents": { "kind": "plaintext", "value": text } }),
+                    None => Json::Null,
+                }
+            }
+            "textDocument/completion" => {
+                json!(documents.get(&uri).map(Document::completions).unwrap_or_default())
+            }
+            _ => {
+                if let Some(id) = id {
+                    send(&mut output, json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": -32601, "message": format!("Unsupported method {}", method) },
+                    }))?;
+                }
+                continue;
+            }
+        };
+        if let Some(id) = id {
+            send(&mut output, json!({ "jsonrpc": "2.0", "id": id, "result": result }))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostics_cover_errors_and_warnings() {
+        let text = "x = 1\ny = x +* 2\n:load elsewhere.rax\nz = 0.1 + 1\n".to_string();
+        let (_, diagnostics) = Document::analyze(text, &Settings::default());
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0]["severity"], 1);
+        assert_eq!(diagnostics[0]["message"], "Invalid expression");
+        assert_eq!(diagnostics[0]["range"]["start"], json!({ "line": 1, "character": 6 }));
+        assert_eq!(diagnostics[1]["severity"], 2);
+        assert_eq!(diagnostics[1]["range"]["start"], json!({ "line": 3, "character": 4 }));
+        assert!(diagnostics[1]["message"].as_str().unwrap().starts_with("0.1 is not exactly representable"));
+        assert!(Document::analyze(String::new(), &Settings::default()).1.is_empty());
+    }
+
+    #[test]
+    fn hover_shows_values_functions_and_builtins() {
+        let text = "width = 0x10\ndouble(x) = x * 2\ndouble(width) + crc32(\"\")\n";
+        let (document, _) = Document::analyze(text.to_string(), &Settings::default());
+        assert_eq!(document.hover(0, 2), Some("width = 16".to_string()));
+        assert_eq!(document.hover(2, 9), Some("width = 16".to_string()));
+        assert_eq!(document.hover(2, 1), Some("double(x)".to_string()));
+        assert_eq!(document.hover(2, 18), Some("crc32(...) (builtin)".to_string()));
+        // Off any name, the line's own value
+        assert_eq!(document.hover(2, 14), Some("= 32".to_string()));
+        assert_eq!(document.hover(9, 0), None);
+    }
+
+    #[test]
+    fn completions_list_every_kind_of_name() {
+        let (document, _) = Document::analyze("mask = 0xff\nlow(x) = x & mask\n".to_string(), &Settings::default());
+        let completions = document.completions();
+        let kind = |label: &str| completions.iter().find(|item| item["label"] == label).map(|item| item["kind"].clone());
+        assert_eq!(kind("mask"), Some(json!(6)));
+        assert_eq!(kind("low"), Some(json!(3)));
+        assert_eq!(kind("popcount"), Some(json!(3)));
+        assert_eq!(kind("while"), Some(json!(14)));
+        assert_eq!(kind("x"), None);
+    }
+
+    #[test]
+    fn columns_count_utf16_units() {
+        let raw = "é😀x";
+        assert_eq!(utf16_column(raw, 0), 0);
+        assert_eq!(utf16_column(raw, 2), 1);
+        assert_eq!(utf16_column(raw, 6), 3);
+        assert_eq!(utf16_column(raw, 99), 4);
+        assert_eq!(byte_column(raw, 3), 6);
+        assert_eq!(byte_column(raw, 4), 7);
+        assert_eq!(byte_column(raw, 99), 7);
+    }
+
+    #[test]
+    fn messages_are_framed_by_content_length() {
+        let mut output = Vec::new();
+        send(&mut output, json!({ "id": 1 })).unwrap();
+        assert_eq!(String::from_utf8(output.clone()).unwrap(), "Content-Length: 8\r\n\r\n{\"id\":1}");
+        let mut input = &output[..];
+        assert_eq!(read_message(&mut input).unwrap(), Some(json!({ "id": 1 })));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+        assert!(read_message(&mut &b"Content-Type: json\r\n\r\n{}"[..]).is_err());
+        assert!(read_message(&mut &b"Content-Length: 5\r\n\r\n{}"[..]).is_err());
+    }
+}
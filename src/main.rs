@@ -1,305 +1,1531 @@
-use std::env;
-use std::io::Write;
-
-enum BaseConversionError {
-    ParseIntError,
-    InvalidInputFormat,
-}
-
-impl From<std::num::ParseIntError> for BaseConversionError {
-    fn from(_: std::num::ParseIntError) -> Self {
-        BaseConversionError::ParseIntError
-    }
-}
-
-#[derive(Debug, PartialEq)]    
-enum Token {
-    Number(String),
-    Plus,
-    Minus,
-    Star,
-    Slash,
-    LParen,
-    RParen
-}
-
-fn parse_num(input: &str) -> Result<String, BaseConversionError> {
-    if input.starts_with("0x") {
-        i64::from_str_radix(&input[2..], 16)
-            .map(|num| num.to_string())
-            .map_err(|_| BaseConversionError::ParseIntError)
-    } else if input.starts_with("b") {
-        i64::from_str_radix(&input[1..], 10)
-            .map(|num| format!("{:b}b", num))
-            .map_err(|_| BaseConversionError::ParseIntError)
-    } else if input.starts_with("Fx") {
-        u64::from_str_radix(&input[2..], 16)
-            .map(f64::from_bits)
-            .map(|float| float.to_string())
-            .map_err(|_| BaseConversionError::ParseIntError)
-    } else if input.starts_with("Bx") {
-        i64::from_str_radix(&input[2..], 16)
-            .map(|num| format!("{:b}", num))
-            .map_err(|_| BaseConversionError::ParseIntError)
-    } else if input.starts_with("Ox") {
-        i64::from_str_radix(&input[2..], 16)
-            .map(|num| format!("{:o}", num))
-            .map_err(|_| BaseConversionError::ParseIntError)
-    } else if input.ends_with("d") {
-        i64::from_str_radix(&input[..input.len() - 1], 2)
-            .map(|num| num.to_string())
-            .map_err(|_| BaseConversionError::ParseIntError)
-    } else if input.ends_with("f") {
-        input[..input.len() - 1].parse::<f64>()
-            .map(|num| format!("0x{:x}", num.to_bits()))
-            .map_err(|_| BaseConversionError::ParseIntError)
-    } else if input.ends_with("o") {
-        i64::from_str_radix(&input[..input.len() - 1], 8)
-            .map(|num| format!("0x{:x}", num))
-            .map_err(|_| BaseConversionError::ParseIntError)
-    } else if input.ends_with("b") {
-        i64::from_str_radix(&input[..input.len() - 1], 2)
-            .map(|num| format!("0x{:x}", num))
-            .map_err(|_| BaseConversionError::ParseIntError)
-    } else {
-        i64::from_str_radix(input, 10)
-            .map(|num| format!("0x{:x}", num))
-            .map_err(|_| BaseConversionError::ParseIntError)
-    }
-}
-
-// fn read_input() -> Vec<String> {
-//     print!("> ");
-//     std::io::stdout().flush().unwrap();
-//     let mut input = String::new();
-//     std::io::stdin()
-//         .read_line(&mut input)
-//         .expect("Cannot read input");
-//     let args = input
-//         .split_whitespace()
-//         .map(|s| s.to_string())
-//         .collect::<Vec<String>>();
-//     args
-// }
-
-fn infix_to_postfix(tokens: Vec<Token>) -> Vec<Token> {
-    // implements shunting yard algorithm to convert Vec<Token>
-    // to reverse polish notation
-
-    let mut output_queue: Vec<Token> = Vec::new();
-    let mut operator_stack: Vec<Token> = Vec::new();
-
-    for token in tokens {
-        match token {
-            Token::Number(_) => output_queue.push(token),
-            Token::Plus | Token::Minus | Token::Star | Token::Slash => {
-                while let Some(op) = operator_stack.last() {
-                    if op != &Token::LParen {
-                        output_queue.push(operator_stack.pop().unwrap());
-                    } else {
-                        break;
-                    }
+use std::borrow::Cow;
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use tracing::debug;
+
+use raxrs::fixed::IntType;
+use raxrs::highlight::Stream;
+use raxrs::settings::Settings;
+use raxrs::table::Table;
+use raxrs::{
+    builtins, cancel, checksum, codec, color, diagnostic, diff, format, hash, highlight, ieee, settings, timestamp, unicode,
+};
+use raxrs::{
+    base_title, check_force_output, convert_float, convert_wide, display_value, evaluate_line, format_base,
+    is_float_base, is_identifier, load_library, logical_lines, sized_type, to_int, Env, Value, MAX_RANGE,
+};
+
+mod clipboard;
+mod extract;
+mod lsp;
+mod record;
+mod report;
+
+use record::{Field, Record};
+
+#[derive(Parser)]
+#[command(version, about = "Programmer's calculator and base converter")]
+#[command(args_conflicts_with_subcommands = true, allow_negative_numbers = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Log parse and evaluation steps to stderr (-vv for more detail)
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Print conversions as a GitHub-flavored Markdown table
+    #[arg(long, global = true)]
+    markdown: bool,
+
+    /// Print conversions in a machine-readable format
+    #[arg(long, value_enum, global = true, conflicts_with = "markdown")]
+    output: Option<record::Format>,
+
+    /// Print the JSON Schema of --output conversions and exit
+    #[arg(long)]
+    schema: bool,
+
+    /// Print the steps of each base conversion before its result
+    #[arg(long, global = true)]
+    show_work: bool,
+
+    /// Print the sign, exponent and mantissa fields of each float input
+    /// instead of converting it
+    #[arg(long, global = true, conflicts_with_all = ["markdown", "output"])]
+    explain_float: bool,
+
+    /// Print each value's bytes in big- and little-endian order with their
+    /// indices, at the --word width if one is set
+    #[arg(long, global = true, conflicts_with_all = ["markdown", "output", "explain_float"])]
+    endian: bool,
+
+    /// Read lines from stdin and convert only this column (counting from 1),
+    /// echoing the rest of each line unchanged
+    #[arg(long, value_name = "N", global = true)]
+    field: Option<NonZeroUsize>,
+
+    /// Column separator for --field, instead of runs of whitespace
+    #[arg(long, value_name = "CHAR", global = true, requires = "field")]
+    delim: Option<char>,
+
+    /// Convert the whitespace-separated values on the clipboard
+    #[arg(long)]
+    paste: bool,
+
+    /// Case of hex digits in output
+    #[arg(long, value_enum, default_value_t, global = true)]
+    hex_case: format::HexCase,
+
+    /// Prefix of hex output: 0x for C, $ for assemblers, 'h for Verilog, or none
+    #[arg(long, value_enum, default_value_t, global = true)]
+    hex_prefix: format::HexPrefix,
+
+    /// Left-pad hex, octal and binary output with zeros to N digits
+    #[arg(long, value_name = "N", default_value_t = 0, global = true)]
+    pad: usize,
+
+    /// Group hex and binary output digits in fours with _, and decimal digits
+    /// in threes with _ or the given separator, e.g. --group=,
+    #[arg(long, value_name = "SEP", global = true)]
+    #[arg(num_args = 0..=1, require_equals = true, default_missing_value = "_")]
+    group: Option<char>,
+
+    /// Wrap long binary and bitset output at this many columns (0 to never
+    /// wrap); defaults to the terminal width when printing to a terminal
+    #[arg(long, value_name = "N", global = true)]
+    wrap: Option<usize>,
+
+    /// Evaluate integers as a fixed word size such as 8, 16, 32 or 64 bits,
+    /// wrapping like a register; prefix with i for signed words, e.g. i16
+    #[arg(long, value_name = "SIZE", global = true, value_parser = settings::parse_word)]
+    word: Option<IntType>,
+
+    /// Treat integers as unsigned: u64 by default, or the unsigned form of
+    /// --word
+    #[arg(long, global = true)]
+    unsigned: bool,
+
+    /// Read literals with the original grammar, where b101 is decimal 101,
+    /// 101d is binary and Bx and Ox take hex digits
+    #[arg(long, global = true)]
+    legacy_literals: bool,
+
+    /// Highlight echoed expressions with ANSI colors
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto, global = true)]
+    color: ColorChoice,
+
+    /// Values to convert, optionally preceded by an output base such as =16,
+    /// or =t8 for the two's complement pattern at 8 bits (any width to 128,
+    /// with =o8 and =s8 for one's complement and sign-magnitude); without
+    /// one, values are shown in decimal, hex, octal, binary and as text.
+    /// - reads values from stdin and lo..hi expands to a range
+    values: Vec<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Checksum hex-encoded bytes or the contents of a file
+    Checksum {
+        #[arg(value_enum)]
+        algorithm: ChecksumAlgorithm,
+        /// Bytes as hex, e.g. DEADBEEF
+        #[arg(required_unless_present = "file")]
+        hex: Option<String>,
+        /// Read the bytes from a file instead
+        #[arg(short, long, conflicts_with = "hex")]
+        file: Option<PathBuf>,
+    },
+    /// Print the digest of text or hex bytes
+    Hash {
+        #[arg(value_enum)]
+        algorithm: HashAlgorithm,
+        /// Text to hash; - reads stdin
+        data: String,
+        /// Read the data as hex bytes, e.g. DEADBEEF
+        #[arg(short = 'x', long)]
+        hex: bool,
+    },
+    /// Encode text or hex bytes as base64, or decode base64 with -d
+    B64 {
+        #[command(flatten)]
+        args: CodecArgs,
+        /// Use the URL-safe alphabet (- and _) without padding
+        #[arg(short, long)]
+        url: bool,
+    },
+    /// Encode text or hex bytes as RFC 4648 base32, or decode it with -d
+    B32 {
+        #[command(flatten)]
+        args: CodecArgs,
+    },
+    /// Encode text or hex bytes as base58 (Bitcoin alphabet), or decode it
+    /// with -d
+    B58 {
+        #[command(flatten)]
+        args: CodecArgs,
+    },
+    /// Print the UTF-8 bytes of code points, or decode UTF-8 bytes with -d
+    Utf8 {
+        /// Code points such as U+1F600 or U+0041 (four to six hex digits), 0x41 or 'A'; with -d, hex bytes
+        #[arg(required = true)]
+        values: Vec<String>,
+        /// Decode hex bytes into code points
+        #[arg(short, long)]
+        decode: bool,
+    },
+    /// Print the version, variant and any timestamp of UUIDs, or format
+    /// 128-bit values as UUIDs
+    #[cfg(feature = "uuid")]
+    Uuid {
+        /// UUIDs such as 550e8400-e29b-41d4-a716-446655440000, or 128-bit
+        /// values such as 0x550e8400e29b41d4a716446655440000
+        #[arg(required = true)]
+        values: Vec<String>,
+    },
+    /// Print two values in binary, one above the other, marking the bits
+    /// that differ
+    Diff {
+        a: String,
+        b: String,
+    },
+    /// Print colors as hex, rgb(), hsl() and the packed 24-bit integer
+    Color {
+        /// Colors such as '#1e90ff', '#fff', 'rgb(30, 144, 255)',
+        /// 'hsl(210, 100%, 56%)' or integers such as 0x1e90ff
+        #[arg(required = true)]
+        values: Vec<String>,
+    },
+    /// Print chmod modes such as 4755 as symbolic permissions, or symbolic
+    /// permissions such as rwxr-xr-x as octal modes
+    Perm {
+        /// Octal modes, modes in another base such as 0x1ed, or symbolic
+        /// permissions with or without a file type as in drwxr-xr-x
+        #[arg(required = true)]
+        values: Vec<String>,
+    },
+    /// Print the netmask, address range and host count of IPv4 blocks
+    #[cfg(feature = "ip")]
+    Net {
+        /// Blocks in CIDR notation such as 10.0.0.0/22
+        #[arg(required = true)]
+        blocks: Vec<String>,
+    },
+    /// Print Unix timestamps as UTC and local ISO 8601 times, or the
+    /// timestamp of an ISO 8601 time such as 2024-01-01T00:00:00Z
+    Time {
+        /// Timestamps in any base, or ISO 8601 times
+        #[arg(required = true)]
+        values: Vec<String>,
+        /// Unit of the timestamps (s, ms, us or ns) instead of guessing it
+        /// from their magnitude; ISO 8601 times are converted to seconds
+        /// unless this is given
+        #[arg(short, long, value_name = "UNIT", value_parser = timestamp::Precision::parse)]
+        precision: Option<timestamp::Precision>,
+    },
+    /// Run a script file, printing the value of each line
+    Run {
+        file: PathBuf,
+    },
+    /// Evaluate expressions independently, printing one result per line
+    Eval {
+        /// Expressions to evaluate
+        #[arg(required_unless_present = "file")]
+        exprs: Vec<String>,
+        /// Read one expression per line from a file (- for stdin)
+        #[arg(short, long, conflicts_with = "exprs")]
+        file: Option<PathBuf>,
+        /// Report failing lines and continue, printing an empty line in
+        /// their place
+        #[arg(short, long)]
+        keep_going: bool,
+    },
+    /// Run a language server for .rax script files over stdio
+    Lsp,
+    /// Write an HTML page describing each value: bases, bits, bytes and
+    /// IEEE 754 fields for floats
+    #[command(allow_negative_numbers = true)]
+    Report {
+        /// Values to describe; - reads from stdin and lo..hi expands to a range
+        #[arg(required = true)]
+        values: Vec<String>,
+        /// Write the page to a file instead of stdout
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Print numbers found at a path such as .latency_ns or .spans[].size in
+    /// JSON or JSON lines
+    Json {
+        /// Fields separated by dots, with [N] for an array element and [] for
+        /// every element
+        path: String,
+        /// File to read (stdin when omitted or -), optionally followed by an
+        /// output base such as =16
+        args: Vec<String>,
+        /// Print one combined value instead of every number
+        #[arg(short, long, value_enum)]
+        aggregate: Option<extract::Aggregate>,
+    },
+    /// Convert numbers matching a pattern in text, printing the rest of the
+    /// text unchanged
+    Grep {
+        /// Regular expression for the numbers; when it has a capture group,
+        /// only the first group is converted
+        #[arg(short = 'e', long = "regexp", default_value = "0x[0-9a-fA-F]+")]
+        pattern: String,
+        /// Base or format to convert to: dec, hex, oct, bin or a format name
+        #[arg(long, default_value = "dec")]
+        to: String,
+        /// Print only the converted matches, one per line
+        #[arg(short, long)]
+        list: bool,
+        /// Files to read (stdin when omitted)
+        files: Vec<PathBuf>,
+    },
+    /// Convert values, the same as passing them without a subcommand
+    #[command(allow_negative_numbers = true)]
+    Convert {
+        /// Values to convert, optionally preceded by an output base such as
+        /// =16; - reads whitespace-separated values from stdin and lo..hi
+        /// expands to a range
+        #[arg(required = true)]
+        values: Vec<String>,
+    },
+}
+
+// Options shared by the byte encoding subcommands
+#[derive(Args)]
+struct CodecArgs {
+    /// Text to encode, or the encoded string to decode; - reads stdin
+    data: String,
+    /// Decode instead of encoding
+    #[arg(short, long)]
+    decode: bool,
+    /// Hex bytes: the input when encoding, the output when decoding
+    #[arg(short = 'x', long)]
+    hex: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorChoice {
+    // For each of stdout and stderr that is a terminal, when NO_COLOR is unset
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ChecksumAlgorithm {
+    Adler32,
+    Fletcher16,
+    Fletcher32,
+}
+
+fn read_line(settings: &Settings) -> Option<String> {
+    print!("> ");
+    let mut input = String::new();
+    std::io::stdout().flush().unwrap();
+    let bytes = std::io::stdin()
+        .read_line(&mut input)
+        .expect("Cannot read input expression");
+    if bytes == 0 {
+        return None;
+    }
+    let input = input.trim_end().to_string();
+    recolor_input(&input, settings);
+    Some(input)
+}
+
+// Redraws an expression just typed at the terminal with highlighting, when
+// it fits on one row so that moving up a row reaches all of it
+fn recolor_input(input: &str, settings: &Settings) {
+    if !highlight::enabled(Stream::Stdout) || !std::io::stdin().is_terminal() || input.starts_with(':') {
+        return;
+    }
+    let fits = terminal_size::terminal_size().is_some_and(|(width, _)| input.chars().count() + 2 < width.0 as usize);
+    if fits {
+        println!("\x1b[1A\x1b[2K> {}", highlight::highlight(input, settings));
+    }
+}
+
+// Runs a REPL line on a worker thread while Ctrl-C is routed to the
+// cancellation flag, so an interrupt stops only this evaluation
+fn run_line_cancellable(input: &str, env: &mut Env) {
+    cancel::reset();
+    std::thread::scope(|scope| {
+        let worker = std::thread::Builder::new()
+            .name("eval".into())
+            .spawn_scoped(scope, || run_line(input, env))
+            .expect("failed to spawn evaluation thread");
+        // A panic has already been reported by the panic hook
+        let _ = worker.join();
+    });
+}
+
+// A line may start with an output base such as =16, which shows its
+// integer result as the conversion modes would, padded and grouped by the
+// session's settings
+fn run_line(input: &str, env: &mut Env) {
+    let (base, input) = match input.split_once(char::is_whitespace) {
+        Some((selector, rest)) if selector.starts_with('=') => {
+            match check_force_output(&[selector.to_string()], &env.settings) {
+                Some(base) => (Some(base), rest),
+                None => {
+                    println!("Error: Unknown output base {}", selector);
+                    return;
                 }
-                operator_stack.push(token);
-            }
-            Token::LParen => operator_stack.push(token),
-            Token::RParen => {
-                while let Some(op) = operator_stack.pop() {
-                    if op == Token::LParen {
-                        break;
-                    } else {
-                        output_queue.push(op);
+            }
+        }
+        _ => (None, input),
+    };
+    let (expanded, warnings, result) = evaluate_line(input, env);
+    if !warnings.is_empty() {
+        println!("{}", diagnostic::render_all(&expanded, &warnings, &env.settings));
+    }
+    match (result, base) {
+        (Ok(Some(value)), None) => println!("{}", display_value(&value, &env.settings)),
+        // Fractions show as they are in float outputs such as =e
+        (Ok(Some(value @ Value::Float(_))), Some(base)) if is_float_base(base) => {
+            println!("{}", format_base(base, 0, &value.to_string(), &env.settings))
+        }
+        (Ok(Some(value)), Some(base)) => {
+            let mut lossy = Vec::new();
+            match value.as_int(&mut lossy) {
+                Ok(num) => {
+                    for warning in lossy {
+                        eprintln!("warning: {}", warning);
                     }
+                    let width = match value {
+                        Value::Fixed(_, ty) => Some(ty),
+                        _ => None,
+                    };
+                    let settings = at_width(&env.settings, width);
+                    println!("{}", format_base(base, num, &value.to_string(), &settings));
                 }
+                Err(message) => println!("Error: {}", message),
             }
         }
+        (Ok(None), _) => {}
+        (Err(diagnostics), _) => println!("{}", diagnostic::render_all(&expanded, &diagnostics, &env.settings)),
     }
+}
 
-    while let Some(op) = operator_stack.pop() {
-        output_queue.push(op);
+// Runs each line of a script as if typed at the prompt
+fn run_script(path: &Path, settings: &Settings) {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("Error: Cannot read {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let mut env = session(settings);
+    for line in logical_lines(&contents) {
+        if let Some(command) = line.text.strip_prefix(':') {
+            run_command(command, &mut env);
+        } else {
+            run_line(&line.text, &mut env);
+        }
     }
+}
 
-    output_queue
+// Evaluates each expression in a fresh environment. Results go to stdout
+// and diagnostics to stderr; exits with status 1 if any expression failed.
+fn run_eval(exprs: Vec<String>, file: Option<PathBuf>, keep_going: bool, settings: &Settings) {
+    let (source, exprs) = match file {
+        Some(path) => {
+            let contents = if path.as_os_str() == "-" {
+                std::io::read_to_string(std::io::stdin())
+            } else {
+                fs::read_to_string(&path)
+            };
+            match contents {
+                Ok(contents) => (path.display().to_string(), contents.lines().map(String::from).collect()),
+                Err(e) => {
+                    eprintln!("Error: Cannot read {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => ("argument".to_string(), exprs),
+    };
+    let base = session(settings);
+    let mut failed = false;
+    for (i, line) in exprs.iter().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (expanded, warnings, result) = evaluate_line(line, &mut base.clone());
+        let location = format!("{}:{}", source, i + 1);
+        if !warnings.is_empty() {
+            let rendered = diagnostic::render_all_for(Stream::Stderr, &expanded, &warnings, &base.settings);
+            eprintln!("{}\n{}", location, rendered);
+        }
+        match result {
+            Ok(Some(value)) => println!("{}", display_value(&value, &base.settings)),
+            Ok(None) => println!(),
+            Err(diagnostics) => {
+                let rendered = diagnostic::render_all_for(Stream::Stderr, &expanded, &diagnostics, &base.settings);
+                eprintln!("{}\n{}", location, rendered);
+                if !keep_going {
+                    std::process::exit(1);
+                }
+                failed = true;
+                println!();
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
 }
 
+fn rc_path() -> Option<PathBuf> {
+    match std::env::var_os("RAX_RC") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => std::env::var_os("HOME").map(|home| Path::new(&home).join(".raxrc")),
+    }
+}
 
-fn parse_expr() -> Vec<Token> {
-    print!("> ");
-    let mut input = String::new();
-    std::io::stdout().flush().unwrap();
-    std::io::stdin()
-        .read_line(&mut input)
-        .expect("Cannot parse input expression");
-    input = input.trim_end().to_string();
-
-    // Tokenize
-    let mut tokens = Vec::<Token>::new();
-    let mut curr = Vec::<char>::new();
-    let mut chars = input.as_str().chars().peekable();
-    
-    while let Some(&c) = chars.peek() {
-        // println!("tokens: {:?}", tokens);
-        // println!("curr: {:?}", curr);
-        match c {
-            ' ' => { chars.next(); }, // Skip spaces
-            '+' | '-' | '/' | '*' | '(' | ')' => {
-                if !curr.is_empty() {
-                    let string: String = curr.iter().collect();
-                    match parse_num(&string) {
-                        Ok(num) => { tokens.push(Token::Number(num)); curr.clear(); },
-                        Err(e) => println!("Could not convert number {}", string),
-                    }
-                }
+// Each non-empty line of the rc file is a REPL command; the leading `:`
+// is optional there
+fn rc_commands() -> Vec<String> {
+    let Some(path) = rc_path() else { return Vec::new() };
+    let Ok(contents) = fs::read_to_string(&path) else {
+        debug!(path = %path.display(), "no rc file");
+        return Vec::new();
+    };
+    debug!(path = %path.display(), "loading rc file");
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.strip_prefix(':').unwrap_or(line).to_string())
+        .collect()
+}
 
-                tokens.push(match c {
-                    '+' => Token::Plus,
-                    '-' => Token::Minus,
-                    '/' => Token::Slash,
-                    '*' => Token::Star,
-                    '(' => Token::LParen,
-                    ')' => Token::RParen,
-                    _ => unreachable!(), // We've checked all cases
-                });
-                chars.next();
-            },
-            _ => { 
-                curr.push(c);
-                chars.next();
+fn is_format_command(command: &str) -> bool {
+    matches!(command.split_whitespace().next(), Some("alphabet" | "plugin"))
+}
+
+// Alphabets and plugins are formats that every mode can use, so they are
+// defined once at startup rather than with the rest of the rc file
+fn define_rc_formats(formats: &mut format::Registry) {
+    for command in rc_commands().iter().filter(|command| is_format_command(command)) {
+        let mut parts = command.split_whitespace();
+        let result = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some("alphabet"), Some(name), Some(digits), None) => formats.define_alphabet(name, digits),
+            (Some("alphabet"), ..) => Err("Usage: :alphabet <name> <digits>".to_string()),
+            (_, Some(path), None, None) => formats.load_plugin(Path::new(path)).map(|_| ()),
+            _ => Err("Usage: :plugin <path>".to_string()),
+        };
+        if let Err(e) = result {
+            eprintln!("warning: rc file: {}", e);
+        }
+    }
+}
+
+fn load_rc(env: &mut Env) {
+    for command in rc_commands().iter().filter(|command| !is_format_command(command)) {
+        run_command(command, env);
+    }
+}
+
+// A REPL or script environment: the command-line settings, then the rc
+// file, with --word still taking precedence over the rc file's word size
+fn session(settings: &Settings) -> Env {
+    let mut env = Env { settings: settings.clone(), ..Env::default() };
+    load_rc(&mut env);
+    if settings.word.is_some() {
+        env.settings.word = settings.word;
+    }
+    env
+}
+
+fn run_command(command: &str, env: &mut Env) {
+    let (name, rest) = command
+        .trim()
+        .split_once(char::is_whitespace)
+        .unwrap_or((command.trim(), ""));
+    let mut parts = rest.split_whitespace();
+    match Some(name).filter(|name| !name.is_empty()) {
+        Some("alias") => match rest.split_once('=') {
+            Some((alias, text)) if is_identifier(alias.trim()) => {
+                env.aliases.insert(alias.trim().to_string(), text.trim().to_string());
+            }
+            None if env.aliases.contains_key(rest.trim()) => {
+                println!("{} = {}", rest.trim(), env.aliases[rest.trim()]);
+            }
+            _ => println!("Usage: :alias <name> = <text>"),
+        },
+        Some("unalias") => {
+            if env.aliases.remove(rest.trim()).is_none() {
+                println!("No alias named {}", rest.trim());
+            }
+        }
+        Some("aliases") => {
+            for (alias, text) in &env.aliases {
+                println!("{} = {}", alias, text);
             }
         }
+        Some("table") => match (parts.next(), parts.next()) {
+            (Some(name), Some(path)) if is_identifier(name) => match Table::load(Path::new(path), &env.settings) {
+                Ok(table) => {
+                    println!("Loaded {} entries into {}", table.len(), name);
+                    env.tables.insert(name.to_string(), table);
+                }
+                Err(e) => println!("Error: {}", e),
+            },
+            _ => println!("Usage: :table <name> <path>"),
+        },
+        Some("formats") => println!("{}", env.settings.formats.names().join(" ")),
+        Some("alphabet") => match (parts.next(), parts.next(), parts.next()) {
+            (Some(name), Some(digits), None) => {
+                if let Err(e) = env.settings.formats.define_alphabet(name, digits) {
+                    println!("Error: {}", e);
+                }
+            }
+            _ => println!("Usage: :alphabet <name> <digits>"),
+        },
+        Some("plugin") if !rest.trim().is_empty() => match env.settings.formats.load_plugin(Path::new(rest.trim())) {
+            Ok(name) => println!("Loaded format {}", name),
+            Err(e) => println!("Error: {}", e),
+        },
+        Some("plugin") => println!("Usage: :plugin <path>"),
+        Some("set") => match (parts.next(), parts.next()) {
+            (Some(setting), Some(value)) => {
+                if let Err(e) = env.settings.set(setting, value) {
+                    println!("Error: {}", e);
+                }
+            }
+            (None, _) => {
+                for line in env.settings.describe() {
+                    println!("{}", line);
+                }
+            }
+            _ => println!("Usage: :set <name> <value>"),
+        },
+        Some("load") if !rest.trim().is_empty() => match load_library(Path::new(rest.trim()), env) {
+            Ok((count, warnings)) => {
+                for warning in warnings {
+                    println!("{}", warning);
+                }
+                println!("Loaded {} definitions from {}", count, rest.trim());
+            }
+            Err(e) => println!("Error: {}", e),
+        },
+        Some("load") => println!("Usage: :load <path>"),
+        Some("paste") => match clipboard::paste() {
+            Ok(text) => {
+                // Echoed as if typed, so the output reads as a transcript
+                for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+                    println!("> {}", highlight::highlight(line, &env.settings));
+                    run_line_cancellable(line, env);
+                }
+            }
+            Err(e) => println!("Error: {}", e),
+        },
+        Some(other) => println!("Unknown command :{}", other),
+        None => println!("Expected a command after :"),
     }
-    if !curr.is_empty() {
-        let string: String = curr.into_iter().collect();
-        match parse_num(&string) {
-            Ok(num) => tokens.push(Token::Number(num)),
-            Err(e) => println!("Could not parse number {}", string),
+}
+
+// Converts one input as the library does, printing its warnings to stderr
+fn convert_input(input: &str, settings: &Settings) -> Result<(i128, String), &'static str> {
+    let mut warnings = Vec::new();
+    let result = raxrs::convert_input(input, settings, &mut warnings);
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
+    }
+    result
+}
+
+// Converts one input for the conversion modes, wrapping it to the --word
+// width like a register, as expressions do
+fn convert_at_word(input: &str, settings: &Settings) -> Result<(i128, String), &'static str> {
+    let (num, result) = convert_input(input, settings)?;
+    let Some(ty) = settings.word.filter(|ty| ty.wrap(num) != num) else { return Ok((num, result)) };
+    let wrapped = ty.wrap(num);
+    // Reinterpreting a bit pattern, as in --word i8 0xff, loses nothing
+    if num as u128 & !ty.mask() != 0 {
+        eprintln!("warning: {} wrapped to {} in {}", input, ty.display(wrapped), ty);
+    }
+    Ok((wrapped, ty.display(wrapped)))
+}
+
+// Settings showing hex, octal and binary at `width`, the declared width of a
+// sized literal such as 8'shFF, unless a word size is already set
+fn at_width(settings: &Settings, width: Option<IntType>) -> Cow<'_, Settings> {
+    match width {
+        Some(ty) if settings.word.is_none() => Cow::Owned(Settings { word: Some(ty), ..settings.clone() }),
+        _ => Cow::Borrowed(settings),
+    }
+}
+
+// The bytes of a text argument, or of stdin for -
+fn read_data(data: &str) -> Option<Vec<u8>> {
+    if data != "-" {
+        return Some(data.as_bytes().to_vec());
+    }
+    let mut bytes = Vec::new();
+    match std::io::Read::read_to_end(&mut std::io::stdin(), &mut bytes) {
+        Ok(_) => Some(bytes),
+        Err(e) => {
+            println!("Error: Cannot read stdin: {}", e);
+            None
         }
     }
-    // println!("{:?}", tokens);
-    tokens
 }
 
-fn eval_expr(tokens: Vec<Token>) -> Result<i64, &'static str>{
-    let mut iter = tokens.iter();
-    let mut stack: Vec<i64> = Vec::new();
+fn run_hash(algorithm: HashAlgorithm, data: &str, hex: bool) {
+    let Some(mut bytes) = read_data(data) else {
+        return;
+    };
+    if hex {
+        bytes = match checksum::parse_hex_bytes(&String::from_utf8_lossy(&bytes)) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                println!("Error: Invalid hex byte string {}", String::from_utf8_lossy(&bytes).trim());
+                return;
+            }
+        };
+    }
+    let digest = match algorithm {
+        HashAlgorithm::Md5 => hash::md5(&bytes),
+        HashAlgorithm::Sha1 => hash::sha1(&bytes),
+        HashAlgorithm::Sha256 => hash::sha256(&bytes),
+    };
+    println!("{}", codec::hex(&digest));
+}
 
-    while let Some(token) = iter.next() {
-            // println!("stack: {:?}", stack);
-        match token {
-            Token::Number(num) => {
-                // keep converting it until it's an int
-                let mut temp_num = num.clone();
-                while !temp_num.chars().all(|c| (c.is_numeric() || c == '.' || c == '-')) {
-                    let parse_result = parse_num(&temp_num);
-                    match parse_result {
-                        Ok(result_num) => {
-                            temp_num = result_num;
-                        },
-                        Err(e) => println!("Could not parse number")
+fn run_codec(
+    args: &CodecArgs,
+    encode: impl Fn(&[u8]) -> String,
+    decode: impl Fn(&str) -> Result<Vec<u8>, &'static str>,
+) {
+    let Some(data) = read_data(&args.data) else {
+        return;
+    };
+    if args.decode {
+        let bytes = match decode(&String::from_utf8_lossy(&data)) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                println!("Error: {}", e);
+                return;
+            }
+        };
+        // Text when it reads as text, otherwise hex
+        match String::from_utf8(bytes) {
+            Ok(text) if !args.hex && !text.chars().any(|c| c.is_control() && !c.is_whitespace()) => println!("{}", text),
+            Ok(text) => println!("{}", codec::hex(text.as_bytes())),
+            Err(e) => println!("{}", codec::hex(e.as_bytes())),
+        }
+    } else if args.hex {
+        match checksum::parse_hex_bytes(&String::from_utf8_lossy(&data)) {
+            Ok(bytes) => println!("{}", encode(&bytes)),
+            Err(_) => println!("Error: Invalid hex byte string {}", String::from_utf8_lossy(&data).trim()),
+        }
+    } else {
+        println!("{}", encode(&data));
+    }
+}
+
+fn run_utf8(values: &[String], decode: bool, settings: &Settings) {
+    if decode {
+        let hex = values.join(" ");
+        let chars = checksum::parse_hex_bytes(&hex)
+            .map_err(|_| format!("Invalid hex byte string {}", hex))
+            .and_then(|bytes| unicode::utf8_decode(&bytes));
+        match chars {
+            Ok(chars) => {
+                for c in chars {
+                    match c.is_control() {
+                        true => println!("U+{:04X}", c as u32),
+                        false => println!("U+{:04X} {}", c as u32, c),
                     }
                 }
-                stack.push(i64::from_str_radix(temp_num.as_str(), 10).unwrap());
-            },
+            }
+            Err(e) => println!("Error: {}", e),
+        }
+        return;
+    }
+    for value in values {
+        let c = convert_input(value, settings).ok();
+        let c = c.and_then(|(num, _)| u32::try_from(num).ok()).and_then(char::from_u32);
+        match c {
+            Some(c) => println!("{}", unicode::utf8_hex(c)),
+            None => println!("Error: {} is not a Unicode scalar value", value),
+        }
+    }
+}
 
-            Token::Plus => {
-                let (a, b) = (stack.pop().ok_or("Invalid expression")?, stack.pop().ok_or("Invalid expression")?);
-                stack.push(b + a);
-            },
+#[cfg(feature = "uuid")]
+fn run_uuid(values: &[String], settings: &Settings) {
+    for value in values {
+        match convert_input(value, settings) {
+            Ok((num, _)) => {
+                println!("{}", value);
+                for line in format::uuid_fields(num as u128) {
+                    println!("{}", line);
+                }
+            }
+            Err(message) => println!("Error: {}", message),
+        }
+    }
+}
 
-            Token::Minus => {
-                let (a, b) = (stack.pop().ok_or("Invalid expression")?, stack.pop().ok_or("Invalid expression")?);
-                stack.push(b - a);
-            },
+fn run_diff(a: &str, b: &str, settings: &Settings) {
+    match (convert_input(a, settings), convert_input(b, settings)) {
+        (Ok((x, _)), Ok((y, _))) => {
+            for line in diff::render([a, b], [x, y], &settings.style) {
+                println!("{}", line);
+            }
+        }
+        (Err(message), _) | (_, Err(message)) => println!("Error: {}", message),
+    }
+}
 
-            Token::Star => {
-                let (a, b) = (stack.pop().ok_or("Invalid expression")?, stack.pop().ok_or("Invalid expression")?);
-                stack.push(b * a);
-            },
+fn run_color(values: &[String], settings: &Settings) {
+    for value in values {
+        let rgb = color::parse(value).or_else(|| {
+            let num = convert_input(value, settings).ok()?.0;
+            u32::try_from(num).ok().filter(|&rgb| rgb <= 0xffffff)
+        });
+        match rgb {
+            Some(rgb) => {
+                println!("{}", value);
+                for line in color::describe(rgb, &settings.style) {
+                    println!("{}", line);
+                }
+            }
+            None => println!("Error: {} is not a color", value),
+        }
+    }
+}
 
-            Token::Slash => {
-                let (a, b) = (stack.pop().ok_or("Invalid expression")?, stack.pop().ok_or("Invalid expression")?);
-                if a == 0 {
-                    return Err("Division by zero");
+fn run_perm(values: &[String], settings: &Settings) {
+    for value in values {
+        if let Some(mode) = format::parse_permissions(value) {
+            println!("{:04o}", mode);
+            continue;
+        }
+        // Bare digits are octal, as chmod reads them
+        let mode = match value.bytes().all(|b| (b'0'..=b'7').contains(&b)) {
+            true => i128::from_str_radix(value, 8).ok(),
+            false => convert_input(value, settings).ok().map(|(num, _)| num),
+        };
+        match mode.and_then(|mode| u32::try_from(mode).ok()).filter(|&mode| mode <= 0o7777) {
+            Some(mode) => println!("{}", format::permissions(mode)),
+            None => println!("Error: {} is not a permission mode from 0000 to 7777", value),
+        }
+    }
+}
+
+#[cfg(feature = "ip")]
+fn run_net(blocks: &[String], settings: &Settings) {
+    for block in blocks {
+        match format::subnet(block, &settings.style) {
+            Ok(lines) => {
+                println!("{}", block);
+                for line in lines {
+                    println!("{}", line);
                 }
-                stack.push(b / a);
-            },
-            _ => return Err("Unexpected token")
+            }
+            Err(message) => println!("Error: {}", message),
+        }
+    }
+}
+
+fn run_time(values: &[String], precision: Option<timestamp::Precision>, settings: &Settings) {
+    for value in values {
+        let unit = precision.unwrap_or(timestamp::Precision::Seconds);
+        if let Ok(epoch) = timestamp::parse(value, unit) {
+            println!("{}", value);
+            println!("  epoch  {} ({}) {}", epoch, format::hex(epoch, &settings.style), unit.name());
+            continue;
+        }
+        let times = convert_input(value, settings)
+            .map_err(|message| format!("{} is neither a timestamp nor an ISO 8601 time: {}", value, message))
+            .and_then(|(num, _)| {
+                let unit = precision.unwrap_or(timestamp::Precision::detect(num));
+                let times = timestamp::from_epoch(num, unit);
+                times.map(|times| (unit, times)).ok_or(format!("{} is out of range for a timestamp", value))
+            });
+        match times {
+            Ok((unit, (utc, local))) => {
+                println!("{} ({})", value, unit.name());
+                println!("  UTC    {}", utc);
+                println!("  local  {}", local);
+            }
+            Err(message) => println!("Error: {}", message),
         }
     }
-    stack.pop().ok_or("Invalid expression")
 }
 
-fn check_force_output(args: &Vec<String>) -> (Option<&'static str>) {
-    let bases: [&'static str; 5] = ["f", "2", "8", "10", "16"];
+// The checksum builtins over hex bytes or a file, as adler32(unhex("...")) or
+// adler32(file("...")) would give them
+fn run_checksum(algorithm: ChecksumAlgorithm, hex: Option<String>, file: Option<PathBuf>) {
+    let (name, digits) = match algorithm {
+        ChecksumAlgorithm::Adler32 => ("adler32", 8),
+        ChecksumAlgorithm::Fletcher16 => ("fletcher16", 4),
+        ChecksumAlgorithm::Fletcher32 => ("fletcher32", 8),
+    };
+    let call = |name: &str, arg: Value| builtins::call(name, &[arg]).expect("checksum builtins exist");
+    let data = match (hex, file) {
+        (_, Some(path)) => call("file", Value::Bytes(path.to_string_lossy().into_owned().into_bytes())),
+        (Some(hex), None) => call("unhex", Value::Bytes(hex.into_bytes())),
+        (None, None) => unreachable!(), // clap requires one of the two
+    };
+    match data.and_then(|data| call(name, data)) {
+        Ok(sum) => println!("0x{:0digits$x}", sum.as_int(&mut Vec::new()).unwrap_or_default(), digits = digits),
+        Err(message) => println!("Error: {}", message),
+    }
+}
+
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => return,
+        1 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .without_time()
+        .init();
+}
+
+// Values given as `-` are replaced by the whitespace-separated values on
+// stdin, which is read once, and ranges such as 0..0xf by each value in them
+fn expand_values(args: &[String], settings: &Settings) -> Vec<String> {
+    let mut stdin_values = None;
+    let mut values = Vec::new();
     for arg in args {
-        let arg_str = arg.as_str();
-        if let Some(c) = arg_str.chars().next(){
-            if c == '=' {
-                let parts: Vec<&str> = arg_str.split('=').collect();
-                if bases.contains(&parts[1]) {
-                    for base in bases {
-                        if base == parts[1] {
-                            return Some(base)
-                        }
-                    }
+        if let Some((lo, hi)) = arg.split_once("..") {
+            if let (Ok(lo), Ok(hi)) = (to_int(lo, settings), to_int(hi, settings)) {
+                if lo <= hi && hi - lo < MAX_RANGE {
+                    values.extend((lo..=hi).map(|num| num.to_string()));
+                    continue;
                 }
             }
         }
+        if arg != "-" {
+            values.push(arg.clone());
+            continue;
+        }
+        if stdin_values.is_none() {
+            let input = std::io::read_to_string(std::io::stdin()).unwrap_or_default();
+            stdin_values = Some(input.split_whitespace().map(String::from).collect::<Vec<_>>());
+            values.extend(stdin_values.iter().flatten().cloned());
+        }
     }
-    None
+    values
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    
-    if args.len() == 1 {
-        loop {
-            let tokens = parse_expr();
-            let result = eval_expr(infix_to_postfix(tokens));
-            match result {
-                Ok(r) => println!("{}", r),
-                Err(e) => println!("{}", e)
+// Radix and digits of integer literals written in a positional base
+fn literal_digits<'a>(input: &'a str, settings: &Settings) -> Option<(u32, &'a str)> {
+    let (radix, digits) = if let Some(digits) = input.strip_prefix("0x").or_else(|| input.strip_prefix("0X")) {
+        (16, digits)
+    } else if let Some(digits) = input.strip_prefix("0b").or_else(|| input.strip_prefix("0B")) {
+        (2, digits)
+    } else if let Some(digits) = input.strip_prefix("0o").or_else(|| input.strip_prefix("0O")) {
+        (8, digits)
+    } else if let Some(digits) = input.strip_suffix('d').filter(|_| settings.legacy_literals) {
+        (2, digits)
+    } else if let Some(digits) = input.strip_suffix('d') {
+        (10, digits)
+    } else if let Some(digits) = input.strip_prefix('b').filter(|_| !settings.legacy_literals) {
+        (2, digits)
+    } else if let Some(digits) = input.strip_prefix("Ox").filter(|_| !settings.legacy_literals) {
+        (8, digits)
+    } else if let Some(digits) = input.strip_suffix('b') {
+        (2, digits)
+    } else if let Some(digits) = input.strip_suffix('o') {
+        (8, digits)
+    } else {
+        (10, input)
+    };
+    (!digits.is_empty() && digits.chars().all(|c| c.is_digit(radix))).then_some((radix, digits))
+}
+
+// Positional expansion of the input followed by repeated division into the
+// output base, for `--show-work`
+fn show_work(input: &str, num: i128, base: &str, settings: &Settings) -> Vec<String> {
+    let mut steps = Vec::new();
+    if let Some((radix, digits)) = literal_digits(input, settings) {
+        if radix != 10 {
+            let count = digits.len() as u32;
+            let terms: Vec<String> = digits
+                .chars()
+                .enumerate()
+                .map(|(i, digit)| format!("{}×{}^{}", digit, radix, count - 1 - i as u32))
+                .collect();
+            let values: Vec<String> = digits
+                .chars()
+                .enumerate()
+                .map(|(i, digit)| {
+                    let digit = digit.to_digit(radix).unwrap_or(0) as u128;
+                    let place = (radix as u128).checked_pow(count - 1 - i as u32);
+                    place.map_or_else(|| "0".to_string(), |place| (digit * place).to_string())
+                })
+                .collect();
+            steps.push(format!("{} in base {}:", highlight::highlight(input, settings), radix));
+            steps.push(format!("  {}", terms.join(" + ")));
+            steps.push(format!("  = {}", values.join(" + ")));
+            steps.push(format!("  = {}", num));
+        }
+    }
+    let radix = match base {
+        "2" => 2,
+        "8" => 8,
+        "16" => 16,
+        _ => return steps,
+    };
+    if num < 0 {
+        let bits = if i64::try_from(num).is_ok() { 64 } else { 128 };
+        steps.push(format!("{} is negative, so base {} shows its {}-bit two's complement", num, radix, bits));
+        return steps;
+    }
+    steps.push(format!("{} to base {} by repeated division:", num, radix));
+    let mut remainders = Vec::new();
+    let mut quotient = num;
+    loop {
+        let remainder = quotient % radix;
+        let digit = std::char::from_digit(remainder as u32, radix as u32).unwrap_or('?');
+        steps.push(format!("  {} ÷ {} = {} remainder {}", quotient, radix, quotient / radix, digit));
+        remainders.push(digit);
+        quotient /= radix;
+        if quotient == 0 {
+            break;
+        }
+    }
+    let digits: String = remainders.iter().rev().collect();
+    steps.push(format!("  remainders read from the bottom up: {}", digits));
+    steps
+}
+
+fn markdown_row(cells: &[String]) -> String {
+    let cells: Vec<String> = cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+    format!("| {} |", cells.join(" | "))
+}
+
+// The input and its value in each base, or the reason it failed
+fn conversion_record(input: &str, bases: &[&str], settings: &Settings) -> Record {
+    let mut record = Record::default();
+    record.push("input", Field::Text(input.to_string()));
+    // Float outputs alone keep fractions, without an integer to truncate to
+    let floats: Option<Vec<String>> = bases.iter().map(|base| convert_float(input, base, settings)).collect();
+    if let Some(floats) = floats {
+        for (base, output) in bases.iter().zip(floats) {
+            record.push(&base_title(base).to_lowercase(), Field::Text(output));
+        }
+        return record;
+    }
+    let settings = &*at_width(settings, sized_type(input));
+    match convert_at_word(input, settings) {
+        Ok((num, result)) => {
+            for base in bases {
+                let field = match *base {
+                    // Same reading as format_base, so a 128-bit pattern isn't -1
+                    "10" => match result.parse::<u128>() {
+                        Ok(bits) if bits > i128::MAX as u128 => Field::Uint(bits),
+                        _ => Field::Int(num),
+                    },
+                    _ => Field::Text(format_base(base, num, &result, settings)),
+                };
+                record.push(&base_title(base).to_lowercase(), field);
             }
         }
+        Err(message) => record.push("error", Field::Text(message.to_string())),
     }
-    else {
-        let base = check_force_output(&args);
-        let mut starting_index = 1;
-        if base.is_some() {
-            starting_index = 2;
-        }
-        for input in &args[starting_index..] {
-            match parse_num(input) {
-                Ok(result) => {
-                    let mut temp_num = 0;
-                    let mut temp_num_str = result.clone();
-                    while !temp_num_str.chars().all(|c| (c.is_numeric() || c == '.' || c == '-')) {
-                        let parse_result = parse_num(&temp_num_str);
-                        match parse_result {
-                            Ok(result_num) => {
-                                temp_num_str = result_num;
-                            },
-                            Err(e) => println!("Could not parse number")
-                        }
+    record
+}
+
+// Replaces the `field`th column of `line` (counting from 1) with `convert`'s
+// result, keeping separators and the other columns byte for byte
+fn replace_field(line: &str, field: usize, delim: Option<char>, convert: impl FnOnce(&str) -> String) -> String {
+    let range = match delim {
+        Some(delim) => {
+            let mut start = 0;
+            let mut columns = line.split(delim).map(|column| {
+                let range = start..start + column.len();
+                start = range.end + delim.len_utf8();
+                range
+            });
+            columns.nth(field - 1)
+        }
+        None => {
+            let mut columns = line.split_whitespace().map(|column| {
+                let start = column.as_ptr() as usize - line.as_ptr() as usize;
+                start..start + column.len()
+            });
+            columns.nth(field - 1)
+        }
+    };
+    match range {
+        Some(range) => format!("{}{}{}", &line[..range.start], convert(&line[range.clone()]), &line[range.end..]),
+        None => line.to_string(),
+    }
+}
+
+// `--field`: a hex-aware cut over stdin. Columns that fail to convert are
+// left as they were, with a warning.
+fn run_fields(field: usize, delim: Option<char>, base: Option<&str>, settings: &Settings) {
+    let base = base.unwrap_or("10");
+    for (number, line) in std::io::stdin().lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                println!("Error: Cannot read stdin: {}", e);
+                return;
+            }
+        };
+        let converted = replace_field(&line, field, delim, |column| {
+            if let Some(output) = convert_float(column, base, settings) {
+                return output;
+            }
+            match convert_at_word(column, settings) {
+                Ok((num, result)) => format_base(base, num, &result, &at_width(settings, sized_type(column))),
+                Err(_) if column.is_empty() => String::new(),
+                Err(message) => convert_wide(column, base, settings).unwrap_or_else(|| {
+                    eprintln!("warning: line {}: {}: {}", number + 1, column, message);
+                    column.to_string()
+                }),
+            }
+        });
+        println!("{}", converted);
+    }
+}
+
+fn run_convert(cli: &Cli, args: &[String], settings: &Settings) {
+    let (markdown, output, show_work) = (cli.markdown, cli.output, cli.show_work);
+    let base = check_force_output(args, settings);
+    let mut starting_index = 0;
+    if base.is_some() {
+        starting_index = 1;
+    }
+    if let Some(field) = cli.field {
+        if args[starting_index..].iter().any(|arg| arg != "-") {
+            println!("Error: --field reads lines from stdin; pass - instead of values");
+            return;
+        }
+        run_fields(field.get(), cli.delim, base, settings);
+        return;
+    }
+    let inputs = expand_values(&args[starting_index..], settings);
+    if cli.explain_float {
+        for input in &inputs {
+            match ieee::parse(input, settings) {
+                Ok(float) => {
+                    let input = highlight::highlight(input, settings);
+                    println!("{} as a {}: {:?}", input, float.precision.name(), float.value);
+                    for line in float.explain() {
+                        println!("{}", line);
                     }
-                    match i64::from_str_radix(&temp_num_str, 10) {
-                        Ok(num) => { temp_num = num; },
-                        Err(_) => println!("Failed to convert expression result")
+                }
+                Err(message) => println!("Error: {}", message),
+            }
+        }
+        return;
+    }
+    if cli.endian {
+        let width = settings.word.map(|ty| ty.bits().div_ceil(8) as usize);
+        for input in &inputs {
+            match convert_at_word(input, settings) {
+                Ok((num, _)) => {
+                    println!("{}", input);
+                    for line in format::byte_order(num, width, &settings.style) {
+                        println!("{}", line);
                     }
-                    if base.is_some() {
-                        match base {
-                            Some("f") => println!("{:.5}", temp_num),
-                            Some("2") => println!("b{:b}", temp_num),
-                            Some("8") => println!("Ox{:o}", temp_num),
-                            Some("10") => println!("{}", temp_num),
-                            Some("16") => println!("0x{:x}", temp_num),
-                            _ => println!("{}", result)
+                }
+                Err(message) => println!("Error: {}", message),
+            }
+        }
+        return;
+    }
+    // Without an output base, tables and records show the common ones side by side
+    let bases = match base {
+        Some(base) => vec![base],
+        None => vec!["10", "16", "8", "2"],
+    };
+    if let Some(format) = output {
+        let records: Vec<Record> = inputs.iter().map(|input| conversion_record(input, &bases, settings)).collect();
+        print!("{}", record::render(&records, format, "conversions"));
+        return;
+    }
+    if markdown {
+        let mut header = vec!["Input".to_string()];
+        header.extend(bases.iter().map(|base| base_title(base).to_string()));
+        println!("{}", markdown_row(&header));
+        println!("|{}", "---|".repeat(header.len()));
+        for input in &inputs {
+            let record = conversion_record(input, &bases, settings);
+            let mut row = vec![format!("`{}`", input)];
+            match record.get("error") {
+                Some(message) => {
+                    row.push(format!("Error: {}", message.text()));
+                    row.resize(header.len(), String::new());
+                }
+                None => row.extend(bases.iter().filter_map(|base| {
+                    let field = record.get(&base_title(base).to_lowercase())?;
+                    Some(format!("`{}`", field.text()))
+                })),
+            }
+            println!("{}", markdown_row(&row));
+        }
+        return;
+    }
+    let Some(base) = base else {
+        for line in all_bases(&inputs, settings) {
+            println!("{}", line);
+        }
+        return;
+    };
+    for input in &inputs {
+        if let Some(output) = convert_float(input, base, settings) {
+            println!("{}", output);
+            continue;
+        }
+        let output = match convert_at_word(input, settings) {
+            Ok((num, result)) => {
+                if show_work {
+                    for step in self::show_work(input, num, base, settings) {
+                        println!("{}", step);
+                    }
+                }
+                format_base(base, num, &result, &at_width(settings, sized_type(input)))
+            }
+            Err(message) => match convert_wide(input, base, settings) {
+                Some(output) => output,
+                None => {
+                    println!("Error: {}", message);
+                    continue;
+                }
+            },
+        };
+        match output.strip_prefix('b') {
+            Some(digits) if base == "2" => println!("{}", format::wrap_binary("b", digits, &settings.style)),
+            _ => println!("{}", output),
+        }
+    }
+}
+
+// Pads each column to its widest cell, two spaces apart. The last cell of a
+// row is left out of the widths, so a short row's error message can run on.
+fn aligned(rows: &[Vec<String>]) -> Vec<String> {
+    let mut widths = Vec::new();
+    for row in rows {
+        widths.resize(widths.len().max(row.len()), 0);
+        for (width, cell) in widths.iter_mut().zip(&row[..row.len().saturating_sub(1)]) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    rows.iter()
+        .map(|row| {
+            let cells = row.iter().zip(&widths).map(|(cell, &width)| format!("{:<width$}", cell, width = width));
+            cells.collect::<Vec<_>>().join("  ").trim_end().to_string()
+        })
+        .collect()
+}
+
+// Inputs given without an output base in decimal, hex, octal, binary and as
+// text: one line per base for a single input, or one line per input
+fn all_bases(inputs: &[String], settings: &Settings) -> Vec<String> {
+    let bases = ["10", "16", "8", "2", "text"];
+    let values = |input: &str| match convert_at_word(input, settings) {
+        Ok((num, result)) => {
+            let settings = at_width(settings, sized_type(input));
+            Ok(bases.iter().map(|base| format_base(base, num, &result, &settings)).collect::<Vec<_>>())
+        }
+        // Past 128 bits there is no text form, so that cell stays empty
+        Err(message) => match convert_wide(input, "10", settings) {
+            Some(_) => Ok(bases.iter().map(|base| convert_wide(input, base, settings).unwrap_or_default()).collect()),
+            None => Err(format!("Error: {}", message)),
+        },
+    };
+    if let [input] = inputs {
+        return match values(input) {
+            Ok(values) => {
+                let rows = bases.iter().zip(values).map(|(base, value)| vec![base_title(base).to_string(), value]);
+                aligned(&rows.collect::<Vec<_>>())
+            }
+            Err(message) => vec![message],
+        };
+    }
+    if inputs.is_empty() {
+        return Vec::new();
+    }
+    let header = std::iter::once("Input").chain(bases.iter().map(|base| base_title(base)));
+    let mut rows = vec![header.map(String::from).collect()];
+    for input in inputs {
+        let mut row = vec![input.clone()];
+        match values(input) {
+            Ok(values) => row.extend(values),
+            Err(message) => row.push(message),
+        }
+        rows.push(row);
+    }
+    aligned(&rows)
+}
+
+fn run_grep(pattern: &str, to: &str, list: bool, files: &[PathBuf], settings: &Settings) {
+    let pattern = match regex::Regex::new(pattern) {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            println!("Error: Invalid pattern: {}", e);
+            return;
+        }
+    };
+    let base = match to {
+        "dec" => Some("10"),
+        "hex" => Some("16"),
+        "oct" => Some("8"),
+        "bin" => Some("2"),
+        name => check_force_output(&[format!("={}", name)], settings),
+    };
+    let Some(base) = base else {
+        println!("Error: Unknown output base {}", to);
+        return;
+    };
+    let convert = |literal: &str| {
+        if let Some(output) = convert_float(literal, base, settings) {
+            return Some(output);
+        }
+        match convert_input(literal, settings) {
+            Ok((num, result)) => Some(format_base(base, num, &result, settings)),
+            Err(message) => convert_wide(literal, base, settings).or_else(|| {
+                eprintln!("warning: {}: {}", literal, message);
+                None
+            }),
+        }
+    };
+    let mut texts = Vec::new();
+    if files.is_empty() {
+        texts.push(("stdin".to_string(), std::io::read_to_string(std::io::stdin())));
+    }
+    texts.extend(files.iter().map(|file| (file.display().to_string(), fs::read_to_string(file))));
+    for (name, text) in texts {
+        let text = match text {
+            Ok(text) => text,
+            Err(e) => {
+                println!("Error: Cannot read {}: {}", name, e);
+                continue;
+            }
+        };
+        let mut out = String::new();
+        let mut last = 0;
+        for captures in pattern.captures_iter(&text) {
+            let Some(literal) = captures.get(1).or_else(|| captures.get(0)) else {
+                continue;
+            };
+            let Some(converted) = convert(literal.as_str()) else {
+                continue;
+            };
+            if list {
+                println!("{}", converted);
+                continue;
+            }
+            out.push_str(&text[last..literal.start()]);
+            out.push_str(&converted);
+            last = literal.end();
+        }
+        if !list {
+            out.push_str(&text[last..]);
+            print!("{}", out);
+        }
+    }
+}
+
+fn run_json(path: &str, args: &[String], aggregate: Option<extract::Aggregate>, settings: &Settings) {
+    let mut base = None;
+    let mut file = None;
+    for arg in args {
+        if arg.starts_with('=') {
+            match check_force_output(std::slice::from_ref(arg), settings) {
+                Some(name) => base = Some(name),
+                None => {
+                    println!("Error: Unknown output base {}", arg);
+                    return;
+                }
+            }
+        } else if file.replace(arg.as_str()).is_some() {
+            println!("Error: Expected at most one file");
+            return;
+        }
+    }
+    let text = match file {
+        Some(file) if file != "-" => fs::read_to_string(file),
+        _ => std::io::read_to_string(std::io::stdin()),
+    };
+    match text {
+        Ok(text) => extract::run(path, &text, base, aggregate, settings),
+        Err(e) => println!("Error: Cannot read {}: {}", file.unwrap_or("stdin"), e),
+    }
+}
+
+fn main() {
+    let mut cli = Cli::parse();
+    init_logging(cli.verbose);
+    let color = |terminal: bool| match cli.color {
+        ColorChoice::Auto => terminal && std::env::var_os("NO_COLOR").is_none(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+    highlight::set_enabled(Stream::Stdout, color(std::io::stdout().is_terminal()));
+    highlight::set_enabled(Stream::Stderr, color(std::io::stderr().is_terminal()));
+    let terminal_width = || {
+        let (width, _) = terminal_size::terminal_size()?;
+        std::io::stdout().is_terminal().then_some(width.0 as usize)
+    };
+
+    if cli.unsigned {
+        cli.word = Some(cli.word.map_or(IntType::U64, |ty| ty.with_signed(false)));
+    }
+    let mut settings = Settings {
+        word: cli.word,
+        legacy_literals: cli.legacy_literals,
+        style: format::Style {
+            hex_case: cli.hex_case,
+            hex_prefix: cli.hex_prefix,
+            pad: cli.pad,
+            group: cli.group,
+            wrap: cli.wrap.or_else(terminal_width).unwrap_or(0),
+        },
+        ..Settings::default()
+    };
+    define_rc_formats(&mut settings.formats);
+
+    if let Some(command) = cli.command.take() {
+        match command {
+            Command::Checksum { algorithm, hex, file } => run_checksum(algorithm, hex, file),
+            Command::Hash { algorithm, data, hex } => run_hash(algorithm, &data, hex),
+            Command::B64 { args, url } => {
+                run_codec(&args, |bytes| codec::base64_encode(bytes, url), codec::base64_decode)
+            }
+            Command::B32 { args } => run_codec(&args, codec::base32_encode, codec::base32_decode),
+            Command::B58 { args } => run_codec(&args, codec::base58_encode, codec::base58_decode),
+            Command::Utf8 { values, decode } => run_utf8(&values, decode, &settings),
+            #[cfg(feature = "uuid")]
+            Command::Uuid { values } => run_uuid(&values, &settings),
+            Command::Diff { a, b } => run_diff(&a, &b, &settings),
+            Command::Color { values } => run_color(&values, &settings),
+            Command::Perm { values } => run_perm(&values, &settings),
+            #[cfg(feature = "ip")]
+            Command::Net { blocks } => run_net(&blocks, &settings),
+            Command::Time { values, precision } => run_time(&values, precision, &settings),
+            Command::Run { file } => run_script(&file, &settings),
+            Command::Eval { exprs, file, keep_going } => run_eval(exprs, file, keep_going, &settings),
+            Command::Report { values, out } => {
+                let html = report::render(&expand_values(&values, &settings), &settings);
+                match out {
+                    Some(path) => {
+                        if let Err(e) = fs::write(&path, html) {
+                            println!("Error: Cannot write {}: {}", path.display(), e);
                         }
                     }
-                },
-                Err(BaseConversionError::ParseIntError) => println!("Error: Failed to parse input"),
-                Err(BaseConversionError::InvalidInputFormat) => println!("Error: Invalid input format"),
+                    None => print!("{}", html),
+                }
             }
+            Command::Json { path, args, aggregate } => run_json(&path, &args, aggregate, &settings),
+            Command::Grep { pattern, to, list, files } => run_grep(&pattern, &to, list, &files, &settings),
+            Command::Lsp => {
+                if let Err(e) = lsp::run(&settings) {
+                    eprintln!("Error: {}", e);
+                }
+            }
+            Command::Convert { values } => run_convert(&cli, &values, &settings),
         }
+        return;
     }
-}
\ No newline at end of file
+
+    if cli.schema {
+        println!("{}", record::conversion_schema());
+        return;
+    }
+
+    if cli.paste {
+        match clipboard::paste() {
+            Ok(text) => cli.values.extend(text.split_whitespace().map(String::from)),
+            Err(e) => {
+                println!("Error: {}", e);
+                return;
+            }
+        }
+    }
+
+    // Without values, --field still converts stdin rather than starting the REPL
+    if cli.values.is_empty() && cli.field.is_none() && !cli.paste {
+        let mut env = session(&settings);
+        cancel::install_handler();
+        while let Some(input) = read_line(&env.settings) {
+            if let Some(command) = input.strip_prefix(':') {
+                run_command(command, &mut env);
+                continue;
+            }
+            run_line_cancellable(&input, &mut env);
+        }
+    }
+    else {
+        run_convert(&cli, &cli.values, &settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn all_bases_of_one_value() {
+        assert_eq!(
+            all_bases(&inputs(&["255"]), &Settings::default()),
+            ["Decimal  255", "Hex      0xff", "Octal    Ox377", "Binary   b11111111", "Text     \"\\xff\""]
+        );
+        assert_eq!(all_bases(&inputs(&["0"]), &Settings::default())[4], "Text     \"\\x00\"");
+        assert_eq!(all_bases(&inputs(&["zz"]), &Settings::default()), ["Error: Failed to parse input"]);
+        assert!(all_bases(&[], &Settings::default()).is_empty());
+    }
+
+    #[test]
+    fn all_bases_of_several_values() {
+        let rows = all_bases(&inputs(&["1", "0x41", "zz"]), &Settings::default());
+        assert_eq!(rows[0], "Input  Decimal  Hex   Octal  Binary    Text");
+        assert_eq!(rows[1], "1      1        0x1   Ox1    b1        \"\\x01\"");
+        assert_eq!(rows[2], "0x41   65       0x41  Ox101  b1000001  \"A\"");
+        assert_eq!(rows[3], "zz     Error: Failed to parse input");
+        assert_eq!(rows.len(), 4);
+    }
+
+    #[test]
+    fn all_bases_at_a_word_size() {
+        let at = |word: &str| Settings { word: settings::parse_word(word).ok(), ..Settings::default() };
+        let byte = all_bases(&inputs(&["-1"]), &at("8"));
+        assert_eq!(byte[..2], ["Decimal  255", "Hex      0xff"]);
+        let signed = all_bases(&inputs(&["0xff"]), &at("i8"));
+        assert_eq!(signed[0], "Decimal  -1");
+    }
+
+    #[cfg(feature = "bigint")]
+    #[test]
+    fn all_bases_past_128_bits() {
+        let wide = all_bases(&inputs(&["0x1ffffffffffffffffffffffffffffffff"]), &Settings::default());
+        assert_eq!(wide[0], "Decimal  680564733841876926926749214863536422911");
+        assert_eq!(wide[4], "Text");
+    }
+}
@@ -0,0 +1,130 @@
+// Exact decimal expansions of doubles, used to tell whether a decimal
+// literal survives conversion to binary unchanged
+
+const LIMB: u64 = 1_000_000_000;
+
+// Little-endian base-10^9 digits
+struct Decimal(Vec<u64>);
+
+impl Decimal {
+    fn from_u64(mut num: u64) -> Self {
+        let mut limbs = Vec::new();
+        while num > 0 {
+            limbs.push(num % LIMB);
+            num /= LIMB;
+        }
+        Decimal(limbs)
+    }
+
+    fn mul_small(&mut self, factor: u64) {
+        let mut carry = 0;
+        for limb in self.0.iter_mut() {
+            let product = *limb * factor + carry;
+            *limb = product % LIMB;
+            carry = product / LIMB;
+        }
+        if carry > 0 {
+            self.0.push(carry);
+        }
+    }
+
+    fn to_digits(&self) -> String {
+        let Some((last, rest)) = self.0.split_last() else {
+            return "0".to_string();
+        };
+        let mut digits = last.to_string();
+        for limb in rest.iter().rev() {
+            digits.push_str(&format!("{:09}", limb));
+        }
+        digits
+    }
+}
+
+// The exact decimal value of a finite double, e.g. 0.1 gives
+// 0.1000000000000000055511151231257827021181583404541015625
+pub fn exact_decimal(value: f64) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    let bits = value.abs().to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64;
+    let fraction = bits & ((1 << 52) - 1);
+    // value = mantissa * 2^exponent
+    let (mantissa, exponent) = if exponent == 0 {
+        (fraction, -1074)
+    } else {
+        (fraction | 1 << 52, exponent - 1075)
+    };
+
+    let mut num = Decimal::from_u64(mantissa);
+    let digits = if exponent >= 0 {
+        for _ in 0..exponent {
+            num.mul_small(2);
+        }
+        num.to_digits()
+    } else {
+        // m * 2^-k == m * 5^k / 10^k
+        let places = (-exponent) as usize;
+        for _ in 0..places {
+            num.mul_small(5);
+        }
+        let digits = num.to_digits();
+        let digits = format!("{:0>width$}", digits, width = places + 1);
+        let (int_part, frac_part) = digits.split_at(digits.len() - places);
+        let frac_part = frac_part.trim_end_matches('0');
+        if frac_part.is_empty() {
+            int_part.to_string()
+        } else {
+            format!("{}.{}", int_part, frac_part)
+        }
+    };
+    let sign = if value.is_sign_negative() && value != 0.0 { "-" } else { "" };
+    format!("{}{}", sign, digits)
+}
+
+// Rewrites a decimal literal such as "00.50", "5e-1" or "-1.5E2" into the
+// canonical form produced by exact_decimal
+fn normalize_literal(literal: &str) -> Option<String> {
+    let (sign, unsigned) = match literal.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", literal.strip_prefix('+').unwrap_or(literal)),
+    };
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+        None => (unsigned, 0),
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if !int_part.chars().chain(frac_part.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    // All digits with the decimal point `point` places from the left
+    let digits = format!("{}{}", int_part, frac_part);
+    let point = int_part.len() as i64 + exponent;
+    let (int_digits, frac_digits) = if point <= 0 {
+        (String::new(), "0".repeat((-point) as usize) + &digits)
+    } else if point as usize >= digits.len() {
+        (digits.clone() + &"0".repeat(point as usize - digits.len()), String::new())
+    } else {
+        let (int_digits, frac_digits) = digits.split_at(point as usize);
+        (int_digits.to_string(), frac_digits.to_string())
+    };
+
+    let int_digits = int_digits.trim_start_matches('0');
+    let int_digits = if int_digits.is_empty() { "0" } else { int_digits };
+    let frac_digits = frac_digits.trim_end_matches('0');
+    let sign = if int_digits == "0" && frac_digits.is_empty() { "" } else { sign };
+    if frac_digits.is_empty() {
+        Some(format!("{}{}", sign, int_digits))
+    } else {
+        Some(format!("{}{}.{}", sign, int_digits, frac_digits))
+    }
+}
+
+// Whether the double parsed from `literal` is exactly the number written
+pub fn is_exact(literal: &str, value: f64) -> bool {
+    match normalize_literal(literal) {
+        Some(normalized) => normalized == exact_decimal(value),
+        None => true,
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt::Write;
+
+use clap::ValueEnum;
+use serde_json::json;
+
+// Bumped whenever a field is renamed, removed or changes type; new optional
+// fields keep the version
+pub const SCHEMA_VERSION: i64 = 1;
+
+// Machine-readable formats for `--output`
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+#[derive(Clone)]
+pub enum Field {
+    Int(i128),
+    // 128-bit patterns above i128::MAX, which have no signed reading
+    Uint(u128),
+    Text(String),
+}
+
+impl Field {
+    // JSON syntax, which is also valid YAML for numbers and strings
+    fn literal(&self) -> String {
+        match self {
+            Field::Int(num) => num.to_string(),
+            Field::Uint(num) => num.to_string(),
+            Field::Text(text) => serde_json::Value::from(text.as_str()).to_string(),
+        }
+    }
+
+    // TOML integers have 64 bits, so wider values are written as strings
+    fn toml(&self) -> toml::Value {
+        match self {
+            Field::Int(num) => i64::try_from(*num).map_or_else(|_| num.to_string().into(), toml::Value::from),
+            Field::Uint(num) => num.to_string().into(),
+            Field::Text(text) => text.as_str().into(),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        match self {
+            Field::Int(num) => num.to_string(),
+            Field::Uint(num) => num.to_string(),
+            Field::Text(text) => text.clone(),
+        }
+    }
+}
+
+// Ordered key/value pairs describing one result, rendered the same way by
+// every output format
+#[derive(Clone, Default)]
+pub struct Record {
+    fields: Vec<(String, Field)>,
+}
+
+impl Record {
+    pub fn push(&mut self, key: &str, field: Field) {
+        self.fields.push((key.to_string(), field));
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Field> {
+        self.fields.iter().find(|(name, _)| name == key).map(|(_, field)| field)
+    }
+}
+
+// Renders records under `key` next to the schema version: a JSON object, a
+// YAML mapping, or a TOML array of tables
+pub fn render(records: &[Record], format: Format, key: &str) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Json => {
+            let objects: Vec<String> = records
+                .iter()
+                .map(|record| {
+                    let pairs: Vec<String> = record
+                        .fields
+                        .iter()
+                        .map(|(key, field)| format!("\"{}\": {}", key, field.literal()))
+                        .collect();
+                    format!("    {{{}}}", pairs.join(", "))
+                })
+                .collect();
+            let _ = writeln!(out, "{{\n  \"schema_version\": {},", SCHEMA_VERSION);
+            if objects.is_empty() {
+                let _ = writeln!(out, "  \"{}\": []\n}}", key);
+            } else {
+                let _ = writeln!(out, "  \"{}\": [\n{}\n  ]\n}}", key, objects.join(",\n"));
+            }
+        }
+        Format::Yaml => {
+            let _ = writeln!(out, "schema_version: {}", SCHEMA_VERSION);
+            if records.is_empty() {
+                let _ = writeln!(out, "{}: []", key);
+            } else {
+                let _ = writeln!(out, "{}:", key);
+            }
+            for record in records {
+                for (i, (key, field)) in record.fields.iter().enumerate() {
+                    let _ = writeln!(out, "  {} {}: {}", if i == 0 { "-" } else { " " }, key, field.literal());
+                }
+            }
+        }
+        // Built as a document so keys such as `balanced ternary` are quoted
+        Format::Toml => {
+            let tables = records
+                .iter()
+                .map(|record| record.fields.iter().map(|(key, field)| (key.clone(), field.toml())).collect())
+                .map(toml::Value::Table)
+                .collect();
+            let mut document = toml::Table::new();
+            document.insert("schema_version".to_string(), SCHEMA_VERSION.into());
+            document.insert(key.to_string(), toml::Value::Array(tables));
+            out = document.to_string();
+        }
+    }
+    out
+}
+
+// JSON Schema for `--output` conversions, printed by `--schema`
+pub fn conversion_schema() -> String {
+    let text = |description: &str| json!({ "type": "string", "description": description });
+    let schema = json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "rax conversions",
+        "type": "object",
+        "required": ["schema_version", "conversions"],
+        "properties": {
+            "schema_version": { "const": SCHEMA_VERSION },
+            "conversions": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["input"],
+                    "properties": {
+                        "input": text("The value as given on the command line"),
+                        "error": text("Why the input could not be converted; no other fields are present"),
+                        "decimal": { "type": "integer", "description": "Value as a signed 128-bit integer" },
+                        "hex": text("Hex, styled by --hex-case and --hex-prefix"),
+                        "octal": text("Octal with an Ox prefix"),
+                        "binary": text("Binary with a b prefix"),
+                        "float": text("Value with five decimal places"),
+                    },
+                    "additionalProperties": text("Value in the named output format, such as gray"),
+                },
+            },
+        },
+    });
+    serde_json::to_string_pretty(&schema).unwrap_or_default()
+}
@@ -0,0 +1,142 @@
+use std::fmt::Write;
+
+use raxrs::format_base;
+use raxrs::ieee::{self, Ieee754};
+use raxrs::settings::Settings;
+
+use crate::convert_input;
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em auto; max-width: 70em; color: #222; }
+h1 { font-size: 1.4em; }
+section { border: 1px solid #ddd; border-radius: 6px; padding: 0 1em 1em; margin-bottom: 1.5em; }
+h2 { font-family: ui-monospace, monospace; font-size: 1.15em; }
+h3 { font-size: 0.95em; color: #555; margin-bottom: 0.3em; }
+table { border-collapse: collapse; font-family: ui-monospace, monospace; }
+th, td { border: 1px solid #ddd; padding: 0.2em 0.5em; text-align: left; }
+th { background: #f5f5f5; font-weight: normal; color: #555; }
+table.bits td, table.bits th { text-align: center; padding: 0.2em 0.3em; }
+table.bits td.one { background: #dbeafe; }
+table.bits .nibble { border-left: 2px solid #999; }
+pre { background: #f8f8f8; padding: 0.5em; }
+.error { color: #b91c1c; }
+";
+
+// What one input decodes to
+struct Entry {
+    num: i128,
+    float: Option<Ieee754>,
+}
+
+// Float literals (1.5, 1.5f, Fx3ff8000000000000, 1.5F) get an IEEE 754
+// breakdown, with their bit pattern as the integer value
+fn decode(input: &str, settings: &Settings) -> Result<Entry, &'static str> {
+    let entry = if ieee::is_float_literal(input) {
+        let float = ieee::parse(input, settings)?;
+        Entry { num: float.bits() as i128, float: Some(float) }
+    } else {
+        Entry { num: convert_input(input, settings)?.0, float: None }
+    };
+    Ok(entry)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Smallest of 8, 16, 32, 64 and 128 bits that holds the value; negative
+// values show 64 bits of two's complement when they fit, like hex output
+fn width(num: i128) -> u32 {
+    match i64::try_from(num) {
+        Ok(num) if num < 0 => 64,
+        Ok(num) => [8, 16, 32].into_iter().find(|&bits| num >> bits == 0).unwrap_or(64),
+        Err(_) => 128,
+    }
+}
+
+fn render_entry(html: &mut String, entry: &Entry, settings: &Settings) {
+    let num = entry.num;
+    let bits = width(num);
+    let _ = writeln!(html, "<table>");
+    for (title, value) in [
+        ("Decimal", num.to_string()),
+        ("Hex", format_base("16", num, "", settings)),
+        ("Octal", format_base("8", num, "", settings)),
+        ("Binary", format_base("2", num, "", settings)),
+    ] {
+        let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", title, value);
+    }
+    let _ = writeln!(html, "</table>");
+
+    let _ = writeln!(html, "<h3>Bits</h3>\n<table class=\"bits\">");
+    let nibble = |bit: u32| if bit % 4 == 3 { " class=\"nibble\"" } else { "" };
+    let header: String = (0..bits).rev().map(|bit| format!("<th{}>{}</th>", nibble(bit), bit)).collect();
+    let _ = writeln!(html, "<tr>{}</tr>", header);
+    let cells: String = (0..bits)
+        .rev()
+        .map(|bit| {
+            let set = (num as u128 >> bit) & 1 == 1;
+            let class = match (set, bit % 4 == 3) {
+                (true, true) => " class=\"one nibble\"",
+                (true, false) => " class=\"one\"",
+                (false, true) => " class=\"nibble\"",
+                (false, false) => "",
+            };
+            format!("<td{}>{}</td>", class, set as u8)
+        })
+        .collect();
+    let _ = writeln!(html, "<tr>{}</tr>\n</table>", cells);
+
+    if let Some(ieee) = &entry.float {
+        let _ = writeln!(html, "<h3>IEEE 754 {}</h3>\n<table>", ieee.precision.name());
+        let exponent_width = ieee.precision.exponent_bits().div_ceil(4) as usize;
+        let mantissa_width = ieee.precision.mantissa_bits().div_ceil(4) as usize;
+        for (title, value) in [
+            ("Value", ieee.value.to_string()),
+            ("Class", ieee.class().to_string()),
+            ("Sign", ieee.sign.to_string()),
+            ("Exponent", format!("0x{:0w$x} ({} unbiased)", ieee.exponent, ieee.unbiased(), w = exponent_width)),
+            ("Mantissa", format!("0x{:0w$x}", ieee.mantissa, w = mantissa_width)),
+        ] {
+            let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", title, value);
+        }
+        let _ = writeln!(html, "</table>");
+    }
+
+    let bytes = &num.to_le_bytes()[..bits as usize / 8];
+    let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let ascii: String = bytes
+        .iter()
+        .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+        .collect();
+    let _ = writeln!(
+        html,
+        "<h3>Bytes (little-endian)</h3>\n<pre>00000000  {}  |{}|</pre>",
+        hex.join(" "),
+        escape(&ascii)
+    );
+}
+
+// A self-contained HTML page describing each input
+pub fn render(inputs: &[String], settings: &Settings) -> String {
+    let mut html = String::new();
+    let _ = writeln!(html, "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">");
+    let _ = writeln!(html, "<title>rax report</title>\n<style>{}</style>\n</head>\n<body>", STYLE);
+    let _ = writeln!(html, "<h1>rax report</h1>");
+    for input in inputs {
+        let _ = writeln!(html, "<section>\n<h2>{}</h2>", escape(input));
+        match decode(input, settings) {
+            Ok(entry) => render_entry(&mut html, &entry, settings),
+            Err(message) => {
+                let _ = writeln!(html, "<p class=\"error\">Error: {}</p>", escape(message));
+            }
+        }
+        let _ = writeln!(html, "</section>");
+    }
+    let _ = writeln!(
+        html,
+        "<footer><small>Generated by rax {}</small></footer>\n</body>\n</html>",
+        env!("CARGO_PKG_VERSION")
+    );
+    html
+}
@@ -0,0 +1,156 @@
+use crate::fixed::IntType;
+use crate::{format, to_int};
+
+// How literals with a fractional part are represented
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Numerics {
+    // IEEE 754 doubles
+    #[default]
+    Float,
+    // Exact base-10 decimals
+    Decimal,
+}
+
+// Options changed at runtime with `:set <name> <value>`
+#[derive(Clone, Default)]
+pub struct Settings {
+    // Treat `a ± b` and `[lo, hi]` as intervals and propagate their bounds
+    pub interval: bool,
+    pub numerics: Numerics,
+    // Show complex results as magnitude and angle instead of a + bi
+    pub polar: bool,
+    // Reduce arithmetic results modulo this value, e.g. 360 for angles
+    pub wrap: Option<i64>,
+    // Integer literals become this fixed-width type, so arithmetic wraps
+    // like a register of that size
+    pub word: Option<IntType>,
+    // Read literals in the original grammar, where a radix letter meant
+    // different things on each side of the digits: b101 was decimal 101 to be
+    // shown in binary, 101d binary to be shown in decimal, and Bx and Ox hex to
+    // be shown in binary and octal
+    pub legacy_literals: bool,
+    pub style: format::Style,
+    // Alphabets and plugins join the built-in formats here
+    pub formats: format::Registry,
+}
+
+// Word size for `:set word` and --word: a bit count such as 16 for an
+// unsigned word, or a type such as i16 for a signed one
+pub fn parse_word(value: &str) -> Result<IntType, String> {
+    let name = if value.starts_with(['u', 'i']) { value.to_string() } else { format!("u{}", value) };
+    IntType::parse(&name).ok_or_else(|| format!("Expected a word size such as 8, 16, i32 or off but found {}", value))
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "on" | "true" | "1" => Ok(true),
+        "off" | "false" | "0" => Ok(false),
+        _ => Err(format!("Expected on or off but found {}", value)),
+    }
+}
+
+impl Settings {
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name {
+            "interval" => self.interval = parse_bool(value)?,
+            "numerics" => {
+                self.numerics = match value {
+                    "float" => Numerics::Float,
+                    "decimal" => Numerics::Decimal,
+                    _ => return Err(format!("Expected float or decimal but found {}", value)),
+                }
+            }
+            "complex" => {
+                self.polar = match value {
+                    "rect" => false,
+                    "polar" => true,
+                    _ => return Err(format!("Expected rect or polar but found {}", value)),
+                }
+            }
+            "wrap" => {
+                self.wrap = match value {
+                    "off" => None,
+                    // 64 bits at most, so residues multiply without overflow
+                    _ => match to_int(value, self).map(i64::try_from) {
+                        Ok(Ok(modulus)) if modulus > 0 => Some(modulus),
+                        _ => return Err(format!("Expected a positive modulus or off but found {}", value)),
+                    },
+                }
+            }
+            "word" => {
+                self.word = match value {
+                    "off" => None,
+                    _ => Some(parse_word(value)?),
+                }
+            }
+            // Flips the sign of the word size, or switches to u64 when none is set
+            "unsigned" => {
+                self.word = match (self.word, parse_bool(value)?) {
+                    (None, true) => Some(IntType::U64),
+                    (Some(ty), true) => Some(ty.with_signed(false)),
+                    (Some(IntType::U64), false) | (None, false) => None,
+                    (Some(ty), false) => Some(ty.with_signed(true)),
+                }
+            }
+            // As --pad, for results shown in hex, octal or binary with a
+            // leading =16, =8 or =2
+            "pad" => {
+                self.style.pad = match value {
+                    "off" => 0,
+                    _ => value.parse().map_err(|_| format!("Expected a digit count or off but found {}", value))?,
+                }
+            }
+            "literals" => {
+                self.legacy_literals = match value {
+                    "standard" => false,
+                    "legacy" => true,
+                    _ => return Err(format!("Expected standard or legacy but found {}", value)),
+                }
+            }
+            // As --group, and applied to integer results: on groups decimal
+            // digits with _, or any other single character can separate them
+            "group" => {
+                self.style.group = match value {
+                    "off" => None,
+                    "on" => Some('_'),
+                    _ => match value.chars().collect::<Vec<_>>()[..] {
+                        [separator] => Some(separator),
+                        _ => return Err(format!("Expected on, off or a separator character but found {}", value)),
+                    },
+                }
+            }
+            _ => return Err(format!("Unknown setting {}", name)),
+        }
+        Ok(())
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let numerics = match self.numerics {
+            Numerics::Float => "float",
+            Numerics::Decimal => "decimal",
+        };
+        vec![
+            format!("interval {}", if self.interval { "on" } else { "off" }),
+            format!("numerics {}", numerics),
+            format!("complex {}", if self.polar { "polar" } else { "rect" }),
+            match self.wrap {
+                Some(modulus) => format!("wrap {}", modulus),
+                None => "wrap off".to_string(),
+            },
+            match self.word {
+                Some(ty) => format!("word {}", ty),
+                None => "word off".to_string(),
+            },
+            format!("unsigned {}", if self.word.is_some_and(|ty| !ty.is_signed()) { "on" } else { "off" }),
+            match self.style.pad {
+                0 => "pad off".to_string(),
+                digits => format!("pad {}", digits),
+            },
+            format!("literals {}", if self.legacy_literals { "legacy" } else { "standard" }),
+            match self.style.group {
+                Some(separator) => format!("group {}", separator),
+                None => "group off".to_string(),
+            },
+        ]
+    }
+}
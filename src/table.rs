@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::settings::Settings;
+use crate::{to_int, Value};
+
+// A user-supplied mapping from integer keys to numbers or names, loaded
+// with `:table name path` and indexed in expressions as `name[key]`
+#[derive(Clone)]
+pub struct Table {
+    entries: HashMap<i128, Value>,
+}
+
+impl Table {
+    // Reads a TOML file of top-level `key = value` pairs, or a CSV file of
+    // key,value rows with an optional header row. Anything else, such as a
+    // TOML section or a key given twice, is an error rather than skipped.
+    pub fn load(path: &Path, settings: &Settings) -> Result<Table, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        let mut table = Table { entries: HashMap::new() };
+        let mut keys = HashMap::new();
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            let document: toml::Table = contents.parse().map_err(|e| format!("{}: {}", path.display(), e))?;
+            for (key, value) in document {
+                let location = format!("{}: {}", path.display(), key);
+                let value = match value {
+                    toml::Value::Integer(num) => Value::Int(num.into()),
+                    toml::Value::Float(float) => Value::Float(float),
+                    toml::Value::String(text) => text_value(&text, settings),
+                    toml::Value::Table(_) => return Err(format!("{}: sections are not supported", location)),
+                    other => return Err(format!("{}: expected a number or string but found {}", location, other.type_str())),
+                };
+                table.insert(&key, value, location, &mut keys, settings)?;
+            }
+            return Ok(table);
+        }
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .comment(Some(b'#'))
+            .trim(csv::Trim::All)
+            .from_reader(contents.as_bytes());
+        for (i, record) in reader.records().enumerate() {
+            let record = record.map_err(|e| format!("{}: {}", path.display(), e))?;
+            let line = record.position().map_or(i as u64 + 1, |position| position.line());
+            let location = format!("{}:{}", path.display(), line);
+            let (Some(key), Some(value), None) = (record.get(0), record.get(1), record.get(2)) else {
+                return Err(format!("{}: expected `key,value`", location));
+            };
+            // The # only starts a comment at the start of a line
+            if value.contains('#') {
+                return Err(format!("{}: comments must be on a line of their own", location));
+            }
+            // Allow a header row such as `opcode,mnemonic`
+            if i == 0 && to_int(key, settings).is_err() {
+                continue;
+            }
+            table.insert(key, text_value(value, settings), location, &mut keys, settings)?;
+        }
+        Ok(table)
+    }
+
+    // Adds an entry, refusing a key that is already present, including one
+    // spelled differently such as 0x10 and 16
+    fn insert(
+        &mut self,
+        key: &str,
+        value: Value,
+        location: String,
+        keys: &mut HashMap<i128, String>,
+        settings: &Settings,
+    ) -> Result<(), String> {
+        let num = to_int(key, settings).map_err(|_| format!("{}: invalid key {}", location, key))?;
+        if let Some(first) = keys.insert(num, location.clone()) {
+            return Err(format!("{}: duplicate key {}, first given at {}", location, key, first));
+        }
+        self.entries.insert(num, value);
+        Ok(())
+    }
+
+    pub fn get(&self, key: i128) -> Option<&Value> {
+        self.entries.get(&key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// A number when the text reads as one, otherwise a name
+fn text_value(text: &str, settings: &Settings) -> Value {
+    match to_int(text, settings) {
+        Ok(num) => Value::Int(num),
+        Err(_) => Value::Name(text.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(name: &str, contents: &str) -> Result<Table, String> {
+        let path = std::env::temp_dir().join(format!("raxrs-table-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        let table = Table::load(&path, &Settings::default());
+        fs::remove_file(&path).unwrap();
+        table.map_err(|e| e.replace(&path.display().to_string(), name))
+    }
+
+    #[test]
+    fn csv_rows_with_header_quotes_and_comments() {
+        let csv = "opcode,mnemonic\n# moves\n0x8B, mov\n\"0x90\",\"nop, really\"\n0b11, 7\n";
+        let table = load("ops.csv", csv).unwrap();
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.get(0x8b), Some(&Value::Name("mov".into())));
+        assert_eq!(table.get(0x90), Some(&Value::Name("nop, really".into())));
+        assert_eq!(table.get(3), Some(&Value::Int(7)));
+        assert_eq!(table.get(4), None);
+        assert!(load("empty.csv", "").unwrap().is_empty());
+    }
+
+    #[test]
+    fn csv_rejects_what_it_cannot_read() {
+        assert_eq!(load("a.csv", "1,one\n0x1,uno\n").err().unwrap(), "a.csv:2: duplicate key 0x1, first given at a.csv:1");
+        assert_eq!(load("a.csv", "1,one # the first\n").err().unwrap(), "a.csv:1: comments must be on a line of their own");
+        assert_eq!(load("a.csv", "1,one,two\n").err().unwrap(), "a.csv:1: expected `key,value`");
+        assert_eq!(load("a.csv", "1\n").err().unwrap(), "a.csv:1: expected `key,value`");
+        assert_eq!(load("a.csv", "key,value\nzz,one\n").err().unwrap(), "a.csv:2: invalid key zz");
+        assert!(load("a.csv", "1,one\n2,two,three\n").is_err());
+    }
+
+    #[test]
+    fn toml_pairs_of_each_value_type() {
+        let toml = "# opcodes\n0x8B = \"mov\" # trailing comment\n\"0x90\" = \"nop\"\n3 = 7\n4 = \"0x10\"\n5 = 2.5\n";
+        let table = load("ops.toml", toml).unwrap();
+        assert_eq!(table.len(), 5);
+        assert_eq!(table.get(0x8b), Some(&Value::Name("mov".into())));
+        assert_eq!(table.get(0x90), Some(&Value::Name("nop".into())));
+        assert_eq!(table.get(3), Some(&Value::Int(7)));
+        assert_eq!(table.get(4), Some(&Value::Int(16)));
+        assert_eq!(table.get(5), Some(&Value::Float(2.5)));
+    }
+
+    #[test]
+    fn toml_rejects_what_it_cannot_read() {
+        assert_eq!(load("a.toml", "[ops]\n1 = \"one\"\n").err().unwrap(), "a.toml: ops: sections are not supported");
+        assert_eq!(load("a.toml", "16 = 1\n0x10 = 2\n").err().unwrap(), "a.toml: 0x10: duplicate key 0x10, first given at a.toml: 16");
+        assert_eq!(load("a.toml", "1 = true\n").err().unwrap(), "a.toml: 1: expected a number or string but found boolean");
+        assert_eq!(load("a.toml", "x = 1\n").err().unwrap(), "a.toml: x: invalid key x");
+        assert!(load("a.toml", "1 = 1\n1 = 2\n").is_err());
+        assert!(load("a.toml", "1 = \n").is_err());
+    }
+}
@@ -0,0 +1,141 @@
+// Unix timestamps and ISO 8601 times for the time subcommand
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl Precision {
+    // Read from s, ms, us or ns, as given to --precision
+    pub fn parse(name: &str) -> Result<Precision, String> {
+        match name {
+            "s" => Ok(Precision::Seconds),
+            "ms" => Ok(Precision::Millis),
+            "us" | "µs" => Ok(Precision::Micros),
+            "ns" => Ok(Precision::Nanos),
+            _ => Err(format!("{} is not one of s, ms, us or ns", name)),
+        }
+    }
+
+    // The unit a timestamp is most likely in, going by its magnitude: seconds
+    // up to the year 5138, then the smaller units over the same span
+    pub fn detect(value: i128) -> Precision {
+        match value.unsigned_abs() {
+            0..100_000_000_000 => Precision::Seconds,
+            100_000_000_000..100_000_000_000_000 => Precision::Millis,
+            100_000_000_000_000..100_000_000_000_000_000 => Precision::Micros,
+            _ => Precision::Nanos,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Precision::Seconds => "seconds",
+            Precision::Millis => "milliseconds",
+            Precision::Micros => "microseconds",
+            Precision::Nanos => "nanoseconds",
+        }
+    }
+
+    fn per_second(self) -> i128 {
+        match self {
+            Precision::Seconds => 1,
+            Precision::Millis => 1_000,
+            Precision::Micros => 1_000_000,
+            Precision::Nanos => 1_000_000_000,
+        }
+    }
+}
+
+// A timestamp as UTC and local ISO 8601 times, e.g. 2023-11-14T22:13:20Z and
+// 2023-11-14T23:13:20+01:00, with as many fraction digits as the unit needs
+pub fn from_epoch(value: i128, precision: Precision) -> Option<(String, String)> {
+    let per_second = precision.per_second();
+    let secs = i64::try_from(value.div_euclid(per_second)).ok()?;
+    let nanos = (value.rem_euclid(per_second) * (1_000_000_000 / per_second)) as u32;
+    let utc: DateTime<Utc> = DateTime::from_timestamp(secs, nanos)?;
+    let local = utc.with_timezone(&Local);
+    let digits = match precision {
+        Precision::Seconds => SecondsFormat::Secs,
+        Precision::Millis => SecondsFormat::Millis,
+        Precision::Micros => SecondsFormat::Micros,
+        Precision::Nanos => SecondsFormat::Nanos,
+    };
+    Some((utc.to_rfc3339_opts(digits, true), local.to_rfc3339_opts(digits, false)))
+}
+
+// The timestamp of an ISO 8601 time in the given unit, rounded down: one with
+// an offset or Z, or a date and time without one, or a bare date, both taken
+// as UTC
+pub fn parse(text: &str, precision: Precision) -> Result<i128, String> {
+    let time = parse_utc(text).ok_or(format!("{} is not an ISO 8601 time", text))?;
+    let nanos = time.timestamp() as i128 * 1_000_000_000 + time.timestamp_subsec_nanos() as i128;
+    Ok(nanos.div_euclid(1_000_000_000 / precision.per_second()))
+}
+
+fn parse_utc(text: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Some(time.to_utc());
+    }
+    for layout in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(text, layout) {
+            return Some(time.and_utc());
+        }
+    }
+    let date = NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?;
+    Some(date.and_time(Default::default()).and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_and_iso_times() {
+        let utc = |value, precision| from_epoch(value, precision).map(|(utc, _)| utc);
+        assert_eq!(utc(1_700_000_000, Precision::Seconds), Some("2023-11-14T22:13:20Z".to_string()));
+        assert_eq!(parse("2024-01-01T00:00:00Z", Precision::Seconds), Ok(1_704_067_200));
+        assert_eq!(parse("2024-01-01T01:00:00+01:00", Precision::Seconds), Ok(1_704_067_200));
+        assert_eq!(parse("2024-01-01", Precision::Seconds), Ok(1_704_067_200));
+        assert_eq!(parse("2024-01-01 00:00:30", Precision::Seconds), Ok(1_704_067_230));
+        assert!(parse("yesterday", Precision::Seconds).is_err());
+        assert_eq!(parse("2024-01-01T00:01", Precision::Seconds), Ok(1_704_067_260));
+        assert!(parse("2024-02-30", Precision::Seconds).is_err());
+        assert!(parse("2024-01-01T25:00:00Z", Precision::Seconds).is_err());
+        assert!(parse("", Precision::Seconds).is_err());
+        // Times before the epoch round down, away from zero
+        assert_eq!(parse("1969-12-31T23:59:59.5Z", Precision::Seconds), Ok(-1));
+        assert_eq!(parse("1969-12-31T23:59:59.5Z", Precision::Millis), Ok(-500));
+        assert_eq!(parse("1970-01-01T00:00:00.000000001Z", Precision::Nanos), Ok(1));
+        assert_eq!(parse("1970-01-01T00:00:00.000000001Z", Precision::Micros), Ok(0));
+    }
+
+    #[test]
+    fn epoch_precision() {
+        assert_eq!(Precision::detect(1_700_000_000), Precision::Seconds);
+        assert_eq!(Precision::detect(1_700_000_000_123), Precision::Millis);
+        assert_eq!(Precision::detect(1_700_000_000_123_456), Precision::Micros);
+        assert_eq!(Precision::detect(1_700_000_000_123_456_789), Precision::Nanos);
+        assert_eq!(Precision::detect(-1_700_000_000_123), Precision::Millis);
+        let utc = |value, precision| from_epoch(value, precision).map(|(utc, _)| utc);
+        assert_eq!(utc(1_700_000_000_123, Precision::Millis), Some("2023-11-14T22:13:20.123Z".to_string()));
+        assert_eq!(utc(-1, Precision::Micros), Some("1969-12-31T23:59:59.999999Z".to_string()));
+        assert_eq!(parse("2024-01-01T00:00:00.5Z", Precision::Millis), Ok(1_704_067_200_500));
+        assert_eq!(Precision::parse("ns"), Ok(Precision::Nanos));
+        assert!(Precision::parse("min").is_err());
+        assert_eq!(Precision::parse("µs"), Ok(Precision::Micros));
+        assert_eq!(Precision::detect(0), Precision::Seconds);
+        assert_eq!(Precision::detect(99_999_999_999), Precision::Seconds);
+        assert_eq!(Precision::detect(100_000_000_000), Precision::Millis);
+        assert_eq!(Precision::detect(i128::MIN), Precision::Nanos);
+        assert_eq!(utc(0, Precision::Nanos), Some("1970-01-01T00:00:00.000000000Z".to_string()));
+        assert_eq!(utc(-1, Precision::Seconds), Some("1969-12-31T23:59:59Z".to_string()));
+        assert_eq!(from_epoch(i128::MAX, Precision::Seconds), None);
+        assert_eq!(from_epoch(1 << 62, Precision::Seconds), None);
+    }
+}
@@ -0,0 +1,127 @@
+// Code points written as U+1F600 and the `=unicode` description of them
+
+// U+ followed by four to six hex digits, up to U+10FFFF. Shorter forms
+// such as U+41 are left to read as U plus a number.
+pub fn parse_codepoint(input: &str) -> Option<u32> {
+    let digits = input.strip_prefix("U+")?;
+    if digits.len() < 4 || digits.len() > 6 {
+        return None;
+    }
+    u32::from_str_radix(digits, 16).ok().filter(|&code| code <= 0x10ffff)
+}
+
+fn hex_units<T: std::fmt::LowerHex>(units: &[T], width: usize) -> String {
+    let units: Vec<String> = units.iter().map(|unit| format!("{:0width$x}", unit, width = width)).collect();
+    units.join(" ")
+}
+
+// e.g. "f0 9f 98 80" for U+1F600
+pub fn utf8_hex(c: char) -> String {
+    hex_units(c.encode_utf8(&mut [0; 4]).as_bytes(), 2)
+}
+
+#[cfg(feature = "unicode")]
+fn name_and_block(c: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    if let Some(name) = unicode_names2::name(c) {
+        parts.push(name.to_string());
+    }
+    if let Some(block) = unicode_blocks::find_unicode_block(c) {
+        parts.push(format!("block {}", block.name()));
+    }
+    parts
+}
+
+#[cfg(not(feature = "unicode"))]
+fn name_and_block(_: char) -> Vec<String> {
+    Vec::new()
+}
+
+// e.g. "U+1F600 😀 GRINNING FACE, block Emoticons, UTF-8 f0 9f 98 80,
+// UTF-16 d83d de00"; names and blocks need the unicode feature
+pub fn describe(code: i128) -> Option<String> {
+    let code = u32::try_from(code).ok().filter(|&code| code <= 0x10ffff)?;
+    let Some(c) = char::from_u32(code) else {
+        return Some(format!("U+{:04X} surrogate, not encodable on its own", code));
+    };
+    let glyph = if c.is_control() || c.is_whitespace() { String::new() } else { format!(" {}", c) };
+    let mut parts = name_and_block(c);
+    parts.push(format!("UTF-8 {}", utf8_hex(c)));
+    parts.push(format!("UTF-16 {}", hex_units(c.encode_utf16(&mut [0; 2]), 4)));
+    Some(format!("U+{:04X}{} {}", code, glyph, parts.join(", ")))
+}
+
+// Code points of a UTF-8 byte sequence, or where and why it is malformed
+pub fn utf8_decode(bytes: &[u8]) -> Result<Vec<char>, String> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Ok(text.chars().collect()),
+        Err(e) => {
+            let at = e.valid_up_to();
+            let reason = match e.error_len() {
+                Some(_) if bytes[at] & 0xc0 == 0x80 => "continuation byte without a lead byte",
+                Some(_) if bytes[at] >= 0xf5 || bytes[at] & 0xfe == 0xc0 => "byte that never appears in UTF-8",
+                Some(_) => "too few continuation bytes, or an overlong or surrogate encoding",
+                None => "sequence cut off at the end",
+            };
+            Err(format!("Invalid UTF-8 at byte {} (0x{:02x}): {}", at, bytes[at], reason))
+        }
+    }
+}
+
+// UTF-16 high and low surrogates for a code point above U+FFFF
+pub fn surrogate_pair(code: u32) -> Option<(u16, u16)> {
+    let offset = code.checked_sub(0x10000).filter(|_| code <= 0x10ffff)?;
+    Some((0xd800 + (offset >> 10) as u16, 0xdc00 + (offset & 0x3ff) as u16))
+}
+
+pub fn from_surrogates(high: u16, low: u16) -> Option<u32> {
+    if !(0xd800..=0xdbff).contains(&high) || !(0xdc00..=0xdfff).contains(&low) {
+        return None;
+    }
+    Some(0x10000 + ((high as u32 - 0xd800) << 10 | (low as u32 - 0xdc00)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_point_notation() {
+        assert_eq!(parse_codepoint("U+0041"), Some(0x41));
+        assert_eq!(parse_codepoint("U+41"), None);
+        assert_eq!(parse_codepoint("U+10FFFF"), Some(0x10ffff));
+        assert_eq!(parse_codepoint("U+0010FFFF"), None);
+        assert_eq!(parse_codepoint("U+110000"), None);
+        assert_eq!(parse_codepoint("U+"), None);
+        assert_eq!(parse_codepoint("u+0041"), None);
+        assert_eq!(parse_codepoint("U+-041"), None);
+        assert_eq!(utf8_hex('A'), "41");
+        assert_eq!(utf8_hex('😀'), "f0 9f 98 80");
+        assert_eq!(describe(-1), None);
+        assert!(describe(0x0a).unwrap().starts_with("U+000A "));
+    }
+
+    #[test]
+    fn utf8_decoding_reports_where_it_fails() {
+        assert_eq!(utf8_decode(&[0x48, 0xf0, 0x9f, 0x98, 0x80]), Ok(vec!['H', '😀']));
+        let error = |bytes: &[u8]| utf8_decode(bytes).unwrap_err();
+        assert!(error(&[0x41, 0x80]).starts_with("Invalid UTF-8 at byte 1 (0x80): continuation byte"));
+        assert!(error(&[0xc0, 0x80]).contains("never appears"));
+        assert!(error(&[0xe2, 0x82]).ends_with("cut off at the end"));
+        assert!(error(&[0xed, 0xa0, 0x80]).contains("surrogate"));
+        assert!(error(&[0xf8]).starts_with("Invalid UTF-8 at byte 0 (0xf8)"));
+        assert_eq!(utf8_decode(&[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn surrogates_cover_only_the_supplementary_planes() {
+        assert_eq!(surrogate_pair(0x10000), Some((0xd800, 0xdc00)));
+        assert_eq!(surrogate_pair(0x10ffff), Some((0xdbff, 0xdfff)));
+        assert_eq!(surrogate_pair(0xffff), None);
+        assert_eq!(surrogate_pair(0x110000), None);
+        assert_eq!(from_surrogates(0xd800, 0xdc00), Some(0x10000));
+        assert_eq!(from_surrogates(0xdbff, 0xdfff), Some(0x10ffff));
+        assert_eq!(from_surrogates(0xdc00, 0xd800), None);
+        assert_eq!(from_surrogates(0xd7ff, 0xdc00), None);
+    }
+}
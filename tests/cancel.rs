@@ -0,0 +1,28 @@
+// The cancel flag is global to the process, so these run apart from the
+// library's own tests, in one test so they don't race each other
+
+use raxrs::{cancel, checksum, eval, Env};
+
+fn interrupted(input: &str) -> bool {
+    match eval(input, &mut Env::default()) {
+        Err(diagnostics) => diagnostics[0].message == "Interrupted",
+        Ok(_) => false,
+    }
+}
+
+#[test]
+fn a_request_stops_loops_and_long_builtins() {
+    cancel::request();
+    assert!(interrupted("for i in 0..100 { i * 2 }"));
+    assert!(interrupted("{ let n = 0; while n < 100 { n = n + 1 }; n }"));
+    assert!(interrupted("adler32(\"abc\")"));
+    assert!(interrupted("crc32(\"abc\")"));
+    // Sums stop between blocks, however they were called
+    assert_eq!(checksum::adler32(&[0; 1 << 20]), Err("Interrupted"));
+    assert_eq!(checksum::CRCS[7].checksum(b"abc"), Err("Interrupted"));
+    if cfg!(feature = "bigint") {
+        assert!(interrupted("3 ** 1000"));
+    }
+    cancel::reset();
+    assert_eq!(eval("adler32(\"abc\") == 0x024d0127", &mut Env::default()).unwrap().unwrap().to_string(), "1");
+}
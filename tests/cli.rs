@@ -0,0 +1,313 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// Runs the binary with `args`, feeding `stdin`, and returns its output
+fn rax(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_raxrs"))
+        .args(args)
+        .env("RAX_RC", "/nonexistent")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // A run that fails before reading, such as grep with a bad pattern, may
+    // close stdin first, so a broken pipe here is not an error
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(args: &[&str]) -> String {
+    String::from_utf8(rax(args, "").stdout).unwrap()
+}
+
+fn stderr(args: &[&str]) -> String {
+    String::from_utf8(rax(args, "").stderr).unwrap()
+}
+
+#[test]
+fn word_wraps_conversions() {
+    assert_eq!(stdout(&["--word", "8", "=16", "300"]), "0x2c\n");
+    assert_eq!(stderr(&["--word", "8", "=16", "300"]), "warning: 300 wrapped to 44 in u8\n");
+    assert_eq!(stdout(&["--word", "i8", "=10", "0xff"]), "-1\n");
+    assert_eq!(stderr(&["--word", "i8", "=10", "0xff"]), "");
+    assert_eq!(stdout(&["--word", "16", "=16", "0x1234"]), "0x1234\n");
+    assert!(stdout(&["--word", "8", "300"]).starts_with("Decimal  44\n"));
+    assert_eq!(stdout(&["--word", "i8", "=16", "-1"]), "0xff\n");
+}
+
+#[test]
+fn checksum_matches_the_builtins() {
+    assert_eq!(stdout(&["checksum", "adler32", "57 69 6b 69 70 65 64 69 61"]), "0x11e60398\n");
+    assert_eq!(stdout(&["checksum", "fletcher16", "6162636465"]), "0xc8f0\n");
+    assert_eq!(repl("=16 fletcher16(unhex(\"6162636465\"))\n"), "0xc8f0\n");
+    assert_eq!(stdout(&["checksum", "fletcher32", "zz"]), "Error: Expected a string of hex byte pairs\n");
+}
+
+#[test]
+fn stray_brackets_and_commas_are_errors() {
+    assert_eq!(repl("]\n"), "]\n^ Unmatched ]\n");
+    assert_eq!(
+        repl("1,2\n"),
+        "1,2\n ^ Commas only separate arguments, list items and tuple elements\n"
+    );
+    assert_eq!(repl("q, r = divmod(17, 5)\nq * 10 + r\n"), "(3, 2)\n32\n");
+}
+
+#[test]
+fn float_outputs_keep_fractions() {
+    assert_eq!(stdout(&["=e", "2E-3"]), "2e-3\n");
+    assert_eq!(stderr(&["=e", "2E-3"]), "");
+    assert_eq!(stdout(&["=e", "1.5"]), "1.5e0\n");
+    assert_eq!(stdout(&["=a", "1.5"]), "0x1.8p+0\n");
+    assert_eq!(stderr(&["=a", "1.5"]), "");
+    assert_eq!(repl("=e 1.5\n=a 0.5\n"), "1.5e0\n0x1p-1\n");
+    assert_eq!(stdout(&["=si", "4.7e-3"]), "4.7 m\n");
+    assert_eq!(stderr(&["=si", "4.7e-3"]), "");
+    assert_eq!(stdout(&["=si", "0.0047"]), "4.7 m\n");
+    // Integer outputs still say what they drop
+    assert_eq!(stderr(&["=16", "1.5"]), "warning: 1.5 truncated to integer 1\n");
+}
+
+#[test]
+fn sized_literals_keep_their_width() {
+    assert_eq!(stdout(&["=16", "8'shFF"]), "0xff\n");
+    assert_eq!(stdout(&["=2", "8'sh80"]), "b10000000\n");
+    assert_eq!(stdout(&["=10", "8'sh80"]), "-128\n");
+    assert_eq!(stdout(&["=16", "16'shFFFF"]), "0xffff\n");
+    assert_eq!(repl("=16 8'shFF\n=2 4'b1010 + 1\n"), "0xff\nb1011\n");
+}
+
+// Runs `lines` through the REPL and returns what it printed, without prompts
+fn repl(lines: &str) -> String {
+    let output = String::from_utf8(rax(&[], lines).stdout).unwrap();
+    output.split_inclusive('\n').map(|line| line.trim_start_matches("> ")).collect()
+}
+
+#[test]
+fn pad_applies_to_hex_octal_and_binary() {
+    assert_eq!(stdout(&["--pad", "8", "=16", "255"]), "0x000000ff\n");
+    assert_eq!(stdout(&["--pad", "12", "=2", "5"]), "b000000000101\n");
+    assert_eq!(stdout(&["--pad", "2", "=16", "0x1234"]), "0x1234\n");
+    // In the REPL it shows through a leading output base; plain results stay decimal
+    assert_eq!(repl(":set pad 8\n=16 255\n=8 0x3f + 1\n255\n"), "0x000000ff\nOx00000100\n255\n");
+    assert_eq!(repl(":set pad 4\n=2 5\n:set pad off\n=2 5\n"), "b0101\nb101\n");
+    assert_eq!(repl(":set pad x\n"), "Error: Expected a digit count or off but found x\n");
+    assert_eq!(repl("=zz 1\n"), "Error: Unknown output base =zz\n");
+}
+
+#[test]
+fn group_applies_to_repl_results() {
+    assert_eq!(repl("1234567\n"), "1234567\n");
+    assert_eq!(repl(":set group on\n1234567\n-1234567\n123\n"), "1_234_567\n-1_234_567\n123\n");
+    assert_eq!(repl(":set group ,\n1000000u32\n2.5e6\n:set group off\n1000000\n"), "1,000,000\n2500000\n1000000\n");
+    assert_eq!(repl(":set group ab\n"), "Error: Expected on, off or a separator character but found ab\n");
+    assert_eq!(stdout(&["--group=,", "=10", "1234567"]), "1,234,567\n");
+}
+
+#[test]
+fn color_follows_each_stream() {
+    let output = rax(&["eval", "1 +* 2"], "");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "argument:1\n1 +* 2\n  ^ Invalid expression\n");
+    let output = rax(&["eval", "--color", "always", "1 +* 2"], "");
+    assert!(String::from_utf8(output.stderr).unwrap().starts_with("argument:1\n\x1b[34m1\x1b[0m \x1b[35m+\x1b[0m"));
+    assert!(stdout(&["--color", "always", "--explain-float", "1.5"]).starts_with("\x1b[34m1.5\x1b[0m as a double"));
+    assert!(stdout(&["--color", "always", "--show-work", "=2", "0x5"]).starts_with("\x1b[36m0x5\x1b[0m in base 16:"));
+    assert!(stdout(&["--explain-float", "1.5"]).starts_with("1.5 as a double"));
+}
+
+// Like stdout, feeding `stdin` to the command
+fn piped(args: &[&str], stdin: &str) -> String {
+    String::from_utf8(rax(args, stdin).stdout).unwrap()
+}
+
+#[test]
+fn dash_reads_values_from_stdin() {
+    assert_eq!(piped(&["=16", "-"], "10 0x20\n\n0b11\n"), "0xa\n0x20\n0x3\n");
+    assert_eq!(piped(&["=16", "5", "-", "7"], "1\n"), "0x5\n0x1\n0x7\n");
+    assert_eq!(piped(&["=16", "-"], ""), "");
+    assert_eq!(piped(&["=16", "-"], "zz\n"), "Error: Failed to parse input\n");
+}
+
+#[test]
+fn field_converts_one_column() {
+    assert_eq!(piped(&["--field", "2", "=16", "-"], "pid 255 x\nfoo 0x10 bar\n"), "pid 0xff x\nfoo 0x10 bar\n");
+    assert_eq!(piped(&["--field", "2", "--delim", ",", "=16", "-"], "a,255,b\n"), "a,0xff,b\n");
+    // Lines without the column, or where it is not a number, pass through
+    let output = rax(&["--field", "2", "=16", "-"], "a 1\nb zz\nc\n");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "a 0x1\nb zz\nc\n");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "warning: line 2: zz: Failed to parse input\n");
+    assert_eq!(stdout(&["--field", "1", "5"]), "Error: --field reads lines from stdin; pass - instead of values\n");
+    assert!(!rax(&["--field", "0", "-"], "").status.success());
+}
+
+#[test]
+fn markdown_tables() {
+    assert_eq!(
+        stdout(&["--markdown", "255", "zz"]),
+        "| Input | Decimal | Hex | Octal | Binary |\n\
+         |---|---|---|---|---|\n\
+         | `255` | `255` | `0xff` | `Ox377` | `b11111111` |\n\
+         | `zz` | Error: Failed to parse input |  |  |  |\n"
+    );
+    assert_eq!(stdout(&["--markdown", "=16", "255"]), "| Input | Hex |\n|---|---|\n| `255` | `0xff` |\n");
+}
+
+#[test]
+fn structured_output_formats() {
+    assert_eq!(
+        stdout(&["--output", "json", "255", "zz"]),
+        "{\n  \"schema_version\": 1,\n  \"conversions\": [\n    \
+         {\"input\": \"255\", \"decimal\": 255, \"hex\": \"0xff\", \"octal\": \"Ox377\", \"binary\": \"b11111111\"},\n    \
+         {\"input\": \"zz\", \"error\": \"Failed to parse input\"}\n  ]\n}\n"
+    );
+    assert_eq!(
+        stdout(&["--output", "yaml", "=16", "255"]),
+        "schema_version: 1\nconversions:\n  - input: \"255\"\n    hex: \"0xff\"\n"
+    );
+    assert_eq!(
+        stdout(&["--output", "toml", "=16", "255"]),
+        "schema_version = 1\n\n[[conversions]]\ninput = \"255\"\nhex = \"0xff\"\n"
+    );
+    // Keys with spaces are quoted and integers past 64 bits become strings
+    let toml: toml::Table = toml::from_str(&stdout(&["--output", "toml", "=bal3", "5"])).unwrap();
+    assert_eq!(toml["conversions"][0]["balanced ternary"].as_str(), Some("bal3#1TT"));
+    let toml: toml::Table = toml::from_str(&stdout(&["--output", "toml", "0x10000000000000000", "zz"])).unwrap();
+    assert_eq!(toml["conversions"][0]["decimal"].as_str(), Some("18446744073709551616"));
+    assert_eq!(toml["conversions"][1]["error"].as_str(), Some("Failed to parse input"));
+    // A 128-bit pattern with the top bit set reads as unsigned, as in =10
+    assert!(stdout(&["--output", "json", "=10", "0xffffffffffffffffffffffffffffffff"])
+        .contains("{\"input\": \"0xffffffffffffffffffffffffffffffff\", \"decimal\": 340282366920938463463374607431768211455}"));
+    assert!(!rax(&["--output", "csv", "255"], "").status.success());
+    assert!(!rax(&["--output", "json", "--markdown", "255"], "").status.success());
+}
+
+#[test]
+fn json_paths() {
+    let path = std::env::temp_dir().join(format!("raxrs-cli-{}.json", std::process::id()));
+    std::fs::write(&path, r#"{"a": {"latency_ns": 1500}, "items": [{"n": 1}, {"n": "0x10"}]}"#).unwrap();
+    let file = path.to_str().unwrap();
+    assert_eq!(stdout(&["json", ".a.latency_ns", file]), "1500\n");
+    assert_eq!(stdout(&["json", ".items[].n", file, "=16"]), "0x1\n0x10\n");
+    assert_eq!(stdout(&["json", ".items[1].n", file]), "16\n");
+    assert_eq!(stdout(&["json", "-a", "sum", ".items[].n", file]), "17\n");
+    assert_eq!(stdout(&["json", "-a", "count", ".items[].n", file]), "2\n");
+    assert_eq!(stdout(&["json", ".missing", file]), "");
+    std::fs::remove_file(&path).unwrap();
+    // JSON lines on stdin
+    assert_eq!(piped(&["json", ".n"], "{\"n\": 3}\n{\"n\": 4}\n"), "3\n4\n");
+    assert_eq!(piped(&["json", "-a", "max", ".n", "-", "=16"], "{\"n\": 3}\n{\"n\": 4}\n"), "0x4\n");
+}
+
+#[test]
+fn grep_converts_matches_in_place() {
+    assert_eq!(piped(&["grep"], "addr 0x10 and 0xff\nnone here\n"), "addr 16 and 255\nnone here\n");
+    assert_eq!(piped(&["grep", "-l", "--to", "bin"], "a 0x10 b 0x20\n"), "b10000\nb100000\n");
+    // Only the first capture group is converted
+    assert_eq!(piped(&["grep", "-e", "id=([0-9]+)", "--to", "hex"], "id=42 x=7\n"), "id=0x2a x=7\n");
+    assert_eq!(piped(&["grep"], ""), "");
+    assert!(piped(&["grep", "-e", "("], "x").starts_with("Error: Invalid pattern"));
+}
+
+#[test]
+fn all_bases_without_an_output_base() {
+    assert_eq!(
+        stdout(&["255"]),
+        "Decimal  255\nHex      0xff\nOctal    Ox377\nBinary   b11111111\nText     \"\\xff\"\n"
+    );
+    let rows = stdout(&["1", "zz"]);
+    assert_eq!(rows.lines().next(), Some("Input  Decimal  Hex  Octal  Binary  Text"));
+    assert_eq!(rows.lines().nth(2), Some("zz     Error: Failed to parse input"));
+}
+
+#[test]
+fn aliases_in_the_repl() {
+    assert_eq!(repl(":alias kb = * 1024\n4 kb\n:aliases\n:alias kb\n"), "4096\nkb = * 1024\nkb = * 1024\n");
+    assert_eq!(repl(":alias kb = * 1024\n:unalias kb\n:unalias kb\n"), "No alias named kb\n");
+    assert_eq!(repl(":alias 1x = 2\n"), "Usage: :alias <name> = <text>\n");
+}
+
+#[test]
+fn verbose_logs_go_to_stderr() {
+    assert_eq!(stderr(&["=16", "255"]), "");
+    let debug = stderr(&["-v", "=16", "255"]);
+    assert!(debug.contains("DEBUG raxrs: parsed input input=255 converted=0xff"), "{}", debug);
+    assert!(!debug.contains("TRACE"));
+    assert!(stderr(&["-vv", "=16", "255"]).contains("TRACE raxrs: converted literal from=0xff to=255"));
+    assert_eq!(stdout(&["-vv", "=16", "255"]), "0xff\n");
+}
+
+#[test]
+fn eval_reads_expressions_from_a_file() {
+    let path = std::env::temp_dir().join(format!("raxrs-cli-{}.rax", std::process::id()));
+    let file = path.to_str().unwrap();
+    std::fs::write(&path, "1 + 1\n# note\n\n2 * 3\n").unwrap();
+    assert_eq!(stdout(&["eval", "--file", file]), "2\n6\n");
+    std::fs::write(&path, "1 + 1\n1 +\n3\n").unwrap();
+    let output = rax(&["eval", "-f", file], "");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "2\n");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), format!("{}:2\n1 +\n  ^ Invalid expression\n", file));
+    assert_eq!(output.status.code(), Some(1));
+    // With --keep-going the failing line leaves a gap
+    let output = rax(&["eval", "-k", "-f", file], "");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "2\n\n3\n");
+    assert_eq!(output.status.code(), Some(1));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(piped(&["eval", "-f", "-"], "4 * 4\n"), "16\n");
+    assert!(stderr(&["eval", "-f", "/nonexistent/raxrs"]).starts_with("Error: Cannot read /nonexistent/raxrs"));
+}
+
+#[test]
+fn report_is_one_html_page() {
+    let page = stdout(&["report", "255", "1.5"]);
+    assert!(page.starts_with("<!DOCTYPE html>\n"));
+    assert!(page.trim_end().ends_with("</html>"));
+    assert!(page.contains("0xff") && page.contains("b11111111"));
+    // Floats also get their IEEE 754 fields
+    assert!(page.contains("xponent"));
+    // Nothing is fetched from elsewhere
+    assert!(!page.contains("<link") && !page.contains("src="));
+    let path = std::env::temp_dir().join(format!("raxrs-cli-{}.html", std::process::id()));
+    assert_eq!(stdout(&["report", "255", "1.5", "-o", path.to_str().unwrap()]), "");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), page);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn show_work_explains_each_conversion() {
+    assert_eq!(
+        stdout(&["--show-work", "=2", "5"]),
+        "5 to base 2 by repeated division:\n  5 ÷ 2 = 2 remainder 1\n  2 ÷ 2 = 1 remainder 0\n  \
+         1 ÷ 2 = 0 remainder 1\n  remainders read from the bottom up: 101\nb101\n"
+    );
+    assert_eq!(stdout(&["--show-work", "=10", "0x1f"]), "0x1f in base 16:\n  1×16^1 + f×16^0\n  = 16 + 15\n  = 31\n31\n");
+}
+
+#[test]
+fn schema_describes_structured_output() {
+    let schema: serde_json::Value = serde_json::from_str(&stdout(&["--schema"])).unwrap();
+    assert_eq!(schema["$schema"], "https://json-schema.org/draft/2020-12/schema");
+    assert_eq!(schema["title"], "rax conversions");
+    let output: serde_json::Value = serde_json::from_str(&stdout(&["--output", "json", "=16", "1"])).unwrap();
+    assert_eq!(output["schema_version"], 1);
+    for key in output.as_object().unwrap().keys() {
+        assert!(schema["properties"].get(key).is_some(), "{} is not in the schema", key);
+    }
+}
+
+#[test]
+fn hex_case_and_prefix() {
+    assert_eq!(stdout(&["--hex-case", "upper", "=16", "255"]), "0xFF\n");
+    assert_eq!(stdout(&["--hex-prefix", "$", "=16", "255"]), "$ff\n");
+    assert_eq!(stdout(&["--hex-prefix", "'h", "--hex-case", "upper", "=16", "255"]), "'hFF\n");
+    assert_eq!(stdout(&["--hex-prefix", "none", "=16", "48879"]), "beef\n");
+    assert!(!rax(&["--hex-prefix", "#", "=16", "1"], "").status.success());
+}
+
+#[test]
+fn long_binary_wraps_at_the_given_width() {
+    assert_eq!(stdout(&["--wrap", "12", "=2", "0xffff"]), "b11111111 \\\n 11111111\n");
+    assert_eq!(stdout(&["--wrap", "0", "=2", "0xffff"]), "b1111111111111111\n");
+    // Not a terminal, so output is not wrapped by default
+    assert_eq!(stdout(&["=2", "0xffffffff"]), format!("b{}\n", "1".repeat(32)));
+}